@@ -1,5 +1,7 @@
 use clap::AppSettings;
+use clap::Args;
 use clap::Parser;
+use clap::Subcommand;
 
 /// A programmable, configurable, and extensible API Gateway!
 #[derive(Parser, Debug, Clone)]
@@ -7,7 +9,7 @@ use clap::Parser;
 #[clap(global_setting(AppSettings::DisableVersionFlag))]
 pub struct DakiaArgs {
     /// Path to Dakia's local directory for storing configuration, interceptors, filters, extensions  and runtime data.
-    #[clap(long)]
+    #[clap(long, global = true)]
     pub dp: Option<String>,
 
     /// Watch for changes in configuration files, interceptors, filters and extensions and automatically apply updates.
@@ -38,7 +40,40 @@ pub struct DakiaArgs {
     pub debug: bool,
 
     /// Whether this server should try to upgrade from a running old server
-    /// It'll work only on linux platforms 
+    /// It'll work only on linux platforms
     #[clap(short, long)]
     pub upgrade: bool,
+
+    #[clap(subcommand)]
+    pub command: Option<DakiaCommand>,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum DakiaCommand {
+    /// Explain how a request would be handled by a gateway, without sending it to any upstream.
+    /// Shows matched downstream, result of each filter, selected router and interceptors of each phase.
+    Explain(ExplainArgs),
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct ExplainArgs {
+    /// Name of the gateway which receives the request, first gateway is used if not provided.
+    #[clap(long)]
+    pub gateway: Option<String>,
+
+    /// HTTP method of the request.
+    #[clap(long, default_value = "GET")]
+    pub method: String,
+
+    /// URL of the request, its host is sent as host header unless host header is provided.
+    #[clap(long)]
+    pub url: String,
+
+    /// Header of the request in "name: value" format, it can be repeated.
+    #[clap(short = 'H', long = "header")]
+    pub headers: Vec<String>,
+
+    /// Body of the request.
+    #[clap(long)]
+    pub data: Option<String>,
 }
@@ -9,7 +9,7 @@ use crate::{
     shared::into::IntoRef,
 };
 
-use super::{source_config::GatewayConfig, DakiaArgs};
+use super::{source_config::GatewayConfig, DakiaArgs, InetAddress};
 
 pub type ConfigVersion = i64;
 
@@ -31,6 +31,8 @@ pub struct DakiaConfig {
     pub upstream_connect_offload_threadpools: Option<usize>,
     pub upstream_connect_offload_thread_per_pool: Option<usize>,
     pub upstream_debug_ssl_keylog: bool,
+    // address to serve prometheus metrics, metrics are not exposed if not present
+    pub metrics_address: Option<InetAddress>,
     pub gateways: Vec<GatewayConfig>,
 }
 
@@ -53,6 +55,7 @@ impl Default for DakiaConfig {
             upstream_connect_offload_threadpools: Default::default(),
             upstream_connect_offload_thread_per_pool: Default::default(),
             upstream_debug_ssl_keylog: Default::default(),
+            metrics_address: Default::default(),
             gateways: Default::default(),
         }
     }
@@ -159,6 +162,7 @@ impl From<SourceDakiaRawConfig> for DakiaConfig {
             upstream_debug_ssl_keylog: source_dakia_raw_config
                 .upstream_debug_ssl_keylog
                 .unwrap_or(false),
+            metrics_address: source_dakia_raw_config.metrics_address,
             gateways: source_dakia_raw_config.gateways,
         }
     }
@@ -188,3 +192,38 @@ impl IntoRef<ServerConf> for DakiaConfig {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::gateway::filter::build_filter_registry;
+
+    use super::*;
+
+    #[test]
+    fn test_sample_config() {
+        let source_dakia_config: SourceDakiaRawConfig =
+            serde_yaml::from_str(include_str!("../../../docs/config.sample.yaml")).unwrap();
+        let dakia_config = DakiaConfig::from(source_dakia_config);
+        assert!(!dakia_config.gateways.is_empty());
+
+        for gateway_config in &dakia_config.gateways {
+            let filter_registry = build_filter_registry(&mut gateway_config.clone()).unwrap();
+            let filter_names = gateway_config
+                .interceptors
+                .iter()
+                .filter_map(|interceptor_config| interceptor_config.filter.as_ref())
+                .chain(
+                    gateway_config
+                        .routers
+                        .iter()
+                        .filter_map(|router_config| router_config.filter.as_ref()),
+                );
+            for filter_name in filter_names {
+                assert!(
+                    filter_registry.get(filter_name).is_some(),
+                    "filter {filter_name} is not declared"
+                );
+            }
+        }
+    }
+}
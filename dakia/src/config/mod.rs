@@ -3,6 +3,6 @@ mod dakia_config;
 mod upstream;
 
 pub mod source_config;
-pub use args::DakiaArgs;
+pub use args::{DakiaArgs, DakiaCommand, ExplainArgs};
 pub use dakia_config::*;
 pub use source_config::InetAddress;
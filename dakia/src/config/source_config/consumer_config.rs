@@ -0,0 +1,13 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+// api key itself is never stored in config, only hex encoded sha256 hash of it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConsumerConfig {
+    pub name: String,
+    pub key_hash: String,
+    pub group: Option<String>,
+    #[serde(default)]
+    pub metadata: HashMap<String, String>,
+}
@@ -1,9 +1,15 @@
 use serde;
 
+use super::{interceptor_config::InterceptorConfig, RouterConfig};
+
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
 pub struct DownstreamConfig {
     pub host: String,
     pub port: Option<u16>,
+    // routing table of the host, routers of gateway are used if it's not present
+    pub routers: Option<Vec<RouterConfig>>,
+    // executed after interceptors of gateway, only for requests of the host
+    pub interceptors: Option<Vec<InterceptorConfig>>,
 }
 
 impl DownstreamConfig {
@@ -1,11 +1,12 @@
 use crate::error::BError;
 use crate::error::DakiaError;
 use crate::error::DakiaResult;
-use crate::gateway::filter::exec_filter;
+use crate::gateway::filter::exec_named_filter;
 use crate::proxy::http::Session;
 use crate::qe::query::Query;
 
 use super::interceptor_config::InterceptorConfig;
+use super::ConsumerConfig;
 use super::DownstreamConfig;
 use super::InetAddress;
 use super::RouterConfig;
@@ -27,15 +28,25 @@ pub struct GatewayConfig {
 
     #[serde(default)]
     pub filters: Vec<Query>,
+
+    // consumers identified by api key, consumers of file are added to these
+    #[serde(default)]
+    pub consumers: Vec<ConsumerConfig>,
+    pub consumers_file: Option<String>,
 }
 
 pub fn find_router_config<'a>(session: &'a Session<'a>) -> DakiaResult<Option<&'a RouterConfig>> {
-    for router_config in session.ctx().gateway_state.gateway_config().routers.iter() {
+    let ctx = session.ctx();
+    let router_configs = ctx.gateway_state.gateway_config().routers(ctx.ds_index);
+    let router_index = ctx.gateway_state.router_index(ctx.ds_index);
+
+    // routers which can't match the path are skipped, rest are evaluated in router order
+    for router_position in router_index.candidates(session.ds_req_path()) {
+        let router_config = &router_configs[router_position];
         match &router_config.filter {
             None => return Ok(Some(router_config)), // if no filter present for any router then it'll be considered a match when encountered
             Some(filter_name) => {
-                let filter = session.ctx().gateway_state.filter_or_err(&filter_name)?;
-                let is_matched = exec_filter(filter, session)?;
+                let is_matched = exec_named_filter(filter_name, session)?;
                 if is_matched {
                     return Ok(Some(router_config));
                 }
@@ -53,6 +64,14 @@ pub fn find_router_config_or_err<'a>(session: &'a Session<'a>) -> DakiaResult<&'
 }
 
 impl GatewayConfig {
+    // routers of downstream matched by host of request take precedence over routers of gateway
+    pub fn routers(&self, ds_index: Option<usize>) -> &Vec<RouterConfig> {
+        ds_index
+            .and_then(|ds_index| self.downstreams.get(ds_index))
+            .and_then(|downstream_config| downstream_config.routers.as_ref())
+            .unwrap_or(&self.routers)
+    }
+
     pub fn find_default_upstream(&self) -> Option<&UpstreamConfig> {
         self.upstreams
             .iter()
@@ -103,6 +122,8 @@ impl Default for GatewayConfig {
             routers: Default::default(),
             interceptors: Default::default(),
             filters: Default::default(),
+            consumers: Default::default(),
+            consumers_file: Default::default(),
         }
     }
 }
@@ -1,3 +1,4 @@
+mod consumer_config;
 mod downstream_config;
 mod gateway_config;
 mod inet_address;
@@ -5,12 +6,13 @@ mod interceptor_config;
 mod router_config;
 mod upstream_config;
 
+pub use consumer_config::ConsumerConfig;
 pub use downstream_config::DownstreamConfig;
-pub use gateway_config::find_router_config_or_err;
 pub use gateway_config::GatewayConfig;
+pub use gateway_config::{find_router_config, find_router_config_or_err};
 pub use inet_address::InetAddress;
 pub use interceptor_config::*;
-pub use router_config::RouterConfig;
+pub use router_config::{RouterConfig, WeightedUpstreamConfig};
 pub use upstream_config::*;
 mod source_dakia_config;
 
@@ -1,7 +1,49 @@
 use serde::{Deserialize, Serialize};
 
+use crate::error::{DakiaError, DakiaResult};
+
+use super::AffinityConfig;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WeightedUpstreamConfig {
+    pub name: String,
+    pub weight: u32,
+}
+
+// copy of sampled requests is sent to mirror upstream, its response is discarded
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MirrorConfig {
+    pub upstream: String,
+    // percentage of matching requests to mirror, all requests are mirrored if not provided
+    pub percentage: Option<u8>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RouterConfig {
     pub filter: Option<String>,
-    pub upstream: String,
+    // routers with higher priority are matched first, routers with same priority are matched in declaration order
+    pub priority: Option<i32>,
+    // either upstream or upstreams is required, upstreams splits traffic as per their weight
+    pub upstream: Option<String>,
+    pub upstreams: Option<Vec<WeightedUpstreamConfig>>,
+    // keeps a client on the same upstream of split
+    pub affinity: Option<AffinityConfig>,
+    pub mirror: Option<MirrorConfig>,
+    // removed from path of upstream request
+    pub strip_prefix: Option<String>,
+    // path of upstream request, {name} is replaced with named capture of $matches path filter of router
+    pub rewrite_path: Option<String>,
+}
+
+impl RouterConfig {
+    // rewrite_path replaces whole path, so prefix stripped before it would be silently ignored
+    pub fn validate(&self) -> DakiaResult<()> {
+        if self.rewrite_path.is_some() && self.strip_prefix.is_some() {
+            return Err(DakiaError::i_explain(format!(
+                "rewrite_path and strip_prefix can not be used together in router of filter {:?}",
+                self.filter
+            )));
+        }
+        Ok(())
+    }
 }
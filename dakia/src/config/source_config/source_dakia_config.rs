@@ -5,7 +5,7 @@ use crate::{
     error::{DakiaError, DakiaResult, ImmutStr},
 };
 
-use super::GatewayConfig;
+use super::{GatewayConfig, InetAddress};
 
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
 pub struct SourceDakiaRawConfig {
@@ -24,6 +24,7 @@ pub struct SourceDakiaRawConfig {
     pub upstream_connect_offload_threadpools: Option<usize>,
     pub upstream_connect_offload_thread_per_pool: Option<usize>,
     pub upstream_debug_ssl_keylog: Option<bool>,
+    pub metrics_address: Option<InetAddress>,
     pub gateways: Vec<GatewayConfig>,
 }
 
@@ -46,6 +47,7 @@ impl Default for SourceDakiaRawConfig {
             upstream_connect_offload_threadpools: None,
             upstream_debug_ssl_keylog: None,
             upstream_keepalive_pool_size: None,
+            metrics_address: None,
             gateways: vec![],
         }
     }
@@ -70,6 +72,7 @@ impl From<DakiaConfig> for SourceDakiaRawConfig {
             upstream_connect_offload_threadpools: dakia_config.upstream_connect_offload_threadpools,
             upstream_debug_ssl_keylog: Some(dakia_config.upstream_debug_ssl_keylog),
             upstream_keepalive_pool_size: Some(dakia_config.upstream_keepalive_pool_size),
+            metrics_address: dakia_config.metrics_address,
             gateways: dakia_config.gateways,
         }
     }
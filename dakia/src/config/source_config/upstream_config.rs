@@ -26,14 +26,82 @@ pub struct UpstreamNodeConfig {
     pub tls: bool,
     pub sni: Option<String>,
     pub weight: Option<u16>,
+    // draining node doesn't receive new requests, it can be removed once in-flight requests are finished
+    pub drain: Option<bool>,
+}
+
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct CircuitBreakerConfig {
+    // percentage (0-100) of failed requests within a window that opens the circuit
+    pub error_rate_threshold: Option<u8>,
+    // response time in milliseconds, slower responses are counted as failures
+    pub latency_threshold: Option<u64>,
+    // minimum number of requests within a window before error rate is evaluated
+    pub min_requests: Option<u32>,
+    // length of the observation window in milliseconds
+    pub window: Option<u64>,
+    // time in milliseconds for which circuit stays open before allowing probe requests
+    pub open_timeout: Option<u64>,
+    // number of probe requests allowed while circuit is half open
+    pub half_open_max_requests: Option<u32>,
+    // upstream to use while circuit is open, requests fail with 503 if not present
+    pub fallback_upstream: Option<String>,
+}
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DnsRecordType {
+    Srv,
+    A,
+}
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct DiscoveryConfig {
+    // dns name to resolve upstream nodes from
+    pub dns: Option<String>,
+    #[serde(rename = "type")]
+    pub record_type: Option<DnsRecordType>,
+    // port of upstream nodes, required for A records as they don't carry port
+    pub port: Option<u16>,
+    // json or yaml file containing list of upstream nodes
+    pub file: Option<String>,
+    // interval to re-resolve upstream nodes, e.g. 10s, 500ms
+    pub refresh: Option<String>,
+    // tls and sni of upstream nodes resolved from dns
+    pub tls: Option<bool>,
+    pub sni: Option<String>,
+}
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct AffinityCookieConfig {
+    pub name: String,
+    // key of mac identifying node in cookie, random secret of process is used if not provided
+    pub secret: Option<String>,
+    // seconds, session cookie is issued if not provided
+    pub ttl: Option<u64>,
+    pub path: Option<String>,
+}
+
+// requests carrying the same affinity key are sent to the same upstream node while it's healthy
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct AffinityConfig {
+    pub cookie: Option<AffinityCookieConfig>,
+    // name of the request header holding affinity key
+    pub header: Option<String>,
 }
 
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
 pub struct UpstreamConfig {
     pub name: String,
     pub default: bool,
+    #[serde(default)]
     pub upstream_nodes: Vec<UpstreamNodeConfig>,
+    pub discovery: Option<DiscoveryConfig>,
     pub traffic_distribution_policy: Option<TrafficDistributionPolicy>,
+    pub circuit_breaker: Option<CircuitBreakerConfig>,
+    pub affinity: Option<AffinityConfig>,
+    // ms, effective weight of a joining node ramps up from zero to its weight within this window
+    pub slow_start: Option<u64>,
 }
 impl UpstreamConfig {
     pub fn find_upstream_node_config(&self, address: InetAddress) -> Option<&UpstreamNodeConfig> {
@@ -26,8 +26,7 @@ impl From<pingora_core::Error> for Error {
 
 impl From<Box<pingora::Error>> for Box<Error> {
     fn from(err: Box<pingora_core::Error>) -> Self {
-        // TODO: format this error
-        err.into()
+        Box::new(Error::PingoraError(*err))
     }
 }
 
@@ -50,10 +49,15 @@ impl From<Box<Error>> for Box<pingora_core::Error> {
             Error::PingoraError(pe) => Box::new(pe),
             Error::DakiaError(de) => {
                 // TODO: handle translation between pingora and dakia error
-                // pass status code correctly
                 // currently, it'll just print error message. Which is enough for debugging for now...
+                let etype = match de.etype {
+                    super::ErrorType::ProxyError(status_code) => {
+                        pingora::ErrorType::HTTPStatus(status_code)
+                    }
+                    _ => pingora::ErrorType::InternalError,
+                };
                 let error_msg = de.to_string();
-                let pe = pingora_core::Error::explain(pingora::ErrorType::InternalError, error_msg);
+                let pe = pingora_core::Error::explain(etype, error_msg);
                 pe
             }
             // TODO: implement conversion for other errors
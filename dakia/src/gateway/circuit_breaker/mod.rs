@@ -0,0 +1,363 @@
+use std::{
+    fmt,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use log::{info, warn};
+
+use crate::{
+    config::source_config::{CircuitBreakerConfig, GatewayConfig},
+    gateway::state::GatewayState,
+    shared::{
+        metrics::{CIRCUIT_BREAKER_STATE, CIRCUIT_BREAKER_TRANSITIONS},
+        mutable_registry::Registry,
+    },
+};
+
+const DEFAULT_ERROR_RATE_THRESHOLD: u8 = 50;
+const DEFAULT_MIN_REQUESTS: u32 = 20;
+const DEFAULT_WINDOW: u64 = 10_000;
+const DEFAULT_OPEN_TIMEOUT: u64 = 30_000;
+const DEFAULT_HALF_OPEN_MAX_REQUESTS: u32 = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+    Closed,
+    Open,
+    HalfOpen,
+}
+
+impl CircuitState {
+    fn metric_value(&self) -> i64 {
+        match self {
+            CircuitState::Closed => 0,
+            CircuitState::Open => 1,
+            CircuitState::HalfOpen => 2,
+        }
+    }
+}
+
+impl fmt::Display for CircuitState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let state_str = match self {
+            CircuitState::Closed => "closed",
+            CircuitState::Open => "open",
+            CircuitState::HalfOpen => "half_open",
+        };
+        write!(f, "{}", state_str)
+    }
+}
+
+struct CircuitStats {
+    state: CircuitState,
+    window_started_at: Instant,
+    total_requests: u32,
+    failed_requests: u32,
+    opened_at: Instant,
+    probes_in_flight: u32,
+    successful_probes: u32,
+}
+
+pub struct CircuitBreaker {
+    gateway_name: String,
+    upstream_name: String,
+    error_rate_threshold: u8,
+    latency_threshold: Option<Duration>,
+    min_requests: u32,
+    window: Duration,
+    open_timeout: Duration,
+    half_open_max_requests: u32,
+    fallback_upstream: Option<String>,
+    stats: Mutex<CircuitStats>,
+}
+
+impl CircuitBreaker {
+    pub fn build(gateway_name: &str, upstream_name: &str, config: &CircuitBreakerConfig) -> Self {
+        let now = Instant::now();
+        let circuit_breaker = Self {
+            gateway_name: gateway_name.to_string(),
+            upstream_name: upstream_name.to_string(),
+            error_rate_threshold: config
+                .error_rate_threshold
+                .unwrap_or(DEFAULT_ERROR_RATE_THRESHOLD),
+            latency_threshold: config.latency_threshold.map(Duration::from_millis),
+            min_requests: config.min_requests.unwrap_or(DEFAULT_MIN_REQUESTS),
+            window: Duration::from_millis(config.window.unwrap_or(DEFAULT_WINDOW)),
+            open_timeout: Duration::from_millis(
+                config.open_timeout.unwrap_or(DEFAULT_OPEN_TIMEOUT),
+            ),
+            half_open_max_requests: config
+                .half_open_max_requests
+                .unwrap_or(DEFAULT_HALF_OPEN_MAX_REQUESTS)
+                .max(1),
+            fallback_upstream: config.fallback_upstream.clone(),
+            stats: Mutex::new(CircuitStats {
+                state: CircuitState::Closed,
+                window_started_at: now,
+                total_requests: 0,
+                failed_requests: 0,
+                opened_at: now,
+                probes_in_flight: 0,
+                successful_probes: 0,
+            }),
+        };
+
+        CIRCUIT_BREAKER_STATE
+            .with_label_values(&[gateway_name, upstream_name])
+            .set(CircuitState::Closed.metric_value());
+
+        circuit_breaker
+    }
+
+    pub fn fallback_upstream(&self) -> &Option<String> {
+        &self.fallback_upstream
+    }
+
+    fn transition(&self, stats: &mut CircuitStats, to: CircuitState) {
+        let from = stats.state;
+        let now = Instant::now();
+
+        stats.state = to;
+        stats.window_started_at = now;
+        stats.total_requests = 0;
+        stats.failed_requests = 0;
+        stats.probes_in_flight = 0;
+        stats.successful_probes = 0;
+
+        if to == CircuitState::Open {
+            stats.opened_at = now;
+            warn!(
+                "circuit breaker of upstream {} in gateway {} changed state from {} to {}",
+                self.upstream_name, self.gateway_name, from, to
+            );
+        } else {
+            info!(
+                "circuit breaker of upstream {} in gateway {} changed state from {} to {}",
+                self.upstream_name, self.gateway_name, from, to
+            );
+        }
+
+        CIRCUIT_BREAKER_STATE
+            .with_label_values(&[&self.gateway_name, &self.upstream_name])
+            .set(to.metric_value());
+        CIRCUIT_BREAKER_TRANSITIONS
+            .with_label_values(&[
+                &self.gateway_name,
+                &self.upstream_name,
+                &from.to_string(),
+                &to.to_string(),
+            ])
+            .inc();
+    }
+
+    // returns true if a request is allowed to be sent to upstream, every allowed request must be followed by a call to record or release
+    pub fn allow_request(&self) -> bool {
+        let mut stats = self.stats.lock().unwrap();
+        match stats.state {
+            CircuitState::Closed => true,
+            CircuitState::Open => {
+                if stats.opened_at.elapsed() < self.open_timeout {
+                    return false;
+                }
+
+                self.transition(&mut stats, CircuitState::HalfOpen);
+                stats.probes_in_flight = 1;
+                true
+            }
+            CircuitState::HalfOpen => {
+                if stats.probes_in_flight + stats.successful_probes >= self.half_open_max_requests {
+                    return false;
+                }
+
+                stats.probes_in_flight += 1;
+                true
+            }
+        }
+    }
+
+    pub fn is_failure(&self, status_code: Option<u16>, latency: Option<Duration>) -> bool {
+        let is_slow = match (self.latency_threshold, latency) {
+            (Some(latency_threshold), Some(latency)) => latency > latency_threshold,
+            _ => false,
+        };
+
+        is_slow || status_code.is_some_and(|status_code| status_code >= 500)
+    }
+
+    pub fn record(&self, is_success: bool) {
+        let mut stats = self.stats.lock().unwrap();
+        match stats.state {
+            CircuitState::Closed => {
+                if stats.window_started_at.elapsed() > self.window {
+                    stats.window_started_at = Instant::now();
+                    stats.total_requests = 0;
+                    stats.failed_requests = 0;
+                }
+
+                stats.total_requests += 1;
+                if !is_success {
+                    stats.failed_requests += 1;
+                }
+
+                let is_threshold_crossed = stats.failed_requests as u64 * 100
+                    >= self.error_rate_threshold as u64 * stats.total_requests as u64;
+
+                if stats.total_requests >= self.min_requests && is_threshold_crossed {
+                    self.transition(&mut stats, CircuitState::Open);
+                }
+            }
+            CircuitState::HalfOpen => {
+                stats.probes_in_flight = stats.probes_in_flight.saturating_sub(1);
+
+                if !is_success {
+                    self.transition(&mut stats, CircuitState::Open);
+                    return;
+                }
+
+                stats.successful_probes += 1;
+                if stats.successful_probes >= self.half_open_max_requests {
+                    self.transition(&mut stats, CircuitState::Closed);
+                }
+            }
+            // responses of requests which were sent before circuit got opened
+            CircuitState::Open => {}
+        }
+    }
+
+    // gives back an allowed request which never reached upstream, without recording its outcome
+    pub fn release(&self) {
+        let mut stats = self.stats.lock().unwrap();
+        if stats.state == CircuitState::HalfOpen {
+            stats.probes_in_flight = stats.probes_in_flight.saturating_sub(1);
+        }
+    }
+}
+
+// circuit breaker of current state is kept if its config is unchanged, so that rebuilding state doesn't close open circuits
+pub fn build_circuit_breaker_registry(
+    gateway_config: &GatewayConfig,
+    current_state: Option<&GatewayState>,
+) -> Registry<Arc<CircuitBreaker>> {
+    let mut registry: Registry<Arc<CircuitBreaker>> = Registry::build();
+
+    for upstream_config in &gateway_config.upstreams {
+        if let Some(circuit_breaker_config) = &upstream_config.circuit_breaker {
+            let current_circuit_breaker = current_state.and_then(|current_state| {
+                let is_unchanged = current_state
+                    .gateway_config()
+                    .upstreams
+                    .iter()
+                    .find(|current_upstream_config| {
+                        current_upstream_config.name == upstream_config.name
+                    })
+                    .is_some_and(|current_upstream_config| {
+                        current_upstream_config.circuit_breaker.as_ref()
+                            == Some(circuit_breaker_config)
+                    });
+                is_unchanged
+                    .then(|| current_state.circuit_breaker(&upstream_config.name))
+                    .flatten()
+            });
+            if let Some(circuit_breaker) = current_circuit_breaker {
+                registry.add(upstream_config.name.clone(), circuit_breaker.clone());
+                continue;
+            }
+
+            let circuit_breaker = CircuitBreaker::build(
+                &gateway_config.name,
+                &upstream_config.name,
+                circuit_breaker_config,
+            );
+            registry.add(upstream_config.name.clone(), Arc::new(circuit_breaker));
+        }
+    }
+
+    registry
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(cb: &CircuitBreaker) -> CircuitState {
+        cb.stats.lock().unwrap().state
+    }
+
+    fn circuit_breaker() -> CircuitBreaker {
+        let config = CircuitBreakerConfig {
+            error_rate_threshold: Some(50),
+            latency_threshold: Some(100),
+            min_requests: Some(4),
+            window: Some(60_000),
+            open_timeout: Some(0),
+            half_open_max_requests: Some(2),
+            fallback_upstream: None,
+        };
+        CircuitBreaker::build("test", "payment", &config)
+    }
+
+    #[test]
+    fn test_opens_after_error_rate_threshold() {
+        let cb = circuit_breaker();
+        cb.record(true);
+        cb.record(false);
+        cb.record(true);
+        assert_eq!(state(&cb), CircuitState::Closed);
+        cb.record(false);
+        assert_eq!(state(&cb), CircuitState::Open);
+    }
+
+    #[test]
+    fn test_half_open_probes() {
+        let cb = circuit_breaker();
+        for _ in 0..4 {
+            cb.record(false);
+        }
+        assert_eq!(state(&cb), CircuitState::Open);
+
+        // open timeout is zero, so first request moves circuit to half open
+        assert!(cb.allow_request());
+        assert_eq!(state(&cb), CircuitState::HalfOpen);
+        assert!(cb.allow_request());
+        assert!(!cb.allow_request());
+
+        cb.record(true);
+        cb.record(true);
+        assert_eq!(state(&cb), CircuitState::Closed);
+    }
+
+    #[test]
+    fn test_failed_probe_reopens() {
+        let cb = circuit_breaker();
+        for _ in 0..4 {
+            cb.record(false);
+        }
+        assert!(cb.allow_request());
+        cb.record(false);
+        assert_eq!(state(&cb), CircuitState::Open);
+    }
+
+    #[test]
+    fn test_released_probe() {
+        let cb = circuit_breaker();
+        for _ in 0..4 {
+            cb.record(false);
+        }
+        assert!(cb.allow_request());
+        assert!(cb.allow_request());
+        assert!(!cb.allow_request());
+
+        cb.release();
+        assert_eq!(state(&cb), CircuitState::HalfOpen);
+        assert!(cb.allow_request());
+    }
+
+    #[test]
+    fn test_is_failure() {
+        let cb = circuit_breaker();
+        assert!(cb.is_failure(Some(503), None));
+        assert!(cb.is_failure(Some(200), Some(Duration::from_millis(101))));
+        assert!(!cb.is_failure(Some(404), Some(Duration::from_millis(10))));
+    }
+}
@@ -0,0 +1,104 @@
+use std::{collections::HashMap, sync::Arc};
+
+use sha2::{Digest, Sha256};
+
+use crate::{
+    config::source_config::{ConsumerConfig, GatewayConfig},
+    error::{DakiaError, DakiaResult},
+    shared::mutable_registry::Registry,
+};
+
+// client of gateway identified by its api key
+#[derive(Debug)]
+pub struct Consumer {
+    pub name: String,
+    pub group: Option<String>,
+    pub metadata: HashMap<String, String>,
+}
+
+pub fn hash_api_key(api_key: &str) -> String {
+    hex::encode(Sha256::digest(api_key.as_bytes()))
+}
+
+fn read_consumers_file(consumers_file: &str) -> DakiaResult<Vec<ConsumerConfig>> {
+    let consumers = std::fs::read_to_string(consumers_file).map_err(|e| {
+        DakiaError::i_explain(format!(
+            "failed to read consumers file {consumers_file} - {e}"
+        ))
+    })?;
+
+    serde_yaml::from_str(&consumers).map_err(|e| {
+        DakiaError::i_explain(format!("invalid consumers file {consumers_file} - {e}"))
+    })
+}
+
+// consumers are indexed by hash of their api key
+pub fn build_consumer_registry(
+    gateway_config: &GatewayConfig,
+) -> DakiaResult<Registry<Arc<Consumer>>> {
+    let mut consumer_configs = gateway_config.consumers.clone();
+    if let Some(consumers_file) = &gateway_config.consumers_file {
+        consumer_configs.extend(read_consumers_file(consumers_file)?);
+    }
+
+    let mut registry: Registry<Arc<Consumer>> = Registry::build();
+    for consumer_config in consumer_configs {
+        let key_hash = consumer_config.key_hash.to_ascii_lowercase();
+        if key_hash.len() != 64 || !key_hash.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+            return Err(DakiaError::i_explain(format!(
+                "key_hash of consumer {} must be hex encoded sha256 hash",
+                consumer_config.name
+            )));
+        }
+
+        if registry.get(&key_hash).is_some() {
+            return Err(DakiaError::i_explain(format!(
+                "key_hash of consumer {} is used by another consumer",
+                consumer_config.name
+            )));
+        }
+
+        let consumer = Consumer {
+            name: consumer_config.name,
+            group: consumer_config.group,
+            metadata: consumer_config.metadata,
+        };
+        registry.add(key_hash, Arc::new(consumer));
+    }
+
+    Ok(registry)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_consumer_registry() {
+        let gateway_config = GatewayConfig {
+            consumers: serde_yaml::from_str(
+                r#"
+                - name: mobile
+                  key_hash: 2CF24DBA5FB0A30E26E83B2AC5B9E29E1B161E5C1FA7425E73043362938B9824
+                  group: partners
+                  metadata:
+                    plan: gold
+                "#,
+            )
+            .unwrap(),
+            ..Default::default()
+        };
+
+        let registry = build_consumer_registry(&gateway_config).unwrap();
+        let consumer = registry.get(&hash_api_key("hello")).unwrap();
+        assert_eq!(consumer.name, "mobile");
+        assert_eq!(consumer.metadata["plan"], "gold");
+        assert!(registry.get(&hash_api_key("hello!")).is_none());
+
+        let mut gateway_config = gateway_config;
+        gateway_config
+            .consumers
+            .push(gateway_config.consumers[0].clone());
+        assert!(build_consumer_registry(&gateway_config).is_err());
+    }
+}
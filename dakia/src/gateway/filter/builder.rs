@@ -1,20 +1,184 @@
+use std::collections::HashMap;
+
 use crate::{
-    config::source_config::GatewayConfig, error::DakiaResult, gateway::filter::query2filter,
-    qe::query::extract_key_str_or_err, shared::mutable_registry::Registry,
+    config::source_config::GatewayConfig,
+    error::{DakiaError, DakiaResult},
+    gateway::filter::query2filter,
+    qe::query::extract_key_str_or_err,
+    shared::mutable_registry::Registry,
+};
+
+use super::{
+    operator::{FilterCriteria, LogicalFilterCriteria},
+    Filter,
 };
 
-use super::Filter;
+// filters which are being resolved are kept in stack, a reference to any of them is a cycle
+struct FilterResolver<'a> {
+    unresolved_filters: &'a HashMap<String, Filter>,
+    resolved_filters: HashMap<String, Filter>,
+    stack: Vec<String>,
+}
+
+impl<'a> FilterResolver<'a> {
+    fn resolve_filter(&mut self, filter_name: &str) -> DakiaResult<Filter> {
+        if let Some(filter) = self.resolved_filters.get(filter_name) {
+            return Ok(filter.clone());
+        }
+
+        if self.stack.iter().any(|name| name == filter_name) {
+            return Err(DakiaError::i_explain(format!(
+                "cyclic filter reference {} -> {filter_name}",
+                self.stack.join(" -> ")
+            )));
+        }
+
+        let unresolved_filter =
+            self.unresolved_filters
+                .get(filter_name)
+                .ok_or(DakiaError::i_explain(format!(
+                    "referenced filter {filter_name} not found"
+                )))?;
+
+        self.stack.push(filter_name.to_string());
+        let mut criteria_list: Vec<FilterCriteria> = vec![];
+        for criteria in &unresolved_filter.criteria_list {
+            criteria_list.push(self.resolve_criteria(criteria)?);
+        }
+        self.stack.pop();
+
+        let filter = Filter { criteria_list };
+        self.resolved_filters
+            .insert(filter_name.to_string(), filter.clone());
+        Ok(filter)
+    }
+
+    fn resolve_criteria_list(
+        &mut self,
+        criteria_list: &[FilterCriteria],
+    ) -> DakiaResult<Vec<FilterCriteria>> {
+        criteria_list
+            .iter()
+            .map(|criteria| self.resolve_criteria(criteria))
+            .collect()
+    }
+
+    fn resolve_criteria(&mut self, criteria: &FilterCriteria) -> DakiaResult<FilterCriteria> {
+        let resolved_criteria = match criteria {
+            FilterCriteria::Logical(LogicalFilterCriteria::And(criteria_list)) => {
+                FilterCriteria::Logical(LogicalFilterCriteria::And(
+                    self.resolve_criteria_list(criteria_list)?,
+                ))
+            }
+            FilterCriteria::Logical(LogicalFilterCriteria::Or(criteria_list)) => {
+                FilterCriteria::Logical(LogicalFilterCriteria::Or(
+                    self.resolve_criteria_list(criteria_list)?,
+                ))
+            }
+            FilterCriteria::Logical(LogicalFilterCriteria::Not(criteria)) => {
+                FilterCriteria::Logical(LogicalFilterCriteria::Not(Box::new(
+                    self.resolve_criteria(criteria)?,
+                )))
+            }
+            FilterCriteria::PartFilterCriteria(_) => criteria.clone(),
+            // criterias of referenced filter are combined with and, same as top level criterias of a filter
+            FilterCriteria::Reference(filter_name) => {
+                let filter = self.resolve_filter(filter_name)?;
+                FilterCriteria::Logical(LogicalFilterCriteria::And(filter.criteria_list))
+            }
+        };
+
+        Ok(resolved_criteria)
+    }
+}
 
 pub fn build_filter_registry(gateway_config: &mut GatewayConfig) -> DakiaResult<Registry<Filter>> {
-    let mut registry: Registry<Filter> = Registry::build();
+    let mut unresolved_filters: HashMap<String, Filter> = HashMap::new();
+    let mut filter_names: Vec<String> = vec![];
 
     for filter_config in &mut gateway_config.filters {
         let filter_name = extract_key_str_or_err(&filter_config, "name")?.to_string();
         filter_config.remove("name");
 
         let filter = query2filter(filter_config)?;
+        filter_names.push(filter_name.clone());
+        unresolved_filters.insert(filter_name, filter);
+    }
+
+    // references are resolved once all filters are parsed, as a filter can reference filters declared after it
+    let mut filter_resolver = FilterResolver {
+        unresolved_filters: &unresolved_filters,
+        resolved_filters: HashMap::new(),
+        stack: vec![],
+    };
+
+    let mut registry: Registry<Filter> = Registry::build();
+    for filter_name in filter_names {
+        let filter = filter_resolver.resolve_filter(&filter_name)?;
         registry.add(filter_name, filter);
     }
 
     Ok(registry)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build_gateway_config(yaml: &str) -> GatewayConfig {
+        GatewayConfig {
+            filters: serde_yaml::from_str(yaml).unwrap(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_filter_reference() {
+        let mut gateway_config = build_gateway_config(
+            r#"
+            - name: admin_route
+              $all_of:
+                - is_internal_client
+                - is_admin_path
+            - name: is_internal_client
+              client.ip:
+                $in_cidr:
+                  - 10.0.0.0/8
+            - name: is_admin_path
+              $filter: is_api_host
+              path:
+                $starts_with: /admin
+            - name: is_api_host
+              host: api.example.com
+            "#,
+        );
+
+        let registry = build_filter_registry(&mut gateway_config).unwrap();
+        let admin_route = registry.get("admin_route").unwrap();
+        assert_eq!(admin_route.part_filter_criterias(true).len(), 3);
+    }
+
+    #[test]
+    fn test_cyclic_filter_reference() {
+        let mut gateway_config = build_gateway_config(
+            r#"
+            - name: a
+              $filter: b
+            - name: b
+              $any_of:
+                - c
+            - name: c
+              $filter: a
+            "#,
+        );
+        assert!(build_filter_registry(&mut gateway_config).is_err());
+
+        let mut gateway_config = build_gateway_config(
+            r#"
+            - name: a
+              $filter: missing
+            "#,
+        );
+        assert!(build_filter_registry(&mut gateway_config).is_err());
+    }
+}
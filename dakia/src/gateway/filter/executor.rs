@@ -1,17 +1,24 @@
+use std::{collections::HashMap, net::IpAddr};
+
 use log::trace;
+use serde_json::Value;
 
 use crate::{
-    error::DakiaResult,
+    error::{DakiaError, DakiaResult},
     gateway::filter::operator::{
-        FilterCriteria, LogicalCriteriaOperator, LogicalFilterCriteria, PartFilterCriteria,
-        PatternOperator, RelationalOperator, SetOperator,
+        CidrOperator, ComparisonOperator, FilterCriteria, LengthOperator, LogicalCriteriaOperator,
+        LogicalFilterCriteria, PartFilterCriteria, PatternOperator, RelationalOperator,
+        SetOperator,
     },
     proxy::http::Session,
-    shared::pattern_matcher::PatternMatcher,
+    shared::pattern_matcher::{PatternMatcher, Pcre2PatternMatcher},
 };
 
 use super::{
-    operator::{CriteriaOperator, HeaderCriteria, PartCriteriaOperator},
+    operator::{
+        BodyJsonCriteria, CookieCriteria, CriteriaOperator, HeaderCriteria, JwtClaimCriteria,
+        PartCriteriaOperator, QueryCriteria,
+    },
     Filter,
 };
 
@@ -35,6 +42,32 @@ fn match_critera_operator(operator: &CriteriaOperator, value: Option<&[u8]>) ->
                 Some(value) => Ok(value != qval),
                 None => Ok(false),
             },
+            RelationalOperator::IEq(qval) => match value {
+                Some(value) => Ok(value.eq_ignore_ascii_case(qval)),
+                None => Ok(false),
+            },
+        },
+        CriteriaOperator::Comparison(comparison_operator) => {
+            let number = value
+                .and_then(|value| std::str::from_utf8(value).ok())
+                .and_then(|value| value.trim().parse::<f64>().ok());
+
+            match number {
+                Some(number) => Ok(match comparison_operator {
+                    ComparisonOperator::Gt(qval) => number > *qval,
+                    ComparisonOperator::Gte(qval) => number >= *qval,
+                    ComparisonOperator::Lt(qval) => number < *qval,
+                    ComparisonOperator::Lte(qval) => number <= *qval,
+                }),
+                None => Ok(false),
+            }
+        }
+        CriteriaOperator::Length(length_operator) => match value {
+            Some(value) => Ok(match length_operator {
+                LengthOperator::Gt(qval) => value.len() > *qval,
+                LengthOperator::Lt(qval) => value.len() < *qval,
+            }),
+            None => Ok(false),
         },
         CriteriaOperator::Pattern(pattern_operator) => match pattern_operator {
             PatternOperator::Contains(qval) => match value {
@@ -45,6 +78,10 @@ fn match_critera_operator(operator: &CriteriaOperator, value: Option<&[u8]>) ->
                 Some(value) => Ok(!contains_slice(value, &qval)),
                 None => Ok(false),
             },
+            PatternOperator::IContains(qval) => match value {
+                Some(value) => Ok(contains_slice(&value.to_ascii_lowercase(), qval)),
+                None => Ok(false),
+            },
             PatternOperator::StartsWith(qval) => match value {
                 Some(value) => Ok(value.starts_with(&qval)),
                 None => Ok(false),
@@ -93,6 +130,17 @@ fn match_critera_operator(operator: &CriteriaOperator, value: Option<&[u8]>) ->
             },
             None => Ok(false),
         },
+        CriteriaOperator::Cidr(cidr_operator) => {
+            let ip = value
+                .and_then(|value| std::str::from_utf8(value).ok())
+                .and_then(|value| value.trim().parse::<IpAddr>().ok());
+
+            match (ip, cidr_operator) {
+                (Some(ip), CidrOperator::InCidr(cidr_set)) => Ok(cidr_set.contains(&ip)),
+                (Some(ip), CidrOperator::NotInCidr(cidr_set)) => Ok(!cidr_set.contains(&ip)),
+                (None, _) => Ok(false),
+            }
+        }
         CriteriaOperator::Exists(exists) => {
             if *exists {
                 Ok(value.is_some())
@@ -153,8 +201,107 @@ fn match_header<'a>(header_criteria: &HeaderCriteria, session: &Session<'a>) ->
     match_part_critera_operators(&header_criteria.operator, req_header_value)
 }
 
-fn match_query<'a>(header_criteria: &HeaderCriteria, session: &Session<'a>) -> DakiaResult<bool> {
-    todo!()
+// query param can be repeated, it's matched if any of its values matches
+fn match_query<'a>(query_criteria: &QueryCriteria, session: &Session<'a>) -> DakiaResult<bool> {
+    let query_param_name = String::from_utf8_lossy(&query_criteria.name);
+    let query_param_values = session.ds_req_query_params().get(query_param_name.as_ref());
+
+    match query_param_values {
+        Some(values) => {
+            for value in values {
+                if match_part_critera_operators(&query_criteria.operator, Some(value.as_bytes()))? {
+                    return Ok(true);
+                }
+            }
+            Ok(false)
+        }
+        None => match_part_critera_operators(&query_criteria.operator, None),
+    }
+}
+
+fn match_cookie<'a>(cookie_criteria: &CookieCriteria, session: &Session<'a>) -> DakiaResult<bool> {
+    let cookie_name = String::from_utf8_lossy(&cookie_criteria.name);
+    let cookie_value = session.ds_req_cookie(&cookie_name);
+    match_part_critera_operators(&cookie_criteria.operator, cookie_value.map(str::as_bytes))
+}
+
+fn find_json_field<'v>(value: &'v Value, path: &[String]) -> Option<&'v Value> {
+    path.iter().try_fold(value, |value, key| match value {
+        Value::Object(map) => map.get(key),
+        Value::Array(array) => array.get(key.parse::<usize>().ok()?),
+        _ => None,
+    })
+}
+
+// string field is matched without quotes, null field is considered as missing
+fn match_body_json<'a>(
+    body_json_criteria: &BodyJsonCriteria,
+    session: &Session<'a>,
+) -> DakiaResult<bool> {
+    let field = session
+        .ds_req_body_json()
+        .and_then(|body_json| find_json_field(body_json, &body_json_criteria.path));
+
+    let field_value = match field {
+        None | Some(Value::Null) => None,
+        Some(Value::String(field)) => Some(field.clone()),
+        Some(field) => Some(field.to_string()),
+    };
+
+    match_part_critera_operators(
+        &body_json_criteria.operator,
+        field_value.as_deref().map(str::as_bytes),
+    )
+}
+
+// claim having array value is matched if any of its elements matches, e.g. roles
+fn match_jwt_claim<'a>(
+    jwt_claim_criteria: &JwtClaimCriteria,
+    session: &Session<'a>,
+) -> DakiaResult<bool> {
+    let claim_value = |value: &Value| match value {
+        Value::Null => None,
+        Value::String(value) => Some(value.clone()),
+        value => Some(value.to_string()),
+    };
+
+    match session.jwt_claim(&jwt_claim_criteria.name) {
+        Some(Value::Array(values)) if !values.is_empty() => {
+            for value in values {
+                let value = claim_value(value);
+                if match_part_critera_operators(
+                    &jwt_claim_criteria.operator,
+                    value.as_deref().map(str::as_bytes),
+                )? {
+                    return Ok(true);
+                }
+            }
+            Ok(false)
+        }
+        claim => {
+            let value = claim.and_then(claim_value);
+            match_part_critera_operators(
+                &jwt_claim_criteria.operator,
+                value.as_deref().map(str::as_bytes),
+            )
+        }
+    }
+}
+
+fn match_host<'a>(
+    criteria_operators: &Vec<PartCriteriaOperator>,
+    session: &Session<'a>,
+) -> DakiaResult<bool> {
+    let req_host = session.ds_req_host();
+    match_part_critera_operators(criteria_operators, req_host.map(str::as_bytes))
+}
+
+fn match_port<'a>(
+    criteria_operators: &Vec<PartCriteriaOperator>,
+    session: &Session<'a>,
+) -> DakiaResult<bool> {
+    let req_port = session.ds_req_port().map(|port| port.to_string());
+    match_part_critera_operators(criteria_operators, req_port.as_deref().map(str::as_bytes))
 }
 
 fn match_path<'a>(
@@ -166,6 +313,22 @@ fn match_path<'a>(
     match_part_critera_operators(criteria_operators, Some(req_path.as_bytes()))
 }
 
+fn match_scheme<'a>(
+    criteria_operators: &Vec<PartCriteriaOperator>,
+    session: &Session<'a>,
+) -> DakiaResult<bool> {
+    let req_scheme = session.ds_req_scheme();
+    match_part_critera_operators(criteria_operators, Some(req_scheme.as_bytes()))
+}
+
+fn match_client_ip<'a>(
+    criteria_operators: &Vec<PartCriteriaOperator>,
+    session: &Session<'a>,
+) -> DakiaResult<bool> {
+    let client_ip = session.ds_client_ip().map(|ip| ip.to_string());
+    match_part_critera_operators(criteria_operators, client_ip.as_deref().map(str::as_bytes))
+}
+
 fn match_method<'a>(
     criteria_operators: &Vec<PartCriteriaOperator>,
     session: &Session<'a>,
@@ -180,54 +343,342 @@ fn exec_part_filter<'a>(
 ) -> DakiaResult<bool> {
     match part_filter_criteria {
         PartFilterCriteria::Header(header_criteria) => match_header(header_criteria, session),
-        PartFilterCriteria::Query(query_criteria) => todo!(),
+        PartFilterCriteria::Query(query_criteria) => match_query(query_criteria, session),
+        PartFilterCriteria::Cookie(cookie_criteria) => match_cookie(cookie_criteria, session),
+        PartFilterCriteria::BodyJson(body_json_criteria) => {
+            match_body_json(body_json_criteria, session)
+        }
+        PartFilterCriteria::JwtClaim(jwt_claim_criteria) => {
+            match_jwt_claim(jwt_claim_criteria, session)
+        }
         PartFilterCriteria::Path(part_criteria_operators) => {
             match_path(part_criteria_operators, session)
         }
-        PartFilterCriteria::Scheme(part_criteria_operators) => todo!(),
+        PartFilterCriteria::Scheme(part_criteria_operators) => {
+            match_scheme(part_criteria_operators, session)
+        }
         PartFilterCriteria::Method(part_criteria_operators) => {
             match_method(part_criteria_operators, session)
         }
+        PartFilterCriteria::ClientIp(part_criteria_operators) => {
+            match_client_ip(part_criteria_operators, session)
+        }
+        PartFilterCriteria::Host(part_criteria_operators) => {
+            match_host(part_criteria_operators, session)
+        }
+        PartFilterCriteria::Port(part_criteria_operators) => {
+            match_port(part_criteria_operators, session)
+        }
+    }
+}
+
+fn exec_filter_criteria<'a>(criteria: &FilterCriteria, session: &Session<'a>) -> DakiaResult<bool> {
+    match criteria {
+        FilterCriteria::Logical(logical_filter_criteria) => match logical_filter_criteria {
+            LogicalFilterCriteria::And(criteria_list) => {
+                for criteria in criteria_list {
+                    if !exec_filter_criteria(criteria, session)? {
+                        return Ok(false);
+                    }
+                }
+                Ok(true)
+            }
+            LogicalFilterCriteria::Or(criteria_list) => {
+                for criteria in criteria_list {
+                    if exec_filter_criteria(criteria, session)? {
+                        return Ok(true);
+                    }
+                }
+                Ok(false)
+            }
+            LogicalFilterCriteria::Not(criteria) => Ok(!exec_filter_criteria(criteria, session)?),
+        },
+        FilterCriteria::PartFilterCriteria(part_filter_criteria) => {
+            trace!(
+                "executing part filter criteria match for \n {:#?}",
+                part_filter_criteria
+            );
+            exec_part_filter(part_filter_criteria, session)
+        }
+        FilterCriteria::Reference(filter_name) => Err(DakiaError::i_explain(format!(
+            "Something went wrong! Filter reference {filter_name} is not resolved"
+        ))),
     }
 }
 
 pub fn exec_filter<'a>(filter: &Filter, session: &Session<'a>) -> DakiaResult<bool> {
     trace!("executing filter match for filter \n {:#?}", filter);
 
+    // every top level criteria must match, filter without any criteria is a match
     for criteria in &filter.criteria_list {
-        match criteria {
-            FilterCriteria::Logical(logical_filter_criteria) => match logical_filter_criteria {
-                LogicalFilterCriteria::And(part_filter_criterias) => {
-                    for part_filter_criteria in part_filter_criterias {
-                        let is_part_filter_matched =
-                            exec_part_filter(part_filter_criteria, session)?;
-                        if !is_part_filter_matched {
-                            return Ok(false);
-                        }
-                    }
-                    return Ok(true);
-                }
-                LogicalFilterCriteria::Or(part_filter_criterias) => {
-                    for part_filter_criteria in part_filter_criterias {
-                        let is_part_filter_matched =
-                            exec_part_filter(part_filter_criteria, session)?;
-                        if is_part_filter_matched {
-                            return Ok(true);
-                        }
+        if !exec_filter_criteria(criteria, session)? {
+            return Ok(false);
+        }
+    }
+
+    Ok(true)
+}
+
+// result of named filter is cached in ctx till Session::reset_filter_results is called,
+// filters only read downstream request, buffered body and jwt claims, see reset_filter_results
+pub fn exec_named_filter<'a>(filter_name: &str, session: &Session<'a>) -> DakiaResult<bool> {
+    let ctx = session.ctx();
+    let cached = ctx
+        .filter_results
+        .lock()
+        .ok()
+        .and_then(|filter_results| filter_results.get(filter_name).copied());
+    if let Some(is_matched) = cached {
+        return Ok(is_matched);
+    }
+
+    let filter = ctx.gateway_state.filter_or_err(filter_name)?;
+    let is_matched = exec_filter(filter, session)?;
+
+    if let Ok(mut filter_results) = ctx.filter_results.lock() {
+        filter_results.insert(filter_name.to_string(), is_matched);
+    }
+
+    Ok(is_matched)
+}
+
+fn find_path_pattern(operators: &[PartCriteriaOperator]) -> Option<&Pcre2PatternMatcher> {
+    let is_path_pattern = |criteria_operator: &&CriteriaOperator| {
+        matches!(
+            criteria_operator,
+            CriteriaOperator::Pattern(PatternOperator::Matches(_))
+        )
+    };
+
+    operators.iter().find_map(|operator| {
+        let criteria_operator = match operator {
+            PartCriteriaOperator::CriteriaOperator(criteria_operator) => {
+                Some(criteria_operator).filter(is_path_pattern)
+            }
+            PartCriteriaOperator::LogicalCriteriaOperator(logical_criteria_operator) => {
+                match logical_criteria_operator {
+                    LogicalCriteriaOperator::And(criteria_operators)
+                    | LogicalCriteriaOperator::Or(criteria_operators) => {
+                        criteria_operators.iter().find(is_path_pattern)
                     }
-                    return Ok(false);
                 }
-            },
-            FilterCriteria::PartFilterCriteria(part_filter_criteria) => {
-                trace!(
-                    "executing part filter criteria match for \n {:#?}",
-                    part_filter_criteria
-                );
-                return exec_part_filter(part_filter_criteria, session);
+            }
+        };
+
+        match criteria_operator {
+            Some(CriteriaOperator::Pattern(PatternOperator::Matches(pattern_matcher))) => {
+                Some(pattern_matcher)
+            }
+            _ => None,
+        }
+    })
+}
+
+// named captures of the first $matches pattern of path in filter, used for rewriting path of router
+pub fn capture_path_params(filter: &Filter, path: &str) -> DakiaResult<HashMap<String, String>> {
+    // pattern under $not can't capture anything from a matched path
+    let part_filter_criterias = filter.part_filter_criterias(false);
+
+    for part_filter_criteria in part_filter_criterias {
+        if let PartFilterCriteria::Path(operators) = part_filter_criteria {
+            if let Some(pattern_matcher) = find_path_pattern(operators) {
+                let captures = pattern_matcher
+                    .named_captures(path.as_bytes())
+                    .map_err(|e| DakiaError::i_explain(format!("failed to capture path - {e}")))?;
+                return Ok(captures.unwrap_or_default());
             }
         }
     }
 
-    // return true if no criteria is present to indicate a match
-    Ok(true)
+    Ok(HashMap::new())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::Cursor, sync::Arc};
+
+    use pingora::protocols::tls::SslDigest;
+    use pingora_proxy::Session as PSession;
+
+    use crate::{
+        config::source_config::GatewayConfig,
+        gateway::{filter::query2filter, interceptor::Phase, state::build_gateway_state},
+        proxy::http::DakiaHttpGatewayCtx,
+        qe::query::Query,
+    };
+
+    use super::*;
+
+    async fn build_ctx(filters: &str) -> DakiaHttpGatewayCtx {
+        let yaml = format!(
+            r#"
+            name: root
+            bind_addresses:
+              - host: 127.0.0.1
+                port: 8080
+            downstreams:
+              - host: example.com
+            upstreams:
+              - name: web
+                default: true
+                upstream_nodes:
+                  - address:
+                      host: 127.0.0.1
+                      port: 3001
+                    tls: false
+            filters:
+{filters}
+            "#
+        );
+        let gateway_config: GatewayConfig = serde_yaml::from_str(&yaml).unwrap();
+        let gateway_state = build_gateway_state(gateway_config, 0, None).await.unwrap();
+        DakiaHttpGatewayCtx::new(Arc::new(gateway_state))
+    }
+
+    async fn build_psession(raw_request: &str) -> PSession {
+        let mut psession = PSession::new_h1(Box::new(Cursor::new(raw_request.as_bytes().to_vec())));
+        assert!(psession.read_request().await.unwrap());
+        psession
+    }
+
+    #[tokio::test]
+    async fn test_match_repeated_query() {
+        let mut ctx = build_ctx(
+            r#"
+              - name: beta
+                query.version:
+                  $in:
+                    - beta
+                    - canary
+            "#,
+        )
+        .await;
+
+        let mut psession =
+            build_psession("GET /x?version=stable&version=canary HTTP/1.1\r\n\r\n").await;
+        let session = Session::build(Phase::Init, &mut psession, &mut ctx);
+        assert!(exec_named_filter("beta", &session).unwrap());
+
+        let mut ctx = DakiaHttpGatewayCtx::new(ctx.gateway_state.clone());
+        let mut psession =
+            build_psession("GET /x?version=stable&channel=beta HTTP/1.1\r\n\r\n").await;
+        let session = Session::build(Phase::Init, &mut psession, &mut ctx);
+        assert!(!exec_named_filter("beta", &session).unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_match_scheme() {
+        let mut ctx = build_ctx(
+            r#"
+              - name: secure
+                scheme: https
+            "#,
+        )
+        .await;
+
+        let mut psession = build_psession("GET /x HTTP/1.1\r\n\r\n").await;
+        let session = Session::build(Phase::Init, &mut psession, &mut ctx);
+        assert_eq!(session.ds_req_scheme(), "http");
+        assert!(!exec_named_filter("secure", &session).unwrap());
+
+        let mut ctx = DakiaHttpGatewayCtx::new(ctx.gateway_state.clone());
+        let mut psession = build_psession("GET /x HTTP/1.1\r\n\r\n").await;
+        psession.digest_mut().unwrap().ssl_digest = Some(Arc::new(SslDigest {
+            cipher: "TLS_AES_128_GCM_SHA256",
+            version: "TLSv1.3",
+            organization: None,
+            serial_number: None,
+            cert_digest: vec![],
+        }));
+        let session = Session::build(Phase::Init, &mut psession, &mut ctx);
+        assert_eq!(session.ds_req_scheme(), "https");
+        assert!(exec_named_filter("secure", &session).unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_cached_filter_result() {
+        let mut ctx = build_ctx(
+            r#"
+              - name: orders
+                path: /orders
+            "#,
+        )
+        .await;
+
+        let mut psession = build_psession("GET /orders HTTP/1.1\r\n\r\n").await;
+        let mut session = Session::build(Phase::Init, &mut psession, &mut ctx);
+        assert!(exec_named_filter("orders", &session).unwrap());
+
+        // cached result is served instead of evaluating filter again
+        session
+            .ctx()
+            .filter_results
+            .lock()
+            .unwrap()
+            .insert("orders".to_string(), false);
+        assert!(!exec_named_filter("orders", &session).unwrap());
+
+        session.reset_filter_results();
+        assert!(exec_named_filter("orders", &session).unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_filter_result_after_buffered_body() {
+        let mut ctx = build_ctx(
+            r#"
+              - name: acme
+                body.json.tenant: acme
+            "#,
+        )
+        .await;
+
+        let body = r#"{"tenant":"acme"}"#;
+        let mut psession = build_psession(&format!(
+            "POST /orders HTTP/1.1\r\ncontent-length: {}\r\n\r\n{body}",
+            body.len()
+        ))
+        .await;
+        let mut session = Session::build(Phase::RequestFilter, &mut psession, &mut ctx);
+        assert!(!exec_named_filter("acme", &session).unwrap());
+
+        session.buffer_ds_req_body(1024).await.unwrap();
+        assert!(exec_named_filter("acme", &session).unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_filter_result_after_jwt_claims() {
+        let mut ctx = build_ctx(
+            r#"
+              - name: admin
+                jwt.claim.role: admin
+            "#,
+        )
+        .await;
+
+        let mut psession = build_psession("GET /orders HTTP/1.1\r\n\r\n").await;
+        let mut session = Session::build(Phase::RequestFilter, &mut psession, &mut ctx);
+        assert!(!exec_named_filter("admin", &session).unwrap());
+
+        let claims = serde_json::json!({"role": "admin"});
+        session.set_jwt_claims(claims.as_object().unwrap().clone());
+        assert!(exec_named_filter("admin", &session).unwrap());
+    }
+
+    #[test]
+    fn test_capture_path_params() {
+        let yaml = r#"
+            method: GET
+            path:
+                $matches: ^/payment/(?<version>v[0-9]+)/(?<rest>.*)$
+        "#;
+        let query: Query = serde_yaml::from_str(yaml).unwrap();
+        let filter = query2filter(&query).unwrap();
+
+        let captures = capture_path_params(&filter, "/payment/v2/orders/7").unwrap();
+        assert_eq!(captures.get("version").unwrap(), "v2");
+        assert_eq!(captures.get("rest").unwrap(), "orders/7");
+        assert!(capture_path_params(&filter, "/search/v2")
+            .unwrap()
+            .is_empty());
+    }
 }
@@ -4,6 +4,6 @@ mod operator;
 mod query2filter;
 
 pub use builder::build_filter_registry;
-pub use executor::exec_filter;
-pub use operator::Filter;
+pub use executor::{capture_path_params, exec_named_filter};
+pub use operator::{Filter, PathCondition};
 pub use query2filter::query2filter;
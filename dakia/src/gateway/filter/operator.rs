@@ -1,4 +1,8 @@
-use crate::{error::Error, qe::query::Query, shared::pattern_matcher::Pcre2PatternMatcher};
+use crate::{
+    error::Error,
+    qe::query::Query,
+    shared::{cidr::CidrSet, pattern_matcher::Pcre2PatternMatcher},
+};
 
 use super::query2filter::query2filter;
 
@@ -6,6 +10,24 @@ use super::query2filter::query2filter;
 pub enum RelationalOperator {
     Eq(Vec<u8>),
     Ne(Vec<u8>),
+    // value is stored in lowercase
+    IEq(Vec<u8>),
+}
+
+// value is parsed as number, it doesn't match if it's not a number
+#[derive(Debug, Clone)]
+pub enum ComparisonOperator {
+    Gt(f64),
+    Gte(f64),
+    Lt(f64),
+    Lte(f64),
+}
+
+// length of value in bytes
+#[derive(Debug, Clone)]
+pub enum LengthOperator {
+    Gt(usize),
+    Lt(usize),
 }
 
 #[derive(Debug, Clone)]
@@ -18,6 +40,8 @@ pub enum SetOperator {
 pub enum PatternOperator {
     Contains(Vec<u8>),
     NotContains(Vec<u8>),
+    // value is stored in lowercase
+    IContains(Vec<u8>),
     StartsWith(Vec<u8>),
     NotStartWith(Vec<u8>),
     EndsWith(Vec<u8>),
@@ -25,6 +49,13 @@ pub enum PatternOperator {
     Matches(Pcre2PatternMatcher),
 }
 
+// value is parsed as ip address, it doesn't match if it's not a valid ip address
+#[derive(Debug, Clone)]
+pub enum CidrOperator {
+    InCidr(CidrSet),
+    NotInCidr(CidrSet),
+}
+
 #[derive(Debug, Clone)]
 pub enum Header {
     Accept,
@@ -90,8 +121,11 @@ impl Header {
 #[derive(Debug, Clone)]
 pub enum CriteriaOperator {
     Relation(RelationalOperator),
+    Comparison(ComparisonOperator),
+    Length(LengthOperator),
     Pattern(PatternOperator),
     Set(SetOperator),
+    Cidr(CidrOperator),
     Exists(bool),
 }
 
@@ -119,32 +153,144 @@ pub struct QueryCriteria {
     pub operator: Vec<PartCriteriaOperator>,
 }
 
+#[derive(Debug, Clone)]
+pub struct CookieCriteria {
+    pub name: Vec<u8>,
+    pub operator: Vec<PartCriteriaOperator>,
+}
+
+// path of field inside json body, array elements are accessed by index
+#[derive(Debug, Clone)]
+pub struct BodyJsonCriteria {
+    pub path: Vec<String>,
+    pub operator: Vec<PartCriteriaOperator>,
+}
+
+// claim of jwt validated by jwt_auth interceptor
+#[derive(Debug, Clone)]
+pub struct JwtClaimCriteria {
+    pub name: String,
+    pub operator: Vec<PartCriteriaOperator>,
+}
+
 #[derive(Debug, Clone)]
 pub enum PartFilterCriteria {
     Header(HeaderCriteria),
     Query(QueryCriteria),
+    Cookie(CookieCriteria),
+    BodyJson(BodyJsonCriteria),
+    JwtClaim(JwtClaimCriteria),
     Path(Vec<PartCriteriaOperator>),
     Scheme(Vec<PartCriteriaOperator>),
     Method(Vec<PartCriteriaOperator>),
+    ClientIp(Vec<PartCriteriaOperator>),
+    Host(Vec<PartCriteriaOperator>),
+    Port(Vec<PartCriteriaOperator>),
 }
 
 #[derive(Debug, Clone)]
 pub enum FilterCriteria {
     Logical(LogicalFilterCriteria),
     PartFilterCriteria(PartFilterCriteria),
+    // name of another filter, it's replaced with criterias of that filter while building filter registry
+    Reference(String),
 }
 
+// operands of logical criteria can be logical criteria themselves, so filter is a tree of any depth
 #[derive(Debug, Clone)]
 pub enum LogicalFilterCriteria {
-    And(Vec<PartFilterCriteria>),
-    Or(Vec<PartFilterCriteria>),
+    And(Vec<FilterCriteria>),
+    Or(Vec<FilterCriteria>),
+    Not(Box<FilterCriteria>),
+}
+
+impl FilterCriteria {
+    fn collect_part_filter_criterias<'a>(
+        &'a self,
+        is_negated_included: bool,
+        part_filter_criterias: &mut Vec<&'a PartFilterCriteria>,
+    ) {
+        match self {
+            FilterCriteria::Logical(LogicalFilterCriteria::And(criteria_list))
+            | FilterCriteria::Logical(LogicalFilterCriteria::Or(criteria_list)) => {
+                for criteria in criteria_list {
+                    criteria
+                        .collect_part_filter_criterias(is_negated_included, part_filter_criterias);
+                }
+            }
+            FilterCriteria::Logical(LogicalFilterCriteria::Not(criteria)) => {
+                if is_negated_included {
+                    criteria
+                        .collect_part_filter_criterias(is_negated_included, part_filter_criterias);
+                }
+            }
+            FilterCriteria::PartFilterCriteria(part_filter_criteria) => {
+                part_filter_criterias.push(part_filter_criteria)
+            }
+            FilterCriteria::Reference(_) => {}
+        }
+    }
+}
+
+// path condition which must hold for filter to match
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PathCondition<'a> {
+    Exact(&'a [u8]),
+    Prefix(&'a [u8]),
+}
+
+// only path criteria combined with and at top level is a necessary condition of filter
+fn find_path_condition(criteria_list: &[FilterCriteria]) -> Option<PathCondition<'_>> {
+    criteria_list.iter().find_map(|criteria| match criteria {
+        FilterCriteria::PartFilterCriteria(PartFilterCriteria::Path(operators)) => {
+            match operators.as_slice() {
+                [PartCriteriaOperator::CriteriaOperator(CriteriaOperator::Relation(
+                    RelationalOperator::Eq(path),
+                ))] => Some(PathCondition::Exact(path)),
+                [PartCriteriaOperator::CriteriaOperator(CriteriaOperator::Pattern(
+                    PatternOperator::StartsWith(path),
+                ))] => Some(PathCondition::Prefix(path)),
+                _ => None,
+            }
+        }
+        FilterCriteria::Logical(LogicalFilterCriteria::And(criteria_list)) => {
+            find_path_condition(criteria_list)
+        }
+        _ => None,
+    })
 }
 
+// top level criterias are implicitly combined with and
 #[derive(Debug, Clone)]
 pub struct Filter {
     pub criteria_list: Vec<FilterCriteria>,
 }
 
+impl Filter {
+    // part criterias of every level of filter, in order of lists and of sorted keys of maps
+    pub fn part_filter_criterias(&self, is_negated_included: bool) -> Vec<&PartFilterCriteria> {
+        let mut part_filter_criterias = vec![];
+        for criteria in &self.criteria_list {
+            criteria.collect_part_filter_criterias(is_negated_included, &mut part_filter_criterias);
+        }
+        part_filter_criterias
+    }
+
+    // $eq or $starts_with path condition of filter, used to index routers by path
+    pub fn path_condition(&self) -> Option<PathCondition<'_>> {
+        find_path_condition(&self.criteria_list)
+    }
+
+    // body of request is buffered only if any filter needs it
+    pub fn requires_body(&self) -> bool {
+        self.part_filter_criterias(true)
+            .iter()
+            .any(|part_filter_criteria| {
+                matches!(part_filter_criteria, PartFilterCriteria::BodyJson(_))
+            })
+    }
+}
+
 impl TryFrom<&Query> for Filter {
     type Error = Box<Error>;
 
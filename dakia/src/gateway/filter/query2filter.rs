@@ -3,14 +3,15 @@ use std::vec;
 use crate::{
     error::{DakiaError, DakiaResult},
     gateway::filter::operator::{
-        Header, HeaderCriteria, LogicalCriteriaOperator, PatternOperator, QueryCriteria,
+        BodyJsonCriteria, CidrOperator, ComparisonOperator, CookieCriteria, Header, HeaderCriteria,
+        JwtClaimCriteria, LengthOperator, LogicalCriteriaOperator, PatternOperator, QueryCriteria,
         RelationalOperator, SetOperator,
     },
     qe::query::{
-        self, extract_bool_or_err, extract_string_or_err, extract_vec_bytes_or_err,
-        extract_vec_or_err, Query, Value,
+        self, extract_bool_or_err, extract_f64_or_err, extract_string_or_err, extract_usize_or_err,
+        extract_vec_bytes_or_err, extract_vec_or_err, Query, Value,
     },
-    shared::pattern_matcher::Pcre2PatternMatcher,
+    shared::{cidr::CidrSet, pattern_matcher::Pcre2PatternMatcher},
 };
 
 use super::{
@@ -21,32 +22,58 @@ use super::{
     Filter,
 };
 
-const LOGICAL_OPERATOR: [&str; 2] = ["$and", "$or"];
-const HTTP_PARTS: [&str; 5] = ["scheme", "path", "method", "header", "query"];
+const LOGICAL_OPERATOR: [&str; 3] = ["$and", "$or", "$not"];
+const REFERENCE_OPERATOR: [&str; 3] = ["$filter", "$all_of", "$any_of"];
+const HTTP_PARTS: [&str; 8] = [
+    "scheme",
+    "path",
+    "method",
+    "header",
+    "query",
+    "client.ip",
+    "host",
+    "port",
+];
 
 pub fn query2filter(query: &Query) -> DakiaResult<Filter> {
-    let mut filter = Filter {
-        criteria_list: vec![],
+    let filter = Filter {
+        criteria_list: build_filter_criteria_list(query)?,
     };
 
-    for (part, part_filter) in query {
-        if is_logical_filter_criteria(&part) {
-            let part_filter_criterias = build_part_filter_criteria_list(part_filter)?;
-            let filter_criteria = if part.eq("$and") {
-                FilterCriteria::Logical(LogicalFilterCriteria::And(part_filter_criterias))
-            } else {
-                FilterCriteria::Logical(LogicalFilterCriteria::Or(part_filter_criterias))
+    Ok(filter)
+}
+
+// keys of map are sorted, so that criterias are in the same order every time config is parsed
+fn build_filter_criteria_list(query: &query::Map) -> DakiaResult<Vec<FilterCriteria>> {
+    let mut criteria_list: Vec<FilterCriteria> = vec![];
+    let mut parts: Vec<_> = query.iter().collect();
+    parts.sort_by_key(|(part, _)| *part);
+
+    for (part, part_filter) in parts {
+        if is_logical_filter_criteria(part) {
+            let operands = build_logical_operands(part_filter)?;
+            let logical_filter_criteria = match part.as_str() {
+                "$and" => LogicalFilterCriteria::And(operands),
+                "$or" => LogicalFilterCriteria::Or(operands),
+                // multiple operands of $not are combined with and before negation
+                _ => LogicalFilterCriteria::Not(Box::new(FilterCriteria::Logical(
+                    LogicalFilterCriteria::And(operands),
+                ))),
             };
 
-            filter.criteria_list.push(filter_criteria);
+            criteria_list.push(FilterCriteria::Logical(logical_filter_criteria));
             continue;
         }
 
-        if is_part_filter_criteria(&part) {
+        if is_reference_filter_criteria(part) {
+            let reference_filter_criteria = build_reference_filter_criteria(part, part_filter)?;
+            criteria_list.push(reference_filter_criteria);
+            continue;
+        }
+
+        if is_part_filter_criteria(part) {
             let part_filter_criteria = build_part_filter_criteria(part, part_filter)?;
-            filter
-                .criteria_list
-                .push(FilterCriteria::PartFilterCriteria(part_filter_criteria));
+            criteria_list.push(FilterCriteria::PartFilterCriteria(part_filter_criteria));
             continue;
         }
 
@@ -55,34 +82,65 @@ pub fn query2filter(query: &Query) -> DakiaResult<Filter> {
         )));
     }
 
-    Ok(filter)
+    Ok(criteria_list)
 }
 
-fn build_part_filter_criteria_list(part_filter: &Value) -> DakiaResult<Vec<PartFilterCriteria>> {
+// operands can be a map, where every key is an operand, or a list of maps, where every map is an operand
+// list allows to use same logical operator more than once at the same level
+fn build_logical_operands(part_filter: &Value) -> DakiaResult<Vec<FilterCriteria>> {
     match part_filter {
         Value::Scaler(scaler) => Err(DakiaError::i_explain(format!(
-            "Invalid part filter, map is expected found {:?}",
+            "Invalid logical filter, map or list is expected found {:?}",
             scaler
         ))),
         Value::Composite(composite) => match composite {
-            query::Composite::Map(hash_map) => {
-                let mut part_filter_criteria_list: Vec<PartFilterCriteria> = vec![];
-
-                for (part, filter) in hash_map {
-                    let part_filter_criteria = build_part_filter_criteria(part, filter)?;
-                    part_filter_criteria_list.push(part_filter_criteria);
+            query::Composite::Map(hash_map) => build_filter_criteria_list(hash_map),
+            query::Composite::Vector(vector) => {
+                let mut operands: Vec<FilterCriteria> = vec![];
+
+                for value in vector {
+                    let hash_map = match value {
+                        Value::Composite(query::Composite::Map(hash_map)) => hash_map,
+                        _ => {
+                            return Err(DakiaError::i_explain(format!(
+                                "Invalid logical filter operand, map is expected found {:?}",
+                                value
+                            )))
+                        }
+                    };
+
+                    let criteria_list = build_filter_criteria_list(hash_map)?;
+                    operands.push(FilterCriteria::Logical(LogicalFilterCriteria::And(
+                        criteria_list,
+                    )));
                 }
 
-                Ok(part_filter_criteria_list)
+                Ok(operands)
             }
-            query::Composite::Vector(vector) => Err(DakiaError::i_explain(format!(
-                "Invalid part filter, map is expected found {:?}",
-                vector
-            ))),
         },
     }
 }
 
+fn build_reference_filter_criteria(part: &str, part_filter: &Value) -> DakiaResult<FilterCriteria> {
+    if part == "$filter" {
+        let filter_name = extract_string_or_err(part_filter)?;
+        return Ok(FilterCriteria::Reference(filter_name));
+    }
+
+    let mut references: Vec<FilterCriteria> = vec![];
+    for value in extract_vec_or_err(part_filter)? {
+        let filter_name = extract_string_or_err(value)?;
+        references.push(FilterCriteria::Reference(filter_name));
+    }
+
+    let logical_filter_criteria = if part == "$all_of" {
+        LogicalFilterCriteria::And(references)
+    } else {
+        LogicalFilterCriteria::Or(references)
+    };
+    Ok(FilterCriteria::Logical(logical_filter_criteria))
+}
+
 fn build_part_filter_criteria(part: &str, part_filter: &Value) -> DakiaResult<PartFilterCriteria> {
     if is_part_nested(part, "header") {
         let nested_part_name = get_nested_part_name(part, "header");
@@ -104,6 +162,41 @@ fn build_part_filter_criteria(part: &str, part_filter: &Value) -> DakiaResult<Pa
         return Ok(PartFilterCriteria::Query(query_criteria));
     }
 
+    if is_part_nested(part, "cookie") {
+        let nested_part_name = get_nested_part_name(part, "cookie");
+        let cookie_criteria = CookieCriteria {
+            name: nested_part_name.as_bytes().to_vec(),
+            operator: build_part_criteria_operator_list(part_filter)?,
+        };
+
+        return Ok(PartFilterCriteria::Cookie(cookie_criteria));
+    }
+
+    if is_part_nested(part, "body.json") {
+        let nested_part_name = get_nested_part_name(part, "body.json");
+        // json path can be written as $.tenant or tenant
+        let path = nested_part_name
+            .trim_start_matches("$.")
+            .split('.')
+            .map(String::from)
+            .collect();
+        let body_json_criteria = BodyJsonCriteria {
+            path,
+            operator: build_part_criteria_operator_list(part_filter)?,
+        };
+
+        return Ok(PartFilterCriteria::BodyJson(body_json_criteria));
+    }
+
+    if let Some(claim_name) = part.strip_prefix("jwt.claim.") {
+        let jwt_claim_criteria = JwtClaimCriteria {
+            name: claim_name.to_string(),
+            operator: build_part_criteria_operator_list(part_filter)?,
+        };
+
+        return Ok(PartFilterCriteria::JwtClaim(jwt_claim_criteria));
+    }
+
     let part_criteria_operator_list = build_part_criteria_operator_list(part_filter)?;
     if is_part(part, "path") {
         return Ok(PartFilterCriteria::Path(part_criteria_operator_list));
@@ -117,6 +210,18 @@ fn build_part_filter_criteria(part: &str, part_filter: &Value) -> DakiaResult<Pa
         return Ok(PartFilterCriteria::Scheme(part_criteria_operator_list));
     }
 
+    if is_part(part, "client.ip") {
+        return Ok(PartFilterCriteria::ClientIp(part_criteria_operator_list));
+    }
+
+    if is_part(part, "host") {
+        return Ok(PartFilterCriteria::Host(part_criteria_operator_list));
+    }
+
+    if is_part(part, "port") {
+        return Ok(PartFilterCriteria::Port(part_criteria_operator_list));
+    }
+
     Err(DakiaError::i_explain(format!(
         "Invalid part filter {}",
         part
@@ -145,6 +250,16 @@ fn build_set_values(val: &Value) -> DakiaResult<Vec<Vec<u8>>> {
     Ok(bytes_vector)
 }
 
+fn build_cidr_set(val: &Value) -> DakiaResult<CidrSet> {
+    let vector = extract_vec_or_err(val)?;
+    let mut cidrs: Vec<String> = vec![];
+    for val in vector {
+        let cidr = extract_string_or_err(val)?;
+        cidrs.push(cidr);
+    }
+    CidrSet::build(&cidrs)
+}
+
 fn build_criteria_operator(key: &str, value: &Value) -> DakiaResult<CriteriaOperator> {
     let criteria_operator = match key.to_lowercase().as_str() {
         // relational operator
@@ -156,6 +271,20 @@ fn build_criteria_operator(key: &str, value: &Value) -> DakiaResult<CriteriaOper
             let bytes = extract_vec_bytes_or_err(value)?;
             CriteriaOperator::Relation(RelationalOperator::Ne(bytes))
         }
+        "$ieq" => {
+            let bytes = extract_vec_bytes_or_err(value)?.to_ascii_lowercase();
+            CriteriaOperator::Relation(RelationalOperator::IEq(bytes))
+        }
+
+        // comparison operator
+        "$gt" => CriteriaOperator::Comparison(ComparisonOperator::Gt(extract_f64_or_err(value)?)),
+        "$gte" => CriteriaOperator::Comparison(ComparisonOperator::Gte(extract_f64_or_err(value)?)),
+        "$lt" => CriteriaOperator::Comparison(ComparisonOperator::Lt(extract_f64_or_err(value)?)),
+        "$lte" => CriteriaOperator::Comparison(ComparisonOperator::Lte(extract_f64_or_err(value)?)),
+
+        // length operator
+        "$len_gt" => CriteriaOperator::Length(LengthOperator::Gt(extract_usize_or_err(value)?)),
+        "$len_lt" => CriteriaOperator::Length(LengthOperator::Lt(extract_usize_or_err(value)?)),
 
         // set operator
         "$in" => {
@@ -176,6 +305,10 @@ fn build_criteria_operator(key: &str, value: &Value) -> DakiaResult<CriteriaOper
             let bytes = extract_vec_bytes_or_err(value)?;
             CriteriaOperator::Pattern(PatternOperator::NotContains(bytes))
         }
+        "$icontains" => {
+            let bytes = extract_vec_bytes_or_err(value)?.to_ascii_lowercase();
+            CriteriaOperator::Pattern(PatternOperator::IContains(bytes))
+        }
         "$starts_with" => {
             let bytes = extract_vec_bytes_or_err(value)?;
             CriteriaOperator::Pattern(PatternOperator::StartsWith(bytes))
@@ -198,6 +331,16 @@ fn build_criteria_operator(key: &str, value: &Value) -> DakiaResult<CriteriaOper
             CriteriaOperator::Pattern(PatternOperator::Matches(pattern_matcher))
         }
 
+        // cidr operator
+        "$in_cidr" => {
+            let cidr_set = build_cidr_set(value)?;
+            CriteriaOperator::Cidr(CidrOperator::InCidr(cidr_set))
+        }
+        "$not_in_cidr" => {
+            let cidr_set = build_cidr_set(value)?;
+            CriteriaOperator::Cidr(CidrOperator::NotInCidr(cidr_set))
+        }
+
         // existance operator
         "$exists" => {
             let exists = extract_bool_or_err(value)?;
@@ -276,10 +419,18 @@ fn is_logical_filter_criteria(key: &str) -> bool {
     LOGICAL_OPERATOR.contains(&key)
 }
 
+fn is_reference_filter_criteria(key: &str) -> bool {
+    REFERENCE_OPERATOR.contains(&key)
+}
+
 fn is_part_filter_criteria(key: &str) -> bool {
     key.starts_with("ds.")
         || key.starts_with("req.")
         || key.starts_with("header.")
+        || key.starts_with("query.")
+        || key.starts_with("cookie.")
+        || key.starts_with("body.json.")
+        || key.starts_with("jwt.claim.")
         || HTTP_PARTS.contains(&key)
 }
 
@@ -344,4 +495,142 @@ mod tests {
         let filter = query2filter(&query).is_ok();
         assert!(filter);
     }
+
+    #[test]
+    fn test_client_ip_filter() {
+        let yaml = r#"
+            client.ip:
+                $in_cidr:
+                    - 10.0.0.0/8
+                    - 2001:db8::/32
+                $not_in_cidr:
+                    - 10.0.0.7
+        "#;
+        let query: Query = serde_yaml::from_str(yaml).unwrap();
+        assert!(query2filter(&query).is_ok());
+
+        let yaml = r#"
+            client.ip:
+                $in_cidr:
+                    - 10.0.0.0/40
+        "#;
+        let query: Query = serde_yaml::from_str(yaml).unwrap();
+        assert!(query2filter(&query).is_err());
+    }
+
+    #[test]
+    fn test_query_filter() {
+        let yaml = r#"
+            scheme: https
+            query.version:
+                $in:
+                    - beta
+                    - canary
+        "#;
+        let query: Query = serde_yaml::from_str(yaml).unwrap();
+        assert!(query2filter(&query).is_ok());
+    }
+
+    #[test]
+    fn test_body_filter() {
+        let yaml = r#"
+            cookie.beta: "1"
+            host: api.example.com
+            port: 8443
+        "#;
+        let query: Query = serde_yaml::from_str(yaml).unwrap();
+        assert!(!query2filter(&query).unwrap().requires_body());
+
+        let yaml = r#"
+            $or:
+                body.json.$.tenant: acme
+                body.json.items.0.sku:
+                    $starts_with: PRO-
+        "#;
+        let query: Query = serde_yaml::from_str(yaml).unwrap();
+        let filter = query2filter(&query).unwrap();
+        assert!(filter.requires_body());
+    }
+
+    #[test]
+    fn test_jwt_claim_filter() {
+        let yaml = r#"
+            jwt.claim.roles: admin
+            jwt.claim.email:
+                $ends_with: "@acme.com"
+        "#;
+        let query: Query = serde_yaml::from_str(yaml).unwrap();
+        let filter = query2filter(&query).unwrap();
+        let jwt_claim_criterias = filter
+            .part_filter_criterias(true)
+            .iter()
+            .filter(|part_filter_criteria| {
+                matches!(part_filter_criteria, PartFilterCriteria::JwtClaim(_))
+            })
+            .count();
+        assert_eq!(jwt_claim_criterias, 2);
+    }
+
+    #[test]
+    fn test_comparison_filter() {
+        let yaml = r#"
+            header.content-length:
+                $gte: 1
+                $lt: "1048576.5"
+            header.x-api-key:
+                $len_gt: 16
+                $len_lt: 64
+            header.x-tenant:
+                $ieq: ACME
+            header.user-agent:
+                $icontains: Mobile
+        "#;
+        let query: Query = serde_yaml::from_str(yaml).unwrap();
+        assert!(query2filter(&query).is_ok());
+
+        let yaml = r#"
+            header.content-length:
+                $gt: large
+        "#;
+        let query: Query = serde_yaml::from_str(yaml).unwrap();
+        assert!(query2filter(&query).is_err());
+    }
+
+    #[test]
+    fn test_nested_logical_filter() {
+        let yaml = r#"
+            method: POST
+            $or:
+                - path: /login
+                - $and:
+                    path:
+                        $starts_with: /admin
+                    $not:
+                        client.ip:
+                            $in_cidr:
+                                - 10.0.0.0/8
+        "#;
+        let query: Query = serde_yaml::from_str(yaml).unwrap();
+        let filter = query2filter(&query).unwrap();
+
+        // top level keys are kept as separate criterias, which are combined with and
+        assert_eq!(filter.criteria_list.len(), 2);
+        assert_eq!(filter.part_filter_criterias(true).len(), 4);
+        assert_eq!(filter.part_filter_criterias(false).len(), 3);
+        assert!(matches!(
+            filter.part_filter_criterias(true).as_slice(),
+            [
+                PartFilterCriteria::Path(_),
+                PartFilterCriteria::ClientIp(_),
+                PartFilterCriteria::Path(_),
+                PartFilterCriteria::Method(_),
+            ]
+        ));
+
+        let yaml = r#"
+            $not: GET
+        "#;
+        let query: Query = serde_yaml::from_str(yaml).unwrap();
+        assert!(query2filter(&query).is_err());
+    }
 }
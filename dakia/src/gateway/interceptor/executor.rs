@@ -2,20 +2,13 @@ use std::sync::Arc;
 
 use log::trace;
 
-use crate::{error::DakiaResult, gateway::filter::exec_filter, proxy::http::Session};
+use crate::{error::DakiaResult, gateway::filter::exec_named_filter, proxy::http::Session};
 
 use super::{is_hook_enabled, is_phase_enabled, Hook, Interceptor, Phase, PhaseResult};
 
 fn match_filter<'a>(filter_name: &Option<String>, session: &Session<'a>) -> DakiaResult<bool> {
     match filter_name {
-        Some(filter_name) => {
-            let filter =
-                session.ctx().gateway_state.filter(filter_name).expect(
-                    format!("Unexpected error, {filter_name} filter not found...").as_str(),
-                );
-
-            Ok(exec_filter(filter, session)?)
-        }
+        Some(filter_name) => Ok(exec_named_filter(filter_name, session)?),
         None => {
             trace!("No filter specified, defaulting to match as true.");
             Ok(true)
@@ -25,13 +18,13 @@ fn match_filter<'a>(filter_name: &Option<String>, session: &Session<'a>) -> Daki
 
 pub async fn exec_hook<'a>(cur_hook: Hook, session: &mut Session<'a>) -> PhaseResult {
     let gateway_state = session.ctx().gateway_state.clone();
-    let interceptors = gateway_state.interceptors();
+    let interceptors = gateway_state.request_interceptors(session.ctx().ds_index);
 
     for interceptor in interceptors {
-        let is_hook_enabled = is_hook_enabled(interceptor.hook_mask(), &cur_hook);
-        // TODO: store filter matching status inside RwLock<HashMap<&str,bool>> inside session.ctx() to avoid doing heavy computation while filtering the same logic
-        let is_filter_matched = match_filter(interceptor.filter(), session)?;
-        if !is_hook_enabled || !is_filter_matched {
+        // filter is not evaluated for interceptors which are not enabled for the hook
+        if !is_hook_enabled(interceptor.hook_mask(), &cur_hook)
+            || !match_filter(interceptor.filter(), session)?
+        {
             continue;
         }
 
@@ -59,7 +52,10 @@ async fn execute_interceptor_phase<'a>(
         is_phase_enabled,
     );
 
-    // TODO: store filter matching status inside RwLock<HashMap<&str,bool>> inside session.ctx() to avoid doing heavy computation while filtering the same logic
+    if !is_phase_enabled {
+        return Ok(false); // false - continue to other phase or interceptor
+    }
+
     let is_filter_matched = match_filter(interceptor.filter(), session)?;
 
     trace!(
@@ -68,8 +64,8 @@ async fn execute_interceptor_phase<'a>(
         is_filter_matched
     );
 
-    if !is_phase_enabled || !is_filter_matched {
-        return Ok(false); // false - continue to other phase or interceptor
+    if !is_filter_matched {
+        return Ok(false);
     }
 
     match phase {
@@ -79,7 +75,7 @@ async fn execute_interceptor_phase<'a>(
         }
         Phase::RequestFilter => interceptor.request_filter(session).await,
         Phase::UpstreamProxyFilter => interceptor.upstream_proxy_filter(session).await,
-        Phase::UpstreamPeerSelection => todo!(), // no such requirement as of now
+        Phase::UpstreamPeerSelection => interceptor.upstream_peer_selection(session).await,
         Phase::PreUpstreamRequest => interceptor.pre_upstream_request(session).await,
         Phase::PostUpstreamResponse => interceptor.post_upstream_response(session).await,
         Phase::PreDownstreamResponse => interceptor.pre_downstream_response(session).await,
@@ -88,7 +84,7 @@ async fn execute_interceptor_phase<'a>(
 
 pub async fn exec_phase<'a>(session: &mut Session<'a>) -> PhaseResult {
     let gateway_state = session.ctx().gateway_state.clone();
-    let interceptors = gateway_state.interceptors();
+    let interceptors = gateway_state.request_interceptors(session.ctx().ds_index);
 
     for interceptor in interceptors {
         let phase_result = execute_interceptor_phase(interceptor, session).await?;
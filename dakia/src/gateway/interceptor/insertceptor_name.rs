@@ -10,6 +10,14 @@ pub enum InterceptorName {
     ResponseRewrite,
     ShortCircuit,
     RequestId,
+    UpstreamSelector,
+    JwtAuth,
+    ApiKey,
+    #[serde(rename = "oauth2_introspect")]
+    OAuth2Introspect,
+    Oidc,
+    ExtAuthz,
+    HmacAuth,
 }
 
 impl InterceptorName {
@@ -24,6 +32,13 @@ impl InterceptorName {
             InterceptorName::ResponseRewrite => "response_rewrite",
             InterceptorName::ShortCircuit => "short_circuit",
             InterceptorName::RequestId => "request_id",
+            InterceptorName::UpstreamSelector => "upstream_selector",
+            InterceptorName::JwtAuth => "jwt_auth",
+            InterceptorName::ApiKey => "api_key",
+            InterceptorName::OAuth2Introspect => "oauth2_introspect",
+            InterceptorName::Oidc => "oidc",
+            InterceptorName::ExtAuthz => "ext_authz",
+            InterceptorName::HmacAuth => "hmac_auth",
         }
     }
 }
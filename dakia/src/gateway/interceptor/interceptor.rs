@@ -39,6 +39,11 @@ pub trait Interceptor: Send + Sync {
         Ok(false)
     }
 
+    // upstream name, pinned node and peer options can be altered through session in this phase
+    async fn upstream_peer_selection(&self, _session: &mut Session) -> PhaseResult {
+        Ok(false)
+    }
+
     async fn pre_upstream_request(&self, _session: &mut Session) -> PhaseResult {
         Ok(false)
     }
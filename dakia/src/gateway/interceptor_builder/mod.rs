@@ -8,11 +8,15 @@ use crate::{
 };
 
 use super::interceptors::{
-    basic_auth::BasicAuthInterceptorBuilder, controller::ControllerInterceptorBuilder,
+    api_key::ApiKeyInterceptorBuilder, basic_auth::BasicAuthInterceptorBuilder,
+    controller::ControllerInterceptorBuilder, ext_authz::ExtAuthzInterceptorBuilder,
+    hmac_auth::HmacAuthInterceptorBuilder, jwt_auth::JwtAuthInterceptorBuilder,
+    oauth2_introspect::OAuth2IntrospectInterceptorBuilder, oidc::OidcInterceptorBuilder,
     rate_limiter::RateLimiterInterceptorBuilder, request_id::RequestIdInterceptorBuilder,
     request_rewrite::RequestRewriteInterceptorBuilder,
     response_rewrite::ResponseRewriteInterceptorBuilder, server_version,
-    short_circuit::ShortCircuitInterceptorBuilder, use_file,
+    short_circuit::ShortCircuitInterceptorBuilder,
+    upstream_selector::UpstreamSelectorInterceptorBuilder, use_file,
 };
 
 pub trait InterceptorBuilder: Sync + Send {
@@ -77,6 +81,41 @@ impl InterceptorBuilderRegistry {
             Arc::new(RequestIdInterceptorBuilder::default()),
         );
 
+        registry.insert(
+            InterceptorName::UpstreamSelector,
+            Arc::new(UpstreamSelectorInterceptorBuilder::default()),
+        );
+
+        registry.insert(
+            InterceptorName::JwtAuth,
+            Arc::new(JwtAuthInterceptorBuilder::default()),
+        );
+
+        registry.insert(
+            InterceptorName::ApiKey,
+            Arc::new(ApiKeyInterceptorBuilder::default()),
+        );
+
+        registry.insert(
+            InterceptorName::OAuth2Introspect,
+            Arc::new(OAuth2IntrospectInterceptorBuilder::default()),
+        );
+
+        registry.insert(
+            InterceptorName::Oidc,
+            Arc::new(OidcInterceptorBuilder::default()),
+        );
+
+        registry.insert(
+            InterceptorName::ExtAuthz,
+            Arc::new(ExtAuthzInterceptorBuilder::default()),
+        );
+
+        registry.insert(
+            InterceptorName::HmacAuth,
+            Arc::new(HmacAuthInterceptorBuilder::default()),
+        );
+
         Self { registry }
     }
 }
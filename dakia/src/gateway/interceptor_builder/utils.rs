@@ -73,10 +73,37 @@ pub fn build_interceptor(
 pub fn build_interceptors(
     gateway_config: &GatewayConfig,
     interceptor_builder_registry: &InterceptorBuilderRegistry,
+) -> DakiaResult<Vec<Arc<dyn Interceptor>>> {
+    build_interceptor_list(&gateway_config.interceptors, interceptor_builder_registry)
+}
+
+// interceptors of each downstream, in the same order as downstreams of gateway
+pub fn build_ds_interceptors(
+    gateway_config: &GatewayConfig,
+    interceptor_builder_registry: &InterceptorBuilderRegistry,
+) -> DakiaResult<Vec<Vec<Arc<dyn Interceptor>>>> {
+    let mut ds_interceptors: Vec<Vec<Arc<dyn Interceptor>>> = vec![];
+
+    for downstream_config in &gateway_config.downstreams {
+        let interceptor_configs = downstream_config
+            .interceptors
+            .as_deref()
+            .unwrap_or_default();
+        let interceptors =
+            build_interceptor_list(interceptor_configs, interceptor_builder_registry)?;
+        ds_interceptors.push(interceptors);
+    }
+
+    Ok(ds_interceptors)
+}
+
+fn build_interceptor_list(
+    interceptor_configs: &[InterceptorConfig],
+    interceptor_builder_registry: &InterceptorBuilderRegistry,
 ) -> DakiaResult<Vec<Arc<dyn Interceptor>>> {
     let mut interceptors: Vec<Arc<dyn Interceptor>> = vec![];
 
-    for interceptor_config in &gateway_config.interceptors {
+    for interceptor_config in interceptor_configs {
         debug!(
             "Initializing interceptor: {:?} (enabled: {})",
             interceptor_config.name, interceptor_config.enabled
@@ -0,0 +1,89 @@
+use std::sync::Arc;
+
+use crate::{
+    config::source_config::InterceptorConfig,
+    error::{DakiaError, DakiaResult},
+    gateway::{interceptor::Interceptor, interceptor_builder::InterceptorBuilder},
+    qe::query::{extract_string_or_err, Composite, Query, Value},
+};
+
+use super::interceptor::{ApiKeyInterceptor, ApiKeySource, ConsumerField};
+
+#[derive(Default)]
+pub struct ApiKeyInterceptorBuilder {}
+
+impl ApiKeyInterceptorBuilder {
+    fn extract_string(config: &Query, key: &str) -> DakiaResult<Option<String>> {
+        config.get(key).map(extract_string_or_err).transpose()
+    }
+
+    // key is looked up in header, query and cookie in this order, x-api-key header is used if none is configured
+    fn extract_sources(config: &Query) -> DakiaResult<Vec<ApiKeySource>> {
+        let mut sources = vec![];
+        if let Some(header) = Self::extract_string(config, "header")? {
+            sources.push(ApiKeySource::Header(header));
+        }
+        if let Some(query) = Self::extract_string(config, "query")? {
+            sources.push(ApiKeySource::Query(query));
+        }
+        if let Some(cookie) = Self::extract_string(config, "cookie")? {
+            sources.push(ApiKeySource::Cookie(cookie));
+        }
+
+        if sources.is_empty() {
+            sources.push(ApiKeySource::Header("x-api-key".to_string()));
+        }
+        Ok(sources)
+    }
+
+    fn extract_consumer_to_headers(config: &Query) -> DakiaResult<Vec<(ConsumerField, String)>> {
+        let consumer_to_headers = match config.get("consumer_to_headers") {
+            None => {
+                return Ok(vec![
+                    (ConsumerField::Name, "x-consumer-name".to_string()),
+                    (ConsumerField::Group, "x-consumer-group".to_string()),
+                ])
+            }
+            Some(Value::Composite(Composite::Map(consumer_to_headers))) => consumer_to_headers,
+            Some(value) => {
+                return Err(DakiaError::i_explain(format!(
+                    "consumer_to_headers must be a map of consumer field to header, found {:?}",
+                    value
+                )))
+            }
+        };
+
+        consumer_to_headers
+            .iter()
+            .map(|(field, header)| {
+                let field = match field.as_str() {
+                    "name" => ConsumerField::Name,
+                    "group" => ConsumerField::Group,
+                    field => match field.strip_prefix("metadata.") {
+                        Some(metadata_key) => ConsumerField::Metadata(metadata_key.to_string()),
+                        None => {
+                            return Err(DakiaError::i_explain(format!(
+                                "unknown consumer field {field}, expected name, group or metadata.<key>"
+                            )))
+                        }
+                    },
+                };
+                Ok((field, extract_string_or_err(header)?))
+            })
+            .collect()
+    }
+}
+
+impl InterceptorBuilder for ApiKeyInterceptorBuilder {
+    fn build(&self, _interceptor_config: InterceptorConfig) -> DakiaResult<Arc<dyn Interceptor>> {
+        let empty_config = Query::new();
+        let config = _interceptor_config.config.as_ref().unwrap_or(&empty_config);
+
+        let interceptor = ApiKeyInterceptor::build(
+            _interceptor_config.filter.clone(),
+            Self::extract_sources(config)?,
+            Self::extract_consumer_to_headers(config)?,
+        );
+        Ok(Arc::new(interceptor))
+    }
+}
@@ -0,0 +1,109 @@
+use std::str::from_utf8;
+
+use async_trait::async_trait;
+use http::StatusCode;
+
+use crate::{
+    gateway::interceptor::{Interceptor, InterceptorName, Phase, PhaseMask, PhaseResult},
+    proxy::http::Session,
+};
+
+pub enum ApiKeySource {
+    Header(String),
+    Query(String),
+    Cookie(String),
+}
+
+pub enum ConsumerField {
+    Name,
+    Group,
+    Metadata(String),
+}
+
+// identifies consumer of request by its api key, consumer is available to later interceptors
+pub struct ApiKeyInterceptor {
+    filter: Option<String>,
+    sources: Vec<ApiKeySource>,
+    // consumer is forwarded to upstream as headers, pairs of consumer field and header name
+    consumer_to_headers: Vec<(ConsumerField, String)>,
+}
+
+impl ApiKeyInterceptor {
+    pub fn build(
+        filter: Option<String>,
+        sources: Vec<ApiKeySource>,
+        consumer_to_headers: Vec<(ConsumerField, String)>,
+    ) -> Self {
+        Self {
+            filter,
+            sources,
+            consumer_to_headers,
+        }
+    }
+
+    fn api_key<'s>(&self, session: &'s Session) -> Option<&'s str> {
+        self.sources.iter().find_map(|source| match source {
+            ApiKeySource::Header(header) => session
+                .ds_req_header(header)
+                .ok()?
+                .and_then(|api_key| from_utf8(api_key).ok()),
+            ApiKeySource::Query(query) => session
+                .ds_req_query_params()
+                .get(query)?
+                .first()
+                .map(String::as_str),
+            ApiKeySource::Cookie(cookie) => session.ds_req_cookie(cookie),
+        })
+    }
+}
+
+#[async_trait]
+impl Interceptor for ApiKeyInterceptor {
+    fn name(&self) -> InterceptorName {
+        InterceptorName::ApiKey
+    }
+
+    fn phase_mask(&self) -> PhaseMask {
+        Phase::RequestFilter.mask() | Phase::PreUpstreamRequest.mask()
+    }
+
+    fn filter(&self) -> &Option<String> {
+        &self.filter
+    }
+
+    async fn request_filter(&self, _session: &mut Session) -> PhaseResult {
+        let consumer = self
+            .api_key(_session)
+            .and_then(|api_key| _session.ctx().gateway_state.find_consumer(api_key))
+            .cloned();
+
+        match consumer {
+            Some(consumer) => {
+                _session.set_consumer(consumer);
+                Ok(false)
+            }
+            None => {
+                _session.set_res_status(StatusCode::UNAUTHORIZED);
+                Ok(true)
+            }
+        }
+    }
+
+    // header of missing field is removed, so that client can not set it
+    async fn pre_upstream_request(&self, _session: &mut Session) -> PhaseResult {
+        for (field, header) in &self.consumer_to_headers {
+            let value = _session.consumer().and_then(|consumer| match field {
+                ConsumerField::Name => Some(consumer.name.clone()),
+                ConsumerField::Group => consumer.group.clone(),
+                ConsumerField::Metadata(key) => consumer.metadata.get(key).cloned(),
+            });
+
+            match value {
+                Some(value) => _session.set_us_req_header(header.clone(), value.into_bytes()),
+                None => _session.remove_us_req_header(header)?,
+            }
+        }
+
+        Ok(false)
+    }
+}
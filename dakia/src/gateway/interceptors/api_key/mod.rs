@@ -0,0 +1,3 @@
+mod builder;
+mod interceptor;
+pub use builder::ApiKeyInterceptorBuilder;
@@ -1,43 +1,136 @@
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
+
+use sha2::{Digest, Sha256};
 
 use crate::{
     config::source_config::InterceptorConfig,
     error::{DakiaError, DakiaResult},
-    gateway::{
-        interceptor::Interceptor, interceptor_builder::InterceptorBuilder,
-        interceptors::basic_auth::BasicAuthInterceptor,
+    gateway::{interceptor::Interceptor, interceptor_builder::InterceptorBuilder},
+    qe::query::{
+        extract_key_str_or_err, extract_string_or_err, extract_vec_or_err, Composite, Query, Value,
     },
-    qe::query::{extract_key_str_or_err, Query},
+    shared::crypto::PasswordHash,
+};
+
+use super::{
+    htpasswd::HtpasswdFile,
+    interceptor::{BasicAuthInterceptor, Credential},
 };
 
+const DEFAULT_REALM: &str = "Protected Area";
+const DEFAULT_USERNAME_HEADER: &str = "x-authenticated-user";
+
+#[derive(Default)]
 pub struct BasicAuthInterceptorBuilder {}
 
 impl BasicAuthInterceptorBuilder {
-    fn get_user_pass(config: &Query) -> DakiaResult<(String, String)> {
-        let username = extract_key_str_or_err(config, "username")?;
-        let password = extract_key_str_or_err(config, "password")?;
-        Ok((username.to_string(), password.to_string()))
+    fn extract_string(config: &Query, key: &str) -> DakiaResult<Option<String>> {
+        config.get(key).map(extract_string_or_err).transpose()
     }
-}
-impl Default for BasicAuthInterceptorBuilder {
-    fn default() -> Self {
-        Self {}
+
+    fn add_user(
+        users: &mut HashMap<String, Credential>,
+        username: &str,
+        credential: Credential,
+    ) -> DakiaResult<()> {
+        if username.contains(':') {
+            return Err(DakiaError::i_explain(format!(
+                "username {username} must not contain :"
+            )));
+        }
+
+        if users.insert(username.to_string(), credential).is_some() {
+            return Err(DakiaError::i_explain(format!(
+                "user {username} is declared more than once"
+            )));
+        }
+        Ok(())
+    }
+
+    // single username and password in plain text is supported along with list of users with hashed passwords
+    fn extract_users(config: &Query) -> DakiaResult<HashMap<String, Credential>> {
+        let mut users = HashMap::new();
+        if config.contains_key("username") || config.contains_key("password") {
+            let username = extract_key_str_or_err(config, "username")?;
+            let password = extract_key_str_or_err(config, "password")?;
+            let credential = Credential::Plain(Sha256::digest(password.as_bytes()).into());
+            Self::add_user(&mut users, username, credential)?;
+        }
+
+        let user_configs = match config.get("users") {
+            Some(users) => extract_vec_or_err(users)?,
+            None => return Ok(users),
+        };
+
+        for user_config in user_configs {
+            let user_config = match user_config {
+                Value::Composite(Composite::Map(user_config)) => user_config,
+                _ => {
+                    return Err(DakiaError::i_explain(format!(
+                        "users must be a list of username and password_hash, found {:?}",
+                        user_config
+                    )))
+                }
+            };
+
+            let username = extract_key_str_or_err(user_config, "username")?;
+            let password_hash = extract_key_str_or_err(user_config, "password_hash")?;
+            let password_hash = PasswordHash::parse(password_hash).map_err(|e| {
+                DakiaError::i_explain(format!(
+                    "invalid password_hash of user {username} - {:?}",
+                    e
+                ))
+            })?;
+            Self::add_user(
+                &mut users,
+                username,
+                Credential::Hashed(Arc::new(password_hash)),
+            )?;
+        }
+
+        Ok(users)
     }
 }
 
 impl InterceptorBuilder for BasicAuthInterceptorBuilder {
     fn build(&self, _interceptor_config: InterceptorConfig) -> DakiaResult<Arc<dyn Interceptor>> {
-        match &_interceptor_config.config {
-            Some(config) => {
-                let (username, password) = BasicAuthInterceptorBuilder::get_user_pass(config)?;
-                let interceptor =
-                    BasicAuthInterceptor::build(_interceptor_config.filter, username, password);
-                Ok(Arc::new(interceptor))
-            }
-            None => Err(DakiaError::i_explain(format!(
+        let config = _interceptor_config
+            .config
+            .as_ref()
+            .ok_or(DakiaError::i_explain(format!(
                 "config required for interceptor {:?}",
                 _interceptor_config.name
-            ))),
+            )))?;
+
+        let users = Self::extract_users(config)?;
+        let htpasswd_file = Self::extract_string(config, "htpasswd_file")?
+            .map(HtpasswdFile::load)
+            .transpose()?;
+
+        if users.is_empty() && htpasswd_file.is_none() {
+            return Err(DakiaError::i_explain(format!(
+                "{:?} interceptor requires username and password, users or htpasswd_file",
+                _interceptor_config.name
+            )));
+        }
+
+        let realm = Self::extract_string(config, "realm")?.unwrap_or(DEFAULT_REALM.to_string());
+        if realm.contains('"') {
+            return Err(DakiaError::i_explain(format!(
+                "realm {realm} must not contain \""
+            )));
         }
+
+        let username_header = Self::extract_string(config, "username_header")?
+            .unwrap_or(DEFAULT_USERNAME_HEADER.to_string());
+
+        let interceptor = BasicAuthInterceptor::build(
+            _interceptor_config.filter,
+            realm,
+            users,
+            htpasswd_file,
+            username_header,
+        );
+        Ok(Arc::new(interceptor))
     }
 }
@@ -0,0 +1,147 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime},
+};
+
+use arc_swap::ArcSwap;
+use log::{info, warn};
+
+use crate::{
+    error::{DakiaError, DakiaResult},
+    shared::crypto::PasswordHash,
+};
+
+// modification time of file is checked at most once in this interval
+const RELOAD_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+pub type HtpasswdUsers = HashMap<String, Arc<PasswordHash>>;
+
+// lines are username:hash, empty lines and lines starting with # are ignored
+pub fn parse_htpasswd(content: &str) -> DakiaResult<HtpasswdUsers> {
+    let mut users = HashMap::new();
+    for (line_index, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let (username, hash) = line.split_once(':').ok_or(DakiaError::i_explain(format!(
+            "htpasswd line {} must be username:hash",
+            line_index + 1
+        )))?;
+
+        let password_hash = PasswordHash::parse(hash).map_err(|e| {
+            DakiaError::i_explain(format!(
+                "invalid hash of user {username} at htpasswd line {} - {:?}",
+                line_index + 1,
+                e
+            ))
+        })?;
+        users.insert(username.to_string(), Arc::new(password_hash));
+    }
+
+    Ok(users)
+}
+
+struct ReloadState {
+    checked_at: Instant,
+    modified_at: Option<SystemTime>,
+}
+
+// users of htpasswd file, file is reloaded when it's modified
+pub struct HtpasswdFile {
+    path: String,
+    users: ArcSwap<HtpasswdUsers>,
+    reload_state: Mutex<ReloadState>,
+}
+
+impl HtpasswdFile {
+    // file is loaded while building, so that invalid file fails config
+    pub fn load(path: String) -> DakiaResult<Self> {
+        let read_err =
+            |e: std::io::Error| DakiaError::i_explain(format!("failed to read {path} - {e}"));
+        let modified_at = std::fs::metadata(&path).map_err(read_err)?.modified().ok();
+        let users = parse_htpasswd(&std::fs::read_to_string(&path).map_err(read_err)?)?;
+
+        Ok(Self {
+            users: ArcSwap::from_pointee(users),
+            reload_state: Mutex::new(ReloadState {
+                checked_at: Instant::now(),
+                modified_at,
+            }),
+            path,
+        })
+    }
+
+    // returns true if users are reloaded, invalid file keeps previous users
+    pub async fn reload_if_modified(&self) -> bool {
+        {
+            let mut reload_state = self.reload_state.lock().unwrap();
+            if reload_state.checked_at.elapsed() < RELOAD_CHECK_INTERVAL {
+                return false;
+            }
+            reload_state.checked_at = Instant::now();
+        }
+
+        let modified_at = match tokio::fs::metadata(&self.path).await {
+            Ok(metadata) => metadata.modified().ok(),
+            Err(e) => {
+                warn!("failed to check htpasswd file {} - {}", self.path, e);
+                return false;
+            }
+        };
+        if modified_at == self.reload_state.lock().unwrap().modified_at {
+            return false;
+        }
+
+        let users = match tokio::fs::read_to_string(&self.path).await {
+            Ok(content) => parse_htpasswd(&content),
+            Err(e) => Err(DakiaError::i_explain(format!("{e}"))),
+        };
+
+        // file is not read again until it's modified, even if it's invalid
+        self.reload_state.lock().unwrap().modified_at = modified_at;
+        match users {
+            Ok(users) => {
+                info!(
+                    "reloaded {} users of htpasswd file {}",
+                    users.len(),
+                    self.path
+                );
+                self.users.store(Arc::new(users));
+                true
+            }
+            Err(e) => {
+                warn!(
+                    "failed to reload htpasswd file {}, using previous users - {:?}",
+                    self.path, e
+                );
+                false
+            }
+        }
+    }
+
+    pub fn find_user(&self, username: &str) -> Option<Arc<PasswordHash>> {
+        self.users.load().get(username).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_htpasswd() {
+        let users = parse_htpasswd(
+            "# users of dakia\n\nalice:{SHA}W6ph5Mm5Pz8GgiULbPgzG37mj9g=\nbob:$2y$04$Eg6ymrprWLh4oWAYzYQrOefheDzEk0dd22j1gQQ9H0SjsvJZBdJJe\n",
+        )
+        .unwrap();
+        assert_eq!(users.len(), 2);
+        assert!(users["alice"].verify(b"password"));
+        assert!(users["bob"].verify(b"password"));
+
+        assert!(parse_htpasswd("alice").is_err());
+        assert!(parse_htpasswd("alice:$apr1$r31.....$HqJZimcKQFAMYayBlzkrA/").is_err());
+    }
+}
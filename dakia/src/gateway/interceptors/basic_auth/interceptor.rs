@@ -1,38 +1,113 @@
-use std::str::from_utf8;
+use std::{collections::HashMap, str::from_utf8, sync::Arc};
 
 use async_trait::async_trait;
 use base64::{engine::general_purpose, Engine};
+use dashmap::DashSet;
 use http::StatusCode;
+use log::debug;
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
 
 use crate::{
     gateway::interceptor::{Interceptor, InterceptorName, Phase, PhaseMask, PhaseResult},
     proxy::http::Session,
+    shared::crypto::PasswordHash,
 };
 
+use super::htpasswd::HtpasswdFile;
+
+// verified credentials are remembered, so that slow hashes are not computed for every request
+const MAX_VERIFIED_CREDENTIALS: usize = 1024;
+
+#[derive(Clone)]
+pub enum Credential {
+    // sha256 of plain password, digests are compared to not leak length of password
+    Plain([u8; 32]),
+    Hashed(Arc<PasswordHash>),
+}
+
 pub struct BasicAuthInterceptor {
-    credentials: String,
     filter: Option<String>,
+    realm: String,
+    users: HashMap<String, Credential>,
+    htpasswd_file: Option<HtpasswdFile>,
+    // authenticated username is forwarded to upstream in this header
+    username_header: String,
+    verified_credentials: DashSet<[u8; 32]>,
 }
 
 impl BasicAuthInterceptor {
-    pub fn build(filter: Option<String>, username: String, password: String) -> Self {
-        let user_pass = format!("{}:{}", username, password);
-        let credentials = general_purpose::STANDARD.encode(user_pass.clone());
-
+    pub fn build(
+        filter: Option<String>,
+        realm: String,
+        users: HashMap<String, Credential>,
+        htpasswd_file: Option<HtpasswdFile>,
+        username_header: String,
+    ) -> Self {
         BasicAuthInterceptor {
             filter,
-            credentials,
+            realm,
+            users,
+            htpasswd_file,
+            username_header,
+            verified_credentials: DashSet::new(),
         }
     }
 
-    fn authorize(&self, auth_header_bytes: &[u8]) -> bool {
-        match from_utf8(auth_header_bytes) {
-            Ok(user_credentials) => match user_credentials.strip_prefix("Basic ") {
-                Some(stripped) => self.credentials == stripped,
-                None => false,
-            },
-            Err(_) => false, // Authorization header must be an valid UTF-8 string
+    fn credentials(&self, session: &Session) -> Option<(String, String)> {
+        let authorization = session.ds_req_header("Authorization").ok()??;
+        let (scheme, encoded) = from_utf8(authorization).ok()?.split_once(' ')?;
+        if !scheme.eq_ignore_ascii_case("basic") {
+            return None;
+        }
+
+        let decoded = general_purpose::STANDARD.decode(encoded.trim()).ok()?;
+        let (username, password) = from_utf8(&decoded).ok()?.split_once(':')?;
+        Some((username.to_string(), password.to_string()))
+    }
+
+    fn find_credential(&self, username: &str) -> Option<Credential> {
+        self.users.get(username).cloned().or_else(|| {
+            let password_hash = self.htpasswd_file.as_ref()?.find_user(username)?;
+            Some(Credential::Hashed(password_hash))
+        })
+    }
+
+    async fn verify(&self, username: &str, password: String) -> bool {
+        let password_hash = match self.find_credential(username) {
+            Some(Credential::Plain(digest)) => {
+                return Sha256::digest(password.as_bytes()).ct_eq(&digest).into()
+            }
+            Some(Credential::Hashed(password_hash)) => password_hash,
+            None => return false,
+        };
+
+        let credentials_digest: [u8; 32] =
+            Sha256::digest(format!("{username}:{password}").as_bytes()).into();
+        if self.verified_credentials.contains(&credentials_digest) {
+            return true;
         }
+
+        // hashes are slow by design, they are verified outside of async workers
+        let is_verified =
+            tokio::task::spawn_blocking(move || password_hash.verify(password.as_bytes()))
+                .await
+                .unwrap_or(false);
+
+        if is_verified {
+            if self.verified_credentials.len() >= MAX_VERIFIED_CREDENTIALS {
+                self.verified_credentials.clear();
+            }
+            self.verified_credentials.insert(credentials_digest);
+        }
+        is_verified
+    }
+
+    fn write_unauthorized(&self, session: &mut Session) -> PhaseResult {
+        let challenge = format!("Basic realm=\"{}\", charset=\"UTF-8\"", self.realm);
+        session.set_res_status(StatusCode::UNAUTHORIZED);
+        session.set_ds_res_header("WWW-Authenticate".to_string(), challenge.into_bytes());
+        Ok(true)
     }
 }
 
@@ -43,7 +118,7 @@ impl Interceptor for BasicAuthInterceptor {
     }
 
     fn phase_mask(&self) -> PhaseMask {
-        Phase::RequestFilter.mask()
+        Phase::RequestFilter.mask() | Phase::PreUpstreamRequest.mask()
     }
 
     fn filter(&self) -> &Option<String> {
@@ -51,37 +126,37 @@ impl Interceptor for BasicAuthInterceptor {
     }
 
     async fn request_filter(&self, _session: &mut Session) -> PhaseResult {
-        let auth_header_option = _session.ds_req_header("Authorization")?;
-        /*
-        TODO: Move code to write unauthorized response to a common method
-
-        Can not right now because of the following error
-        - implicit elided lifetime not allowed here expected lifetime parameter
-        */
-
-        match auth_header_option {
-            Some(auth_header_bytes) => {
-                let is_authorized = self.authorize(auth_header_bytes);
-
-                if is_authorized {
-                    Ok(false)
-                } else {
-                    _session.set_res_status(StatusCode::UNAUTHORIZED);
-                    _session.set_ds_res_header(
-                        "WWW-Authenticate".to_string(),
-                        "Basic realm=\"Protected Area\"".as_bytes().to_vec(),
-                    );
-                    Ok(true)
-                }
+        // credentials verified with previous users are not valid anymore
+        if let Some(htpasswd_file) = &self.htpasswd_file {
+            if htpasswd_file.reload_if_modified().await {
+                self.verified_credentials.clear();
             }
-            None => {
-                _session.set_res_status(StatusCode::UNAUTHORIZED);
-                _session.set_ds_res_header(
-                    "WWW-Authenticate".to_string(),
-                    "Basic realm=\"Protected Area\"".as_bytes().to_vec(),
-                );
-                Ok(true)
+        }
+
+        let (username, password) = match self.credentials(_session) {
+            Some(credentials) => credentials,
+            None => return self.write_unauthorized(_session),
+        };
+
+        if self.verify(&username, password).await {
+            _session.set_authenticated_user(username);
+            Ok(false)
+        } else {
+            debug!("rejecting basic auth credentials of user {username}");
+            self.write_unauthorized(_session)
+        }
+    }
+
+    // header is removed if user is not authenticated, so that client can not set it
+    async fn pre_upstream_request(&self, _session: &mut Session) -> PhaseResult {
+        match _session.authenticated_user() {
+            Some(username) => {
+                let username = username.as_bytes().to_vec();
+                _session.set_us_req_header(self.username_header.clone(), username)
             }
+            None => _session.remove_us_req_header(&self.username_header)?,
         }
+
+        Ok(false)
     }
 }
@@ -1,4 +1,4 @@
 mod builder;
+mod htpasswd;
 mod interceptor;
 pub use builder::BasicAuthInterceptorBuilder;
-pub use interceptor::BasicAuthInterceptor;
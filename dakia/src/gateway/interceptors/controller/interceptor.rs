@@ -1,20 +1,40 @@
 use std::str::from_utf8;
 
 use async_trait::async_trait;
-use bytes::Bytes;
+use bytes::{Bytes, BytesMut};
 use http::StatusCode;
+use serde::{Deserialize, Serialize};
 
 use crate::{
     config::{source_config::SourceDakiaRawConfig, DakiaConfig},
-    error::DakiaResult,
+    error::{DakiaError, DakiaResult},
     gateway::{
         interceptor::{Interceptor, InterceptorName, Phase, PhaseMask, PhaseResult},
+        lb::node_state,
         state::build_gateway_state,
     },
-    proxy::http::Session,
+    proxy::http::{explain, ExplainRequest, Session},
     shared::dakia_state::DAKIA_STATE_STORE,
 };
 
+// node of configured upstream addressed as host:port, gateway of controller is used if gateway is not given
+#[derive(Deserialize)]
+struct NodeRequest {
+    gateway: Option<String>,
+    upstream: String,
+    node: String,
+    drain: Option<bool>,
+}
+
+#[derive(Serialize)]
+struct NodeStatus<'a> {
+    gateway: &'a str,
+    upstream: &'a str,
+    node: &'a str,
+    drain: bool,
+    in_flight_requests: i64,
+}
+
 pub struct ControllerInterceptor {
     filter: Option<String>,
 }
@@ -47,8 +67,13 @@ impl ControllerInterceptor {
         dakia_config.version = cur_dakia_config.version + 1;
 
         for gateway_config in &dakia_config.gateways {
-            let gateway_state =
-                build_gateway_state(gateway_config.clone(), dakia_config.version).await?;
+            let current_state = DAKIA_STATE_STORE.get_gateway_state(&gateway_config.name)?;
+            let gateway_state = build_gateway_state(
+                gateway_config.clone(),
+                dakia_config.version,
+                current_state.as_deref(),
+            )
+            .await?;
             DAKIA_STATE_STORE.update_gateway_state(gateway_state)?;
         }
 
@@ -56,18 +81,21 @@ impl ControllerInterceptor {
         Ok(())
     }
 
+    // body may already be buffered for filters, it's served by read_ds_req_body in that case as well
+    async fn read_body(&self, session: &mut Session<'_>) -> DakiaResult<Bytes> {
+        let mut body = BytesMut::new();
+        while let Some(chunk) = session.read_ds_req_body().await? {
+            body.extend_from_slice(&chunk);
+        }
+        Ok(body.freeze())
+    }
+
     async fn update_in_memory_dakia_config(&self, _session: &mut Session<'_>) -> DakiaResult<()> {
-        let body = _session.read_ds_req_body().await?;
-        let body_str = match &body {
-            Some(bval) => {
-                if bval.is_empty() {
-                    return self.write_bad_request_response(_session).await;
-                } else {
-                    from_utf8(&bval).expect("Failed to parse content: invalid UTF-8 encoding")
-                }
-            }
-            None => return self.write_bad_request_response(_session).await,
-        };
+        let body = self.read_body(_session).await?;
+        if body.is_empty() {
+            return self.write_bad_request_response(_session).await;
+        }
+        let body_str = from_utf8(&body).expect("Failed to parse content: invalid UTF-8 encoding");
 
         let content_type_hedaer = _session.ds_req_header("Content-Type")?;
         match content_type_hedaer {
@@ -100,6 +128,131 @@ impl ControllerInterceptor {
         Ok(())
     }
 
+    // request to explain is received as json body, report is written as json response
+    async fn write_explain_report_in_response(&self, session: &mut Session<'_>) -> DakiaResult<()> {
+        let body = self.read_body(session).await?;
+        let explain_request: ExplainRequest = match serde_json::from_slice(&body) {
+            Ok(explain_request) => explain_request,
+            Err(_) => return self.write_bad_request_response(session).await,
+        };
+
+        let gateway_state = match &explain_request.gateway {
+            Some(gateway_name) => match DAKIA_STATE_STORE.get_gateway_state(gateway_name)? {
+                Some(gateway_state) => gateway_state,
+                None => {
+                    session.set_res_status(StatusCode::NOT_FOUND);
+                    return Ok(());
+                }
+            },
+            None => session.ctx().gateway_state.clone(),
+        };
+
+        let report = match explain(gateway_state, &explain_request).await {
+            Ok(report) => report,
+            Err(_) => return self.write_bad_request_response(session).await,
+        };
+
+        let report_str = serde_json::to_string(&report).map_err(|e| {
+            DakiaError::i_explain(format!("failed to serialize explain report - {e}"))
+        })?;
+        session.set_ds_res_header(
+            "content-type".to_string(),
+            "application/json".as_bytes().to_vec(),
+        );
+        session
+            .write_ds_res_body(Some(Bytes::from(report_str)), true)
+            .await?;
+        Ok(())
+    }
+
+    // PATCH toggles drain of node, DELETE removes node once it's draining and none of its requests is in flight
+    async fn update_upstream_node(
+        &self,
+        session: &mut Session<'_>,
+        is_removal: bool,
+    ) -> DakiaResult<()> {
+        let body = self.read_body(session).await?;
+        let node_request: NodeRequest = match serde_json::from_slice(&body) {
+            Ok(node_request) => node_request,
+            Err(_) => return self.write_bad_request_response(session).await,
+        };
+        if !is_removal && node_request.drain.is_none() {
+            return self.write_bad_request_response(session).await;
+        }
+
+        let gateway_name = match &node_request.gateway {
+            Some(gateway_name) => gateway_name.clone(),
+            None => session.ctx().gateway_state.gateway_config().name.clone(),
+        };
+
+        let mut dakia_config = DAKIA_STATE_STORE.get_dakia_config()?;
+        let node_configs = dakia_config
+            .gateways
+            .iter_mut()
+            .find(|gateway_config| gateway_config.name == gateway_name)
+            .and_then(|gateway_config| {
+                gateway_config
+                    .upstreams
+                    .iter_mut()
+                    .find(|upstream_config| upstream_config.name == node_request.upstream)
+            })
+            .map(|upstream_config| &mut upstream_config.upstream_nodes);
+        // nodes of discovered upstreams are not part of config
+        let (node_configs, position) = match node_configs.and_then(|node_configs| {
+            let position = node_configs.iter().position(|node_config| {
+                node_config.address.get_formatted_address() == node_request.node
+            })?;
+            Some((node_configs, position))
+        }) {
+            Some(node) => node,
+            None => {
+                session.set_res_status(StatusCode::NOT_FOUND);
+                return Ok(());
+            }
+        };
+
+        let in_flight_requests = node_state::in_flight_requests(
+            &gateway_name,
+            &node_request.upstream,
+            &node_configs[position].address,
+        )
+        .await?;
+        let mut drain = node_configs[position].drain.unwrap_or(false);
+        let is_conflict = is_removal && (!drain || in_flight_requests > 0);
+
+        if is_conflict {
+            session.set_res_status(StatusCode::CONFLICT);
+        } else if is_removal {
+            node_configs.remove(position);
+        } else {
+            drain = node_request.drain.unwrap_or(drain);
+            node_configs[position].drain = Some(drain);
+        }
+
+        let node_status = NodeStatus {
+            gateway: &gateway_name,
+            upstream: &node_request.upstream,
+            node: &node_request.node,
+            drain,
+            in_flight_requests,
+        };
+        let node_status_str = serde_json::to_string(&node_status)
+            .map_err(|e| DakiaError::i_explain(format!("failed to serialize node status - {e}")))?;
+
+        if !is_conflict {
+            self.store_dakia_config_in_store(dakia_config).await?;
+        }
+
+        session.set_ds_res_header(
+            "content-type".to_string(),
+            "application/json".as_bytes().to_vec(),
+        );
+        session
+            .write_ds_res_body(Some(Bytes::from(node_status_str)), true)
+            .await?;
+        Ok(())
+    }
+
     async fn write_dakia_config_in_response(&self, _session: &mut Session<'_>) -> DakiaResult<()> {
         let dakia_config = DAKIA_STATE_STORE.get_dakia_config()?;
         let source_dakia_raw_config = SourceDakiaRawConfig::from(dakia_config);
@@ -151,6 +304,12 @@ impl Interceptor for ControllerInterceptor {
             self.write_dakia_config_in_response(_session).await?;
         } else if method == "PUT" {
             self.update_in_memory_dakia_config(_session).await?;
+        } else if method == "POST" {
+            self.write_explain_report_in_response(_session).await?;
+        } else if method == "PATCH" {
+            self.update_upstream_node(_session, false).await?;
+        } else if method == "DELETE" {
+            self.update_upstream_node(_session, true).await?;
         } else {
             self.write_invalid_method_response(_session).await?;
         }
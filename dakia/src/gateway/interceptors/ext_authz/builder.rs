@@ -0,0 +1,114 @@
+use std::{sync::Arc, time::Duration};
+
+use http::Uri;
+
+use crate::{
+    config::source_config::InterceptorConfig,
+    error::{DakiaError, DakiaResult},
+    gateway::{interceptor::Interceptor, interceptor_builder::InterceptorBuilder},
+    proxy::http::MAX_DS_REQ_BODY_SIZE,
+    qe::query::{
+        extract_bool_or_err, extract_key_i64_or_err, extract_key_str_or_err, extract_string_or_err,
+        Composite, Query, Value,
+    },
+};
+
+use super::{client::AuthzClient, interceptor::ExtAuthzInterceptor};
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(1);
+const DEFAULT_MAX_BODY_SIZE: usize = 8 * 1024;
+
+#[derive(Default)]
+pub struct ExtAuthzInterceptorBuilder {}
+
+impl ExtAuthzInterceptorBuilder {
+    fn extract_bool(config: &Query, key: &str) -> DakiaResult<bool> {
+        Ok(config
+            .get(key)
+            .map(extract_bool_or_err)
+            .transpose()?
+            .unwrap_or(false))
+    }
+
+    // header names are matched in lowercase
+    fn extract_header_names(config: &Query, key: &str) -> DakiaResult<Vec<String>> {
+        let header_names = match config.get(key) {
+            None => vec![],
+            Some(Value::Composite(Composite::Vector(values))) => values
+                .iter()
+                .map(extract_string_or_err)
+                .collect::<DakiaResult<_>>()?,
+            Some(value) => vec![extract_string_or_err(value)?],
+        };
+
+        Ok(header_names
+            .iter()
+            .map(|header_name| header_name.to_ascii_lowercase())
+            .collect())
+    }
+
+    fn extract_duration(config: &Query, key: &str) -> DakiaResult<Option<Duration>> {
+        if !config.contains_key(key) {
+            return Ok(None);
+        }
+
+        let millis = extract_key_i64_or_err(config, key)?;
+        Ok(Some(Duration::from_millis(millis as u64)))
+    }
+
+    // body is buffered before it's sent, so it's limited by retry buffer of pingora
+    fn extract_max_body_size(config: &Query) -> DakiaResult<Option<usize>> {
+        if !Self::extract_bool(config, "include_body")? {
+            return Ok(None);
+        }
+
+        let max_body_size = match config.get("max_body_size") {
+            Some(_) => extract_key_i64_or_err(config, "max_body_size")? as usize,
+            None => DEFAULT_MAX_BODY_SIZE,
+        };
+        if max_body_size > MAX_DS_REQ_BODY_SIZE {
+            return Err(DakiaError::i_explain(format!(
+                "max_body_size must not exceed {MAX_DS_REQ_BODY_SIZE} bytes"
+            )));
+        }
+        Ok(Some(max_body_size))
+    }
+}
+
+impl InterceptorBuilder for ExtAuthzInterceptorBuilder {
+    fn build(&self, _interceptor_config: InterceptorConfig) -> DakiaResult<Arc<dyn Interceptor>> {
+        let config = _interceptor_config
+            .config
+            .as_ref()
+            .ok_or(DakiaError::i_explain(format!(
+                "{:?} interceptor config not found.",
+                _interceptor_config.name
+            )))?;
+
+        let url = extract_key_str_or_err(config, "url")?;
+        match url.parse::<Uri>() {
+            Ok(uri) if uri.scheme().is_some() && uri.host().is_some() => {}
+            _ => {
+                return Err(DakiaError::i_explain(format!(
+                    "url {url} must be an absolute url"
+                )))
+            }
+        }
+
+        let client = AuthzClient::build(
+            url.to_string(),
+            Self::extract_duration(config, "timeout")?.unwrap_or(DEFAULT_TIMEOUT),
+            Self::extract_header_names(config, "upstream_headers")?,
+            Self::extract_duration(config, "cache_ttl")?.unwrap_or_default(),
+        );
+
+        let interceptor = ExtAuthzInterceptor::build(
+            _interceptor_config.filter.clone(),
+            client,
+            Self::extract_header_names(config, "request_headers")?,
+            Self::extract_max_body_size(config)?,
+            Self::extract_bool(config, "fail_open")?,
+        );
+        Ok(Arc::new(interceptor))
+    }
+}
@@ -0,0 +1,267 @@
+use std::{
+    collections::BTreeMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use bytes::Bytes;
+use dashmap::DashMap;
+use http::StatusCode;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::{
+    error::{DakiaError, DakiaResult},
+    shared::http_client::{self, HttpClientRequest},
+};
+
+// cached decisions are dropped when cache grows beyond this, expired decisions are dropped first
+const MAX_CACHED_DECISIONS: usize = 10_000;
+
+// headers of authorization response which are not forwarded to client on denial
+const HOP_BY_HOP_HEADERS: [&str; 8] = [
+    "connection",
+    "keep-alive",
+    "proxy-connection",
+    "te",
+    "trailer",
+    "transfer-encoding",
+    "upgrade",
+    "content-length",
+];
+
+// metadata of downstream request sent to authorization service as json
+#[derive(Serialize)]
+pub struct AuthzRequest {
+    pub method: String,
+    pub path: String,
+    pub query: Option<String>,
+    // repeated headers are joined with comma, sorted map keeps serialized request stable for cache key
+    pub headers: BTreeMap<String, String>,
+    pub client_ip: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub body: Option<String>,
+    // body which is not valid utf-8
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub body_base64: Option<String>,
+}
+
+pub enum Decision {
+    // configured upstream headers, headers missing in authorization response have no value
+    Allow {
+        upstream_headers: Vec<(String, Option<Vec<u8>>)>,
+    },
+    Deny {
+        status: StatusCode,
+        headers: Vec<(String, Vec<u8>)>,
+        body: Bytes,
+    },
+}
+
+struct CachedDecision {
+    decision: Arc<Decision>,
+    expires_at: Instant,
+}
+
+// 2xx response of authorization service allows request, 5xx response is a failure, other responses deny it
+pub struct AuthzClient {
+    url: String,
+    timeout: Duration,
+    upstream_headers: Vec<String>,
+    cache_ttl: Duration,
+    cache: DashMap<[u8; 32], CachedDecision>,
+}
+
+impl AuthzClient {
+    pub fn build(
+        url: String,
+        timeout: Duration,
+        upstream_headers: Vec<String>,
+        cache_ttl: Duration,
+    ) -> Self {
+        Self {
+            url,
+            timeout,
+            upstream_headers,
+            cache_ttl,
+            cache: DashMap::new(),
+        }
+    }
+
+    pub fn upstream_headers(&self) -> &[String] {
+        &self.upstream_headers
+    }
+
+    // decisions are cached by hash of authorization request, failures are never cached
+    pub async fn authorize(&self, request: &AuthzRequest) -> DakiaResult<Arc<Decision>> {
+        let body = serde_json::to_vec(request).map_err(|e| {
+            DakiaError::i_explain(format!("failed to serialize authorization request - {e}"))
+        })?;
+
+        let request_hash: [u8; 32] = Sha256::digest(&body).into();
+        if let Some(cached) = self.cache.get(&request_hash) {
+            if cached.expires_at > Instant::now() {
+                return Ok(cached.decision.clone());
+            }
+        }
+
+        let decision = Arc::new(self.fetch(body).await?);
+        if !self.cache_ttl.is_zero() {
+            self.evict_if_full();
+            self.cache.insert(
+                request_hash,
+                CachedDecision {
+                    decision: decision.clone(),
+                    expires_at: Instant::now() + self.cache_ttl,
+                },
+            );
+        }
+
+        Ok(decision)
+    }
+
+    fn evict_if_full(&self) {
+        if self.cache.len() < MAX_CACHED_DECISIONS {
+            return;
+        }
+
+        let now = Instant::now();
+        self.cache.retain(|_, cached| cached.expires_at > now);
+        if self.cache.len() >= MAX_CACHED_DECISIONS {
+            self.cache.clear();
+        }
+    }
+
+    async fn fetch(&self, body: Vec<u8>) -> DakiaResult<Decision> {
+        let mut request = HttpClientRequest::get(&self.url, self.timeout);
+        request.method = "POST".to_string();
+        request.body = Some(Bytes::from(body));
+        request
+            .headers
+            .push(("content-type".to_string(), b"application/json".to_vec()));
+
+        let response = http_client::send(request).await?;
+        let status = response.header.status;
+        if status.is_server_error() {
+            return Err(DakiaError::i_explain(format!(
+                "authorization service {} responded with status {status}",
+                self.url
+            )));
+        }
+
+        if status.is_success() {
+            let upstream_headers = self
+                .upstream_headers
+                .iter()
+                .map(|header_name| {
+                    let header_value = response
+                        .header
+                        .headers
+                        .get(header_name)
+                        .map(|header_value| header_value.as_bytes().to_vec());
+                    (header_name.clone(), header_value)
+                })
+                .collect();
+            return Ok(Decision::Allow { upstream_headers });
+        }
+
+        let headers = response
+            .header
+            .headers
+            .iter()
+            .filter(|(header_name, _)| !HOP_BY_HOP_HEADERS.contains(&header_name.as_str()))
+            .map(|(header_name, header_value)| {
+                (header_name.to_string(), header_value.as_bytes().to_vec())
+            })
+            .collect();
+
+        Ok(Decision::Deny {
+            status,
+            headers,
+            body: response.body,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use crate::shared::test_server;
+
+    use super::*;
+
+    fn authz_request(path: &str) -> AuthzRequest {
+        AuthzRequest {
+            method: "GET".to_string(),
+            path: path.to_string(),
+            query: None,
+            headers: BTreeMap::from([("x-tenant".to_string(), "a".to_string())]),
+            client_ip: Some("10.0.0.1".to_string()),
+            body: None,
+            body_base64: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_authorize() {
+        let requests = Arc::new(AtomicUsize::new(0));
+        let server_requests = requests.clone();
+        let address = test_server::serve(move |request| {
+            server_requests.fetch_add(1, Ordering::SeqCst);
+            let authz_request: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+            assert_eq!(authz_request["headers"]["x-tenant"], "a");
+            assert_eq!(authz_request["client_ip"], "10.0.0.1");
+
+            match authz_request["path"].as_str().unwrap() {
+                "/allowed" => (200, "{}".to_string()),
+                "/denied" => (403, r#"{"reason":"forbidden"}"#.to_string()),
+                _ => (503, "{}".to_string()),
+            }
+        })
+        .await;
+
+        let client = AuthzClient::build(
+            address,
+            Duration::from_secs(5),
+            vec!["content-type".to_string(), "x-user-id".to_string()],
+            Duration::from_secs(60),
+        );
+
+        let decision = client.authorize(&authz_request("/allowed")).await.unwrap();
+        match decision.as_ref() {
+            Decision::Allow { upstream_headers } => assert_eq!(
+                upstream_headers,
+                &vec![
+                    (
+                        "content-type".to_string(),
+                        Some(b"application/json".to_vec())
+                    ),
+                    ("x-user-id".to_string(), None),
+                ]
+            ),
+            Decision::Deny { .. } => panic!("request must be allowed"),
+        }
+        // decision is served from cache
+        client.authorize(&authz_request("/allowed")).await.unwrap();
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+
+        let decision = client.authorize(&authz_request("/denied")).await.unwrap();
+        match decision.as_ref() {
+            Decision::Deny {
+                status,
+                headers,
+                body,
+            } => {
+                assert_eq!(*status, StatusCode::FORBIDDEN);
+                assert!(headers.iter().all(|(name, _)| name != "content-length"));
+                assert_eq!(body.as_ref(), br#"{"reason":"forbidden"}"#);
+            }
+            Decision::Allow { .. } => panic!("request must be denied"),
+        }
+
+        assert!(client.authorize(&authz_request("/failing")).await.is_err());
+        assert!(client.authorize(&authz_request("/failing")).await.is_err());
+        assert_eq!(requests.load(Ordering::SeqCst), 4);
+    }
+}
@@ -0,0 +1,183 @@
+use std::collections::BTreeMap;
+
+use async_trait::async_trait;
+use base64::{engine::general_purpose, Engine};
+use bytes::Bytes;
+use http::StatusCode;
+use log::warn;
+
+use crate::{
+    error::DakiaResult,
+    gateway::interceptor::{Interceptor, InterceptorName, Phase, PhaseMask, PhaseResult},
+    proxy::http::Session,
+};
+
+use super::client::{AuthzClient, AuthzRequest, Decision};
+
+// asks authorization service whether request is allowed
+pub struct ExtAuthzInterceptor {
+    filter: Option<String>,
+    client: AuthzClient,
+    // headers of request sent to authorization service, all of them if none is configured
+    request_headers: Vec<String>,
+    // body is sent only if it's within this size, none if body is not sent
+    max_body_size: Option<usize>,
+    // request is allowed if authorization service fails
+    is_fail_open: bool,
+}
+
+impl ExtAuthzInterceptor {
+    pub fn build(
+        filter: Option<String>,
+        client: AuthzClient,
+        request_headers: Vec<String>,
+        max_body_size: Option<usize>,
+        is_fail_open: bool,
+    ) -> Self {
+        Self {
+            filter,
+            client,
+            request_headers,
+            max_body_size,
+            is_fail_open,
+        }
+    }
+
+    async fn build_request(&self, session: &mut Session<'_>) -> DakiaResult<AuthzRequest> {
+        let mut headers: BTreeMap<String, String> = BTreeMap::new();
+        for (header_name, header_value) in session.ds_req_headers() {
+            let header_name = header_name.as_str();
+            if !self.request_headers.is_empty()
+                && !self.request_headers.iter().any(|name| name == header_name)
+            {
+                continue;
+            }
+
+            let header_value = String::from_utf8_lossy(header_value.as_bytes());
+            headers
+                .entry(header_name.to_string())
+                .and_modify(|value| {
+                    value.push_str(", ");
+                    value.push_str(&header_value);
+                })
+                .or_insert_with(|| header_value.to_string());
+        }
+
+        let mut request = AuthzRequest {
+            method: session.ds_req_method()?.to_string(),
+            path: session.ds_req_path().to_string(),
+            query: session.ds_req_query()?.map(str::to_string),
+            headers,
+            client_ip: session.ds_client_ip().map(|ip| ip.to_string()),
+            body: None,
+            body_base64: None,
+        };
+
+        let max_body_size = match self.max_body_size {
+            Some(max_body_size) => max_body_size,
+            None => return Ok(request),
+        };
+
+        // body may already be buffered for filters
+        if session.ds_req_body().is_none() {
+            session.buffer_ds_req_body(max_body_size).await?;
+        }
+        if let Some(body) = session.ds_req_body() {
+            if body.len() <= max_body_size {
+                match std::str::from_utf8(body) {
+                    Ok(body) => request.body = Some(body.to_string()),
+                    Err(_) => request.body_base64 = Some(general_purpose::STANDARD.encode(body)),
+                }
+            }
+        }
+
+        Ok(request)
+    }
+
+    async fn write_denial(
+        &self,
+        session: &mut Session<'_>,
+        status: StatusCode,
+        headers: &[(String, Vec<u8>)],
+        body: &Bytes,
+    ) -> PhaseResult {
+        session.set_res_status(status);
+        for (header_name, header_value) in headers {
+            if header_name == "set-cookie" {
+                session.add_ds_res_cookie(String::from_utf8_lossy(header_value).to_string());
+            } else {
+                session.set_ds_res_header(header_name.clone(), header_value.clone());
+            }
+        }
+        session.set_ds_res_header(
+            "content-length".to_string(),
+            body.len().to_string().into_bytes(),
+        );
+
+        if !body.is_empty() {
+            session.write_ds_res_body(Some(body.clone()), true).await?;
+        }
+        Ok(true)
+    }
+}
+
+#[async_trait]
+impl Interceptor for ExtAuthzInterceptor {
+    fn name(&self) -> InterceptorName {
+        InterceptorName::ExtAuthz
+    }
+
+    fn phase_mask(&self) -> PhaseMask {
+        Phase::RequestFilter.mask() | Phase::PreUpstreamRequest.mask()
+    }
+
+    fn filter(&self) -> &Option<String> {
+        &self.filter
+    }
+
+    async fn request_filter(&self, _session: &mut Session) -> PhaseResult {
+        let request = self.build_request(_session).await?;
+
+        match self.client.authorize(&request).await {
+            Ok(decision) => match decision.as_ref() {
+                Decision::Allow { upstream_headers } => {
+                    _session.set_authz_us_req_headers(upstream_headers.clone());
+                    Ok(false)
+                }
+                Decision::Deny {
+                    status,
+                    headers,
+                    body,
+                } => self.write_denial(_session, *status, headers, body).await,
+            },
+            Err(e) => {
+                warn!("authorization service failed - {:?}", e);
+                if !self.is_fail_open {
+                    _session.set_res_status(StatusCode::FORBIDDEN);
+                    return Ok(true);
+                }
+
+                // upstream headers are not granted, values set by client are removed
+                let upstream_headers = self
+                    .client
+                    .upstream_headers()
+                    .iter()
+                    .map(|header_name| (header_name.clone(), None))
+                    .collect();
+                _session.set_authz_us_req_headers(upstream_headers);
+                Ok(false)
+            }
+        }
+    }
+
+    // headers set by client are replaced, so that upstream can trust them
+    async fn pre_upstream_request(&self, _session: &mut Session) -> PhaseResult {
+        for (header_name, header_value) in _session.take_authz_us_req_headers() {
+            _session.remove_us_req_header(&header_name)?;
+            if let Some(header_value) = header_value {
+                _session.set_us_req_header(header_name, header_value);
+            }
+        }
+        Ok(false)
+    }
+}
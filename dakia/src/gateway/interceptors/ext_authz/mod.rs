@@ -0,0 +1,4 @@
+mod builder;
+mod client;
+mod interceptor;
+pub use builder::ExtAuthzInterceptorBuilder;
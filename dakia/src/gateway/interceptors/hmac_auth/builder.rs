@@ -0,0 +1,168 @@
+use std::{sync::Arc, time::Duration};
+
+use crate::{
+    config::source_config::InterceptorConfig,
+    error::{DakiaError, DakiaResult},
+    gateway::{interceptor::Interceptor, interceptor_builder::InterceptorBuilder},
+    proxy::http::MAX_DS_REQ_BODY_SIZE,
+    qe::query::{
+        extract_key_i64_or_err, extract_key_str_or_err, extract_string_or_err, extract_vec_or_err,
+        Composite, Query, Value,
+    },
+};
+
+use super::{
+    interceptor::{HmacAuthInterceptor, SignatureHeaders},
+    signature::{HmacKey, SignatureEncoding, SignedPart},
+};
+
+const DEFAULT_MAX_CLOCK_SKEW: Duration = Duration::from_secs(300);
+const DEFAULT_SIGNED_PARTS: [&str; 5] = ["method", "path", "query", "timestamp", "body_digest"];
+
+#[derive(Default)]
+pub struct HmacAuthInterceptorBuilder {}
+
+impl HmacAuthInterceptorBuilder {
+    fn extract_string(config: &Query, key: &str) -> DakiaResult<Option<String>> {
+        config.get(key).map(extract_string_or_err).transpose()
+    }
+
+    fn extract_header_name(config: &Query, key: &str, default: &str) -> DakiaResult<String> {
+        Ok(Self::extract_string(config, key)?
+            .unwrap_or(default.to_string())
+            .to_ascii_lowercase())
+    }
+
+    fn extract_keys(config: &Query) -> DakiaResult<Vec<HmacKey>> {
+        let mut keys = vec![];
+        if let Some(secret) = Self::extract_string(config, "secret")? {
+            keys.push(HmacKey {
+                id: None,
+                secret: secret.into_bytes(),
+            });
+        }
+
+        let key_configs = match config.get("keys") {
+            Some(keys) => extract_vec_or_err(keys)?.as_slice(),
+            None => &[],
+        };
+        for key_config in key_configs {
+            let key_config = match key_config {
+                Value::Composite(Composite::Map(key_config)) => key_config,
+                _ => {
+                    return Err(DakiaError::i_explain(format!(
+                        "keys must be a list of id and secret, found {:?}",
+                        key_config
+                    )))
+                }
+            };
+
+            keys.push(HmacKey {
+                id: Some(extract_key_str_or_err(key_config, "id")?.to_string()),
+                secret: extract_key_str_or_err(key_config, "secret")?
+                    .as_bytes()
+                    .to_vec(),
+            });
+        }
+
+        if keys.is_empty() {
+            return Err(DakiaError::i_explain(
+                "secret or keys must be configured".to_string(),
+            ));
+        }
+        Ok(keys)
+    }
+
+    fn extract_encoding(config: &Query) -> DakiaResult<SignatureEncoding> {
+        match Self::extract_string(config, "signature_encoding")?.as_deref() {
+            None | Some("hex") => Ok(SignatureEncoding::Hex),
+            Some("base64") => Ok(SignatureEncoding::Base64),
+            Some(encoding) => Err(DakiaError::i_explain(format!(
+                "unknown signature_encoding {encoding}, expected hex or base64"
+            ))),
+        }
+    }
+
+    // timestamp and nonce must be signed, otherwise they can be altered to replay a request
+    fn extract_signed_parts(config: &Query, is_nonce_used: bool) -> DakiaResult<Vec<SignedPart>> {
+        let signed_parts: Vec<SignedPart> = match config.get("signed_parts") {
+            Some(signed_parts) => extract_vec_or_err(signed_parts)?
+                .iter()
+                .map(|signed_part| {
+                    SignedPart::try_from(extract_string_or_err(signed_part)?.as_str())
+                })
+                .collect::<DakiaResult<_>>()?,
+            None => DEFAULT_SIGNED_PARTS
+                .iter()
+                .map(|signed_part| SignedPart::try_from(*signed_part))
+                .collect::<DakiaResult<_>>()?,
+        };
+
+        if !signed_parts.contains(&SignedPart::Timestamp) {
+            return Err(DakiaError::i_explain(
+                "signed_parts must contain timestamp".to_string(),
+            ));
+        }
+        if is_nonce_used && !signed_parts.contains(&SignedPart::Nonce) {
+            return Err(DakiaError::i_explain(
+                "signed_parts must contain nonce if nonce_header is configured".to_string(),
+            ));
+        }
+        Ok(signed_parts)
+    }
+
+    // body is buffered before it's verified, so it's limited by retry buffer of pingora
+    fn extract_max_body_size(config: &Query) -> DakiaResult<usize> {
+        if !config.contains_key("max_body_size") {
+            return Ok(MAX_DS_REQ_BODY_SIZE);
+        }
+
+        let max_body_size = extract_key_i64_or_err(config, "max_body_size")? as usize;
+        if max_body_size > MAX_DS_REQ_BODY_SIZE {
+            return Err(DakiaError::i_explain(format!(
+                "max_body_size must not exceed {MAX_DS_REQ_BODY_SIZE} bytes"
+            )));
+        }
+        Ok(max_body_size)
+    }
+}
+
+impl InterceptorBuilder for HmacAuthInterceptorBuilder {
+    fn build(&self, _interceptor_config: InterceptorConfig) -> DakiaResult<Arc<dyn Interceptor>> {
+        let config = _interceptor_config
+            .config
+            .as_ref()
+            .ok_or(DakiaError::i_explain(format!(
+                "{:?} interceptor config not found.",
+                _interceptor_config.name
+            )))?;
+
+        let headers = SignatureHeaders {
+            signature: Self::extract_header_name(config, "signature_header", "x-signature")?,
+            signature_prefix: Self::extract_string(config, "signature_prefix")?.unwrap_or_default(),
+            key_id: Self::extract_header_name(config, "key_id_header", "x-key-id")?,
+            timestamp: Self::extract_header_name(config, "timestamp_header", "x-timestamp")?,
+            nonce: Self::extract_string(config, "nonce_header")?
+                .map(|nonce_header| nonce_header.to_ascii_lowercase()),
+        };
+
+        let max_clock_skew = if config.contains_key("max_clock_skew") {
+            Duration::from_millis(extract_key_i64_or_err(config, "max_clock_skew")? as u64)
+        } else {
+            DEFAULT_MAX_CLOCK_SKEW
+        };
+
+        let signed_parts = Self::extract_signed_parts(config, headers.nonce.is_some())?;
+        let interceptor = HmacAuthInterceptor::build(
+            _interceptor_config.filter.clone(),
+            Self::extract_keys(config)?,
+            headers,
+            Self::extract_encoding(config)?,
+            signed_parts,
+            Self::extract_string(config, "separator")?.unwrap_or("\n".to_string()),
+            max_clock_skew,
+            Self::extract_max_body_size(config)?,
+        );
+        Ok(Arc::new(interceptor))
+    }
+}
@@ -0,0 +1,223 @@
+use std::{
+    str::from_utf8,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use http::StatusCode;
+use log::debug;
+
+use crate::{
+    error::DakiaResult,
+    gateway::interceptor::{Interceptor, InterceptorName, Phase, PhaseMask, PhaseResult},
+    proxy::http::Session,
+};
+
+use super::signature::{
+    body_digest, find_signing_key, shared_replay_guard, HmacKey, ReplayGuard, SignatureEncoding,
+    SignedPart,
+};
+
+// headers carrying signature and its metadata
+pub struct SignatureHeaders {
+    pub signature: String,
+    // stripped from signature before decoding, like "sha256="
+    pub signature_prefix: String,
+    pub key_id: String,
+    pub timestamp: String,
+    // replays are detected by nonce if it's configured, by signature otherwise
+    pub nonce: Option<String>,
+}
+
+// verifies hmac-sha256 signature of canonical string built from configured parts of request
+pub struct HmacAuthInterceptor {
+    filter: Option<String>,
+    keys: Vec<HmacKey>,
+    headers: SignatureHeaders,
+    encoding: SignatureEncoding,
+    signed_parts: Vec<SignedPart>,
+    separator: String,
+    // timestamp of request can differ from clock of gateway by this much
+    max_clock_skew: Duration,
+    max_body_size: usize,
+    replay_guard: Arc<ReplayGuard>,
+}
+
+impl HmacAuthInterceptor {
+    #[allow(clippy::too_many_arguments)]
+    pub fn build(
+        filter: Option<String>,
+        keys: Vec<HmacKey>,
+        headers: SignatureHeaders,
+        encoding: SignatureEncoding,
+        signed_parts: Vec<SignedPart>,
+        separator: String,
+        max_clock_skew: Duration,
+        max_body_size: usize,
+    ) -> Self {
+        Self {
+            filter,
+            replay_guard: shared_replay_guard(&keys),
+            keys,
+            headers,
+            encoding,
+            signed_parts,
+            separator,
+            max_clock_skew,
+            max_body_size,
+        }
+    }
+
+    fn header<'s>(session: &'s Session, header_name: &str) -> Option<&'s str> {
+        let header_value = session.ds_req_header(header_name).ok()??;
+        from_utf8(header_value).ok().map(str::trim)
+    }
+
+    fn write_unauthorized(&self, session: &mut Session, reason: &str) -> PhaseResult {
+        debug!("rejecting signed request - {reason}");
+        session.set_res_status(StatusCode::UNAUTHORIZED);
+        Ok(true)
+    }
+
+    // body is buffered to be signed, request with body which can not be buffered is rejected
+    async fn body(&self, session: &mut Session<'_>) -> DakiaResult<Option<Bytes>> {
+        if session.ds_req_body().is_none() {
+            session.buffer_ds_req_body(self.max_body_size).await?;
+        }
+        if let Some(body) = session.ds_req_body() {
+            return Ok(Some(body.clone()));
+        }
+
+        let has_body = session.ds_req_header("transfer-encoding")?.is_some()
+            || Self::header(session, "content-length").is_some_and(|length| length != "0");
+        Ok((!has_body).then(Bytes::new))
+    }
+
+    fn canonical_string(
+        &self,
+        session: &Session,
+        timestamp: &str,
+        nonce: &str,
+        body: &[u8],
+    ) -> DakiaResult<Vec<u8>> {
+        let mut canonical = vec![];
+        for (index, signed_part) in self.signed_parts.iter().enumerate() {
+            if index > 0 {
+                canonical.extend_from_slice(self.separator.as_bytes());
+            }
+
+            match signed_part {
+                SignedPart::Method => {
+                    canonical.extend_from_slice(session.ds_req_method()?.as_bytes())
+                }
+                SignedPart::Path => canonical.extend_from_slice(session.ds_req_path().as_bytes()),
+                SignedPart::Query => canonical
+                    .extend_from_slice(session.ds_req_query()?.unwrap_or_default().as_bytes()),
+                SignedPart::Timestamp => canonical.extend_from_slice(timestamp.as_bytes()),
+                SignedPart::Nonce => canonical.extend_from_slice(nonce.as_bytes()),
+                SignedPart::Header(header_name) => {
+                    let header_value = Self::header(session, header_name).unwrap_or_default();
+                    canonical.extend_from_slice(format!("{header_name}:{header_value}").as_bytes());
+                }
+                SignedPart::Body => canonical.extend_from_slice(body),
+                SignedPart::BodyDigest => canonical.extend_from_slice(body_digest(body).as_bytes()),
+            }
+        }
+        Ok(canonical)
+    }
+}
+
+#[async_trait]
+impl Interceptor for HmacAuthInterceptor {
+    fn name(&self) -> InterceptorName {
+        InterceptorName::HmacAuth
+    }
+
+    fn phase_mask(&self) -> PhaseMask {
+        Phase::RequestFilter.mask()
+    }
+
+    fn filter(&self) -> &Option<String> {
+        &self.filter
+    }
+
+    async fn request_filter(&self, _session: &mut Session) -> PhaseResult {
+        let signature = match Self::header(_session, &self.headers.signature)
+            .and_then(|signature| signature.strip_prefix(self.headers.signature_prefix.as_str()))
+            .and_then(|signature| self.encoding.decode(signature))
+        {
+            Some(signature) => signature,
+            None => return self.write_unauthorized(_session, "missing or malformed signature"),
+        };
+
+        let timestamp = Self::header(_session, &self.headers.timestamp)
+            .unwrap_or_default()
+            .to_string();
+        // timestamp is in seconds, it's compared in millis as clock skew can be configured in millis
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+        let max_clock_skew = self.max_clock_skew.as_millis() as u64;
+        let timestamp_millis = match timestamp
+            .parse::<u64>()
+            .ok()
+            .and_then(|timestamp_secs| timestamp_secs.checked_mul(1000))
+        {
+            Some(timestamp_millis) if timestamp_millis.abs_diff(now) <= max_clock_skew => {
+                timestamp_millis
+            }
+            _ => return self.write_unauthorized(_session, "missing or stale timestamp"),
+        };
+
+        let nonce = match &self.headers.nonce {
+            Some(nonce_header) => match Self::header(_session, nonce_header) {
+                Some(nonce) if !nonce.is_empty() => Some(nonce.to_string()),
+                _ => return self.write_unauthorized(_session, "missing nonce"),
+            },
+            None => None,
+        };
+
+        let body = if self.signed_parts.iter().any(SignedPart::requires_body) {
+            match self.body(_session).await? {
+                Some(body) => body,
+                None => {
+                    _session.set_res_status(StatusCode::PAYLOAD_TOO_LARGE);
+                    return Ok(true);
+                }
+            }
+        } else {
+            Bytes::new()
+        };
+
+        let canonical = self.canonical_string(
+            _session,
+            &timestamp,
+            nonce.as_deref().unwrap_or_default(),
+            &body,
+        )?;
+        let key_id = Self::header(_session, &self.headers.key_id);
+        let key = match find_signing_key(&self.keys, key_id, &canonical, &signature) {
+            Some(key) => key,
+            None => return self.write_unauthorized(_session, "invalid signature"),
+        };
+
+        // request is remembered till its timestamp turns stale, afterwards it's rejected as stale
+        let mut identity = key.id.clone().unwrap_or_default().into_bytes();
+        identity.push(0);
+        match &nonce {
+            Some(nonce) => identity.extend_from_slice(nonce.as_bytes()),
+            None => identity.extend_from_slice(&signature),
+        }
+        if !self
+            .replay_guard
+            .check(&identity, timestamp_millis + max_clock_skew + 1, now)
+        {
+            return self.write_unauthorized(_session, "replayed request");
+        }
+
+        Ok(false)
+    }
+}
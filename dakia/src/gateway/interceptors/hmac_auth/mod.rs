@@ -0,0 +1,4 @@
+mod builder;
+mod interceptor;
+mod signature;
+pub use builder::HmacAuthInterceptorBuilder;
@@ -0,0 +1,292 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex, PoisonError, Weak},
+};
+
+use base64::{engine::general_purpose, Engine};
+use dashmap::DashMap;
+use hmac::{Hmac, Mac};
+use once_cell::sync::Lazy;
+use sha2::{Digest, Sha256};
+
+use crate::error::DakiaError;
+
+type HmacSha256 = Hmac<Sha256>;
+
+// signatures or nonces seen within allowed clock skew are remembered to reject replays,
+// oldest of them are forgotten beyond this
+const MAX_SEEN_REQUESTS: usize = 1_000_000;
+
+// replay guards keyed by digest of signing keys of their interceptors
+// they live outside of gateway state, so that rebuilding gateway state doesn't forget seen requests
+static REPLAY_GUARDS: Lazy<DashMap<[u8; 32], Weak<ReplayGuard>>> = Lazy::new(DashMap::new);
+
+// part of request included in canonical string
+#[derive(Debug, Clone, PartialEq)]
+pub enum SignedPart {
+    Method,
+    Path,
+    Query,
+    Timestamp,
+    Nonce,
+    // included as "name:value"
+    Header(String),
+    Body,
+    // lowercase hex of sha256 of body
+    BodyDigest,
+}
+
+impl TryFrom<&str> for SignedPart {
+    type Error = Box<crate::error::Error>;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let signed_part = match value {
+            "method" => SignedPart::Method,
+            "path" => SignedPart::Path,
+            "query" => SignedPart::Query,
+            "timestamp" => SignedPart::Timestamp,
+            "nonce" => SignedPart::Nonce,
+            "body" => SignedPart::Body,
+            "body_digest" => SignedPart::BodyDigest,
+            value => match value.strip_prefix("header.") {
+                Some(header_name) => SignedPart::Header(header_name.to_ascii_lowercase()),
+                None => {
+                    return Err(DakiaError::i_explain(format!(
+                        "unknown signed part {value}, expected method, path, query, timestamp, nonce, body, body_digest or header.<name>"
+                    )))
+                }
+            },
+        };
+        Ok(signed_part)
+    }
+}
+
+impl SignedPart {
+    pub fn requires_body(&self) -> bool {
+        matches!(self, SignedPart::Body | SignedPart::BodyDigest)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SignatureEncoding {
+    Hex,
+    Base64,
+}
+
+impl SignatureEncoding {
+    pub fn decode(&self, signature: &str) -> Option<Vec<u8>> {
+        match self {
+            SignatureEncoding::Hex => hex::decode(signature).ok(),
+            SignatureEncoding::Base64 => general_purpose::STANDARD.decode(signature).ok(),
+        }
+    }
+}
+
+pub struct HmacKey {
+    // key without id is tried for every request
+    pub id: Option<String>,
+    pub secret: Vec<u8>,
+}
+
+// returns key whose hmac of canonical string matches signature, multiple keys of same id allow rotation
+pub fn find_signing_key<'a>(
+    keys: &'a [HmacKey],
+    key_id: Option<&str>,
+    canonical: &[u8],
+    signature: &[u8],
+) -> Option<&'a HmacKey> {
+    keys.iter()
+        .filter(|key| key.id.is_none() || key.id.as_deref() == key_id)
+        .find(|key| {
+            // safe to unwrap, hmac accepts key of any length
+            let mut mac = HmacSha256::new_from_slice(&key.secret).unwrap();
+            mac.update(canonical);
+            // comparison is constant time
+            mac.verify_slice(signature).is_ok()
+        })
+}
+
+pub fn body_digest(body: &[u8]) -> String {
+    hex::encode(Sha256::digest(body))
+}
+
+#[derive(Default)]
+struct SeenRequests {
+    // unix time in millis till which request is remembered, keyed by hash of its identity
+    seen_till: HashMap<[u8; 32], u64>,
+    // requests in order they were seen, with time till which they were remembered
+    order: VecDeque<([u8; 32], u64)>,
+}
+
+pub struct ReplayGuard {
+    max_seen_requests: usize,
+    seen_requests: Mutex<SeenRequests>,
+}
+
+impl Default for ReplayGuard {
+    fn default() -> Self {
+        Self::build(MAX_SEEN_REQUESTS)
+    }
+}
+
+impl ReplayGuard {
+    pub fn build(max_seen_requests: usize) -> Self {
+        Self {
+            max_seen_requests,
+            seen_requests: Mutex::new(SeenRequests::default()),
+        }
+    }
+
+    // returns false if request was seen before, times are unix time in millis
+    // expired requests are forgotten in order they were seen, oldest requests are evicted if too many are remembered
+    pub fn check(&self, identity: &[u8], expires_at: u64, now: u64) -> bool {
+        let identity: [u8; 32] = Sha256::digest(identity).into();
+        let mut seen_requests = self
+            .seen_requests
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if seen_requests
+            .seen_till
+            .get(&identity)
+            .is_some_and(|seen_till| *seen_till > now)
+        {
+            return false;
+        }
+
+        seen_requests.seen_till.insert(identity, expires_at);
+        seen_requests.order.push_back((identity, expires_at));
+
+        while let Some(&(oldest, oldest_seen_till)) = seen_requests.order.front() {
+            if oldest_seen_till > now && seen_requests.order.len() <= self.max_seen_requests {
+                break;
+            }
+
+            seen_requests.order.pop_front();
+            // request seen again later is remembered till its latest expiry
+            if seen_requests.seen_till.get(&oldest) == Some(&oldest_seen_till) {
+                seen_requests.seen_till.remove(&oldest);
+            }
+        }
+        true
+    }
+}
+
+// interceptors with same signing keys accept same signatures, so they share replay guard
+// guard is dropped with the last interceptor using it
+pub fn shared_replay_guard(keys: &[HmacKey]) -> Arc<ReplayGuard> {
+    let mut hasher = Sha256::new();
+    for key in keys {
+        let id = key.id.as_deref().unwrap_or_default();
+        hasher.update([key.id.is_some() as u8]);
+        for part in [id.as_bytes(), &key.secret] {
+            hasher.update((part.len() as u64).to_be_bytes());
+            hasher.update(part);
+        }
+    }
+    let keys_digest: [u8; 32] = hasher.finalize().into();
+
+    REPLAY_GUARDS.retain(|_, replay_guard| replay_guard.strong_count() > 0);
+    let mut replay_guard = REPLAY_GUARDS.entry(keys_digest).or_default();
+    match replay_guard.upgrade() {
+        Some(replay_guard) => replay_guard,
+        None => {
+            let new_replay_guard = Arc::new(ReplayGuard::default());
+            *replay_guard = Arc::downgrade(&new_replay_guard);
+            new_replay_guard
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_signing_key() {
+        let keys = vec![
+            HmacKey {
+                id: Some("partner-a".to_string()),
+                secret: b"old".to_vec(),
+            },
+            HmacKey {
+                id: Some("partner-a".to_string()),
+                secret: b"new".to_vec(),
+            },
+            HmacKey {
+                id: Some("partner-b".to_string()),
+                secret: b"other".to_vec(),
+            },
+        ];
+
+        let canonical = b"POST\n/orders\n1700000000\n";
+        let signature = SignatureEncoding::Hex
+            .decode(&hex::encode(
+                HmacSha256::new_from_slice(b"new")
+                    .unwrap()
+                    .chain_update(canonical)
+                    .finalize()
+                    .into_bytes(),
+            ))
+            .unwrap();
+
+        let key = find_signing_key(&keys, Some("partner-a"), canonical, &signature).unwrap();
+        assert_eq!(key.secret, b"new");
+        assert!(find_signing_key(&keys, Some("partner-b"), canonical, &signature).is_none());
+        assert!(find_signing_key(&keys, None, canonical, &signature).is_none());
+        assert!(find_signing_key(&keys, Some("partner-a"), b"GET", &signature).is_none());
+    }
+
+    #[test]
+    fn test_replay_guard() {
+        let replay_guard = ReplayGuard::default();
+        assert!(replay_guard.check(b"signature", 1300, 1000));
+        assert!(!replay_guard.check(b"signature", 1300, 1200));
+        // remembered request expires with its timestamp
+        assert!(replay_guard.check(b"signature", 1600, 1300));
+        assert!(replay_guard.check(b"other", 1300, 1000));
+    }
+
+    #[test]
+    fn test_replay_guard_eviction() {
+        let replay_guard = ReplayGuard::build(2);
+        assert!(replay_guard.check(b"first", 1300, 1000));
+        assert!(replay_guard.check(b"second", 1300, 1000));
+        // guard is full, oldest request is evicted instead of rejecting new ones
+        assert!(replay_guard.check(b"third", 1300, 1000));
+        assert!(replay_guard.check(b"first", 1300, 1000));
+        assert!(!replay_guard.check(b"third", 1300, 1000));
+    }
+
+    #[test]
+    fn test_shared_replay_guard() {
+        let keys = |secret: &str| {
+            vec![HmacKey {
+                id: Some("partner-a".to_string()),
+                secret: secret.as_bytes().to_vec(),
+            }]
+        };
+
+        let replay_guard = shared_replay_guard(&keys("shared"));
+        assert!(replay_guard.check(b"signature", 1300, 1000));
+        // interceptor rebuilt with same keys remembers requests seen by previous one
+        assert!(Arc::ptr_eq(
+            &replay_guard,
+            &shared_replay_guard(&keys("shared"))
+        ));
+        assert!(!shared_replay_guard(&keys("shared")).check(b"signature", 1300, 1000));
+        assert!(shared_replay_guard(&keys("other")).check(b"signature", 1300, 1000));
+
+        drop(replay_guard);
+        assert!(shared_replay_guard(&keys("shared")).check(b"signature", 1300, 1000));
+    }
+
+    #[test]
+    fn test_signed_part() {
+        assert_eq!(
+            SignedPart::try_from("header.X-Date").unwrap(),
+            SignedPart::Header("x-date".to_string())
+        );
+        assert!(SignedPart::try_from("body_digest").unwrap().requires_body());
+        assert!(SignedPart::try_from("host").is_err());
+    }
+}
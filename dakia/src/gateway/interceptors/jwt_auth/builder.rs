@@ -0,0 +1,148 @@
+use std::{sync::Arc, time::Duration};
+
+use crate::{
+    config::source_config::InterceptorConfig,
+    error::{DakiaError, DakiaResult},
+    gateway::{interceptor::Interceptor, interceptor_builder::InterceptorBuilder},
+    qe::query::{extract_key_i64_or_err, extract_string_or_err, Composite, Query, Value},
+};
+
+use super::{
+    interceptor::JwtAuthInterceptor,
+    jwks::JwksCache,
+    jwt::{Algorithm, Jwk, JwtValidation, VerificationKey},
+};
+
+const DEFAULT_JWKS_REFRESH_INTERVAL: Duration = Duration::from_secs(3600);
+
+#[derive(Default)]
+pub struct JwtAuthInterceptorBuilder {}
+
+impl JwtAuthInterceptorBuilder {
+    fn extract_string(config: &Query, key: &str) -> DakiaResult<Option<String>> {
+        config.get(key).map(extract_string_or_err).transpose()
+    }
+
+    // value can be a single string or a list of strings
+    fn extract_strings(config: &Query, key: &str) -> DakiaResult<Vec<String>> {
+        match config.get(key) {
+            None => Ok(vec![]),
+            Some(Value::Composite(Composite::Vector(values))) => {
+                values.iter().map(extract_string_or_err).collect()
+            }
+            Some(value) => Ok(vec![extract_string_or_err(value)?]),
+        }
+    }
+
+    fn extract_duration(config: &Query, key: &str) -> DakiaResult<Option<Duration>> {
+        if !config.contains_key(key) {
+            return Ok(None);
+        }
+
+        let millis = extract_key_i64_or_err(config, key)?;
+        Ok(Some(Duration::from_millis(millis as u64)))
+    }
+
+    fn extract_keys(config: &Query) -> DakiaResult<Vec<Jwk>> {
+        let mut keys = vec![];
+        let kid = Self::extract_string(config, "kid")?;
+
+        if let Some(secret) = Self::extract_string(config, "secret")? {
+            keys.push(Jwk {
+                kid: kid.clone(),
+                key: VerificationKey::from_secret(secret.as_bytes()),
+            });
+        }
+
+        if let Some(public_key) = Self::extract_string(config, "public_key")? {
+            keys.push(Jwk {
+                kid: kid.clone(),
+                key: VerificationKey::from_public_key_pem(&public_key)?,
+            });
+        }
+
+        if let Some(public_key_file) = Self::extract_string(config, "public_key_file")? {
+            let public_key = std::fs::read_to_string(&public_key_file).map_err(|e| {
+                DakiaError::i_explain(format!("failed to read {public_key_file} - {e}"))
+            })?;
+            keys.push(Jwk {
+                kid,
+                key: VerificationKey::from_public_key_pem(&public_key)?,
+            });
+        }
+
+        Ok(keys)
+    }
+
+    // all supported algorithms are allowed by default, a key is used only for algorithm of its type
+    fn extract_algorithms(config: &Query) -> DakiaResult<Vec<Algorithm>> {
+        let algorithms = Self::extract_strings(config, "algorithms")?;
+        if algorithms.is_empty() {
+            return Ok(vec![Algorithm::HS256, Algorithm::RS256, Algorithm::ES256]);
+        }
+
+        algorithms
+            .iter()
+            .map(|algorithm| Algorithm::try_from(algorithm.as_str()))
+            .collect()
+    }
+}
+
+pub fn extract_claims_to_headers(config: &Query) -> DakiaResult<Vec<(String, String)>> {
+    match config.get("claims_to_headers") {
+        None => Ok(vec![]),
+        Some(Value::Composite(Composite::Map(claims_to_headers))) => claims_to_headers
+            .iter()
+            .map(|(claim, header)| Ok((claim.clone(), extract_string_or_err(header)?)))
+            .collect(),
+        Some(value) => Err(DakiaError::i_explain(format!(
+            "claims_to_headers must be a map of claim to header, found {:?}",
+            value
+        ))),
+    }
+}
+
+impl InterceptorBuilder for JwtAuthInterceptorBuilder {
+    fn build(&self, _interceptor_config: InterceptorConfig) -> DakiaResult<Arc<dyn Interceptor>> {
+        let config = _interceptor_config
+            .config
+            .as_ref()
+            .ok_or(DakiaError::i_explain(format!(
+                "{:?} interceptor config not found.",
+                _interceptor_config.name
+            )))?;
+
+        let keys = Self::extract_keys(config)?;
+        let jwks_cache = match Self::extract_string(config, "jwks_url")? {
+            Some(jwks_url) => {
+                let refresh_interval = Self::extract_duration(config, "jwks_refresh_interval")?
+                    .unwrap_or(DEFAULT_JWKS_REFRESH_INTERVAL);
+                Some(JwksCache::build(jwks_url, refresh_interval))
+            }
+            None => None,
+        };
+
+        if keys.is_empty() && jwks_cache.is_none() {
+            return Err(DakiaError::i_explain(format!(
+                "{:?} interceptor requires secret, public_key, public_key_file or jwks_url",
+                _interceptor_config.name
+            )));
+        }
+
+        let validation = JwtValidation {
+            algorithms: Self::extract_algorithms(config)?,
+            issuers: Self::extract_strings(config, "issuer")?,
+            audiences: Self::extract_strings(config, "audience")?,
+            clock_skew: Self::extract_duration(config, "clock_skew")?.unwrap_or_default(),
+        };
+
+        let interceptor = JwtAuthInterceptor::build(
+            _interceptor_config.filter.clone(),
+            keys,
+            jwks_cache,
+            validation,
+            extract_claims_to_headers(config)?,
+        );
+        Ok(Arc::new(interceptor))
+    }
+}
@@ -0,0 +1,148 @@
+use std::{
+    str::from_utf8,
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use async_trait::async_trait;
+use http::StatusCode;
+use log::debug;
+use serde_json::Value;
+
+use crate::{
+    error::DakiaResult,
+    gateway::interceptor::{Interceptor, InterceptorName, Phase, PhaseMask, PhaseResult},
+    proxy::http::Session,
+};
+
+use super::{
+    jwks::JwksCache,
+    jwt::{Jwk, Jwt, JwtValidation},
+};
+
+pub fn bearer_token<'s>(session: &'s Session) -> Option<&'s str> {
+    let authorization = session.ds_req_header("Authorization").ok()??;
+    let (scheme, token) = from_utf8(authorization).ok()?.split_once(' ')?;
+    if !scheme.eq_ignore_ascii_case("bearer") {
+        return None;
+    }
+    Some(token.trim())
+}
+
+// claims are forwarded as headers, header of missing claim is removed so that client can not set it
+pub fn forward_claims(
+    session: &mut Session,
+    claims_to_headers: &[(String, String)],
+) -> DakiaResult<()> {
+    for (claim, header) in claims_to_headers {
+        let claim_value = match session.jwt_claim(claim) {
+            None | Some(Value::Null) => None,
+            Some(Value::String(claim_value)) => Some(claim_value.clone()),
+            Some(claim_value) => Some(claim_value.to_string()),
+        };
+
+        match claim_value {
+            Some(claim_value) => {
+                session.set_us_req_header(header.clone(), claim_value.into_bytes())
+            }
+            None => session.remove_us_req_header(header)?,
+        }
+    }
+
+    Ok(())
+}
+
+pub struct JwtAuthInterceptor {
+    filter: Option<String>,
+    keys: Vec<Jwk>,
+    jwks_cache: Option<JwksCache>,
+    validation: JwtValidation,
+    // claims are forwarded to upstream as headers, pairs of claim and header name
+    claims_to_headers: Vec<(String, String)>,
+}
+
+impl JwtAuthInterceptor {
+    pub fn build(
+        filter: Option<String>,
+        keys: Vec<Jwk>,
+        jwks_cache: Option<JwksCache>,
+        validation: JwtValidation,
+        claims_to_headers: Vec<(String, String)>,
+    ) -> Self {
+        Self {
+            filter,
+            keys,
+            jwks_cache,
+            validation,
+            claims_to_headers,
+        }
+    }
+
+    fn write_unauthorized(&self, session: &mut Session, is_token_present: bool) -> PhaseResult {
+        let challenge = if is_token_present {
+            "Bearer error=\"invalid_token\""
+        } else {
+            "Bearer"
+        };
+
+        session.set_res_status(StatusCode::UNAUTHORIZED);
+        session.set_ds_res_header(
+            "WWW-Authenticate".to_string(),
+            challenge.as_bytes().to_vec(),
+        );
+        Ok(true)
+    }
+}
+
+#[async_trait]
+impl Interceptor for JwtAuthInterceptor {
+    fn name(&self) -> InterceptorName {
+        InterceptorName::JwtAuth
+    }
+
+    fn phase_mask(&self) -> PhaseMask {
+        Phase::RequestFilter.mask() | Phase::PreUpstreamRequest.mask()
+    }
+
+    fn filter(&self) -> &Option<String> {
+        &self.filter
+    }
+
+    async fn request_filter(&self, _session: &mut Session) -> PhaseResult {
+        let jwt = match bearer_token(_session).map(Jwt::decode) {
+            Some(Ok(jwt)) => jwt,
+            Some(Err(e)) => {
+                debug!("rejecting malformed jwt - {:?}", e);
+                return self.write_unauthorized(_session, true);
+            }
+            None => return self.write_unauthorized(_session, false),
+        };
+
+        let jwks_keys = match &self.jwks_cache {
+            Some(jwks_cache) => jwks_cache.keys(jwt.kid()).await?,
+            None => Arc::default(),
+        };
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs_f64();
+        let keys = self.keys.iter().chain(jwks_keys.iter());
+
+        match jwt.validate(keys, &self.validation, now) {
+            Ok(claims) => {
+                _session.set_jwt_claims(claims);
+                Ok(false)
+            }
+            Err(e) => {
+                debug!("rejecting jwt - {:?}", e);
+                self.write_unauthorized(_session, true)
+            }
+        }
+    }
+
+    async fn pre_upstream_request(&self, _session: &mut Session) -> PhaseResult {
+        forward_claims(_session, &self.claims_to_headers)?;
+        Ok(false)
+    }
+}
@@ -0,0 +1,370 @@
+use std::time::Duration;
+
+use base64::{engine::general_purpose, Engine};
+use jsonwebtoken::{crypto, DecodingKey};
+use serde_json::{Map, Value};
+
+use crate::error::{DakiaError, DakiaResult};
+
+pub type Claims = Map<String, Value>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Algorithm {
+    HS256,
+    RS256,
+    ES256,
+}
+
+impl TryFrom<&str> for Algorithm {
+    type Error = Box<crate::error::Error>;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "HS256" => Ok(Algorithm::HS256),
+            "RS256" => Ok(Algorithm::RS256),
+            "ES256" => Ok(Algorithm::ES256),
+            _ => Err(DakiaError::i_explain(format!(
+                "unsupported jwt algorithm {value}"
+            ))),
+        }
+    }
+}
+
+impl From<Algorithm> for jsonwebtoken::Algorithm {
+    fn from(algorithm: Algorithm) -> Self {
+        match algorithm {
+            Algorithm::HS256 => jsonwebtoken::Algorithm::HS256,
+            Algorithm::RS256 => jsonwebtoken::Algorithm::RS256,
+            Algorithm::ES256 => jsonwebtoken::Algorithm::ES256,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub enum VerificationKey {
+    Secret(DecodingKey),
+    Rsa(DecodingKey),
+    // key of P-256 curve
+    Ec(DecodingKey),
+}
+
+impl VerificationKey {
+    pub fn from_secret(secret: &[u8]) -> Self {
+        VerificationKey::Secret(DecodingKey::from_secret(secret))
+    }
+
+    // public key is pem encoded spki or pkcs1 rsa key, or spki ec key
+    pub fn from_public_key_pem(pem: &str) -> DakiaResult<Self> {
+        if let Ok(rsa_key) = DecodingKey::from_rsa_pem(pem.as_bytes()) {
+            return Ok(VerificationKey::Rsa(rsa_key));
+        }
+
+        DecodingKey::from_ec_pem(pem.as_bytes())
+            .map(VerificationKey::Ec)
+            .map_err(|e| DakiaError::i_explain(format!("invalid rsa or ec public key - {e}")))
+    }
+
+    // key is used only for algorithm of its own type, so public key can never be used as hmac secret
+    fn supports(&self, algorithm: Algorithm) -> bool {
+        matches!(
+            (self, algorithm),
+            (VerificationKey::Secret(_), Algorithm::HS256)
+                | (VerificationKey::Rsa(_), Algorithm::RS256)
+                | (VerificationKey::Ec(_), Algorithm::ES256)
+        )
+    }
+
+    // signature is base64url encoded, as in jwt
+    fn verify(&self, message: &[u8], signature: &str, algorithm: Algorithm) -> bool {
+        let key = match self {
+            VerificationKey::Secret(key) | VerificationKey::Rsa(key) | VerificationKey::Ec(key) => {
+                key
+            }
+        };
+        crypto::verify(signature, message, key, algorithm.into()).unwrap_or(false)
+    }
+}
+
+#[derive(Clone)]
+pub struct Jwk {
+    // key without id is tried for every token
+    pub kid: Option<String>,
+    pub key: VerificationKey,
+}
+
+pub struct JwtValidation {
+    pub algorithms: Vec<Algorithm>,
+    // claim must match any of the configured values, it's not validated if none is configured
+    pub issuers: Vec<String>,
+    pub audiences: Vec<String>,
+    pub clock_skew: Duration,
+}
+
+pub struct Jwt {
+    algorithm: Algorithm,
+    kid: Option<String>,
+    signing_input: String,
+    signature: String,
+    claims: Claims,
+}
+
+fn decode_json_object(encoded: &str) -> DakiaResult<Map<String, Value>> {
+    let json = general_purpose::URL_SAFE_NO_PAD
+        .decode(encoded)
+        .map_err(|_| DakiaError::i_explain("jwt is not base64url encoded"))?;
+
+    match serde_json::from_slice(&json) {
+        Ok(Value::Object(object)) => Ok(object),
+        _ => Err(DakiaError::i_explain("jwt must have json object")),
+    }
+}
+
+impl Jwt {
+    pub fn decode(token: &str) -> DakiaResult<Self> {
+        let mut parts = token.split('.');
+        let (header, payload, signature) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(header), Some(payload), Some(signature)) if parts.next().is_none() => {
+                (header, payload, signature)
+            }
+            _ => return Err(DakiaError::i_explain("jwt must have three parts")),
+        };
+
+        let jwt_header = decode_json_object(header)?;
+        let algorithm = match jwt_header.get("alg") {
+            Some(Value::String(algorithm)) => Algorithm::try_from(algorithm.as_str())?,
+            _ => return Err(DakiaError::i_explain("jwt header must have alg")),
+        };
+        let kid = match jwt_header.get("kid") {
+            Some(Value::String(kid)) => Some(kid.clone()),
+            _ => None,
+        };
+
+        Ok(Jwt {
+            algorithm,
+            kid,
+            signing_input: format!("{header}.{payload}"),
+            signature: signature.to_string(),
+            claims: decode_json_object(payload)?,
+        })
+    }
+
+    pub fn kid(&self) -> Option<&str> {
+        self.kid.as_deref()
+    }
+
+    // now is seconds since unix epoch, claims are returned if token is valid
+    pub fn validate<'k>(
+        self,
+        keys: impl IntoIterator<Item = &'k Jwk>,
+        validation: &JwtValidation,
+        now: f64,
+    ) -> DakiaResult<Claims> {
+        if !validation.algorithms.contains(&self.algorithm) {
+            return Err(DakiaError::i_explain(format!(
+                "jwt algorithm {:?} is not allowed",
+                self.algorithm
+            )));
+        }
+
+        let is_verified = keys
+            .into_iter()
+            .filter(|jwk| jwk.kid.is_none() || jwk.kid == self.kid)
+            .filter(|jwk| jwk.key.supports(self.algorithm))
+            .any(|jwk| {
+                jwk.key.verify(
+                    self.signing_input.as_bytes(),
+                    &self.signature,
+                    self.algorithm,
+                )
+            });
+        if !is_verified {
+            return Err(DakiaError::i_explain("jwt signature is invalid"));
+        }
+
+        let clock_skew = validation.clock_skew.as_secs_f64();
+        self.validate_time("exp", |exp| now <= exp + clock_skew)?;
+        self.validate_time("nbf", |nbf| now + clock_skew >= nbf)?;
+
+        if !validation.issuers.is_empty() {
+            let is_valid_issuer = match self.claims.get("iss") {
+                Some(Value::String(iss)) => validation.issuers.contains(iss),
+                _ => false,
+            };
+            if !is_valid_issuer {
+                return Err(DakiaError::i_explain("jwt issuer is invalid"));
+            }
+        }
+
+        if !validation.audiences.is_empty() {
+            // aud can be a single audience or a list of audiences
+            let is_valid_audience = match self.claims.get("aud") {
+                Some(Value::String(aud)) => validation.audiences.contains(aud),
+                Some(Value::Array(auds)) => auds.iter().any(|aud| match aud {
+                    Value::String(aud) => validation.audiences.contains(aud),
+                    _ => false,
+                }),
+                _ => false,
+            };
+            if !is_valid_audience {
+                return Err(DakiaError::i_explain("jwt audience is invalid"));
+            }
+        }
+
+        Ok(self.claims)
+    }
+
+    // time claims are optional, but must be numeric if present
+    fn validate_time(&self, claim: &str, is_valid: impl Fn(f64) -> bool) -> DakiaResult<()> {
+        match self.claims.get(claim) {
+            None => Ok(()),
+            Some(Value::Number(time)) => match time.as_f64() {
+                Some(time) if is_valid(time) => Ok(()),
+                _ => Err(DakiaError::i_explain(format!(
+                    "jwt {claim} is not satisfied"
+                ))),
+            },
+            Some(_) => Err(DakiaError::i_explain(format!(
+                "jwt {claim} must be numeric"
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use jsonwebtoken::EncodingKey;
+
+    use super::*;
+
+    const NOW: f64 = 1_700_000_000.0;
+
+    const RSA_PUBLIC_KEY: &str = "-----BEGIN PUBLIC KEY-----
+MIIBIjANBgkqhkiG9w0BAQEFAAOCAQ8AMIIBCgKCAQEA1eMjDcOH3r+HkOLUze6K
+YSEuwbhmf1YlH2+eswto/XktjHi0H5z/oyfZSGReQA4PSWsF9ylsaS0ldXQj53G+
+bWIZwNVek9BcFqO6QLCBd7Uo7C1Gu4T4t6sPbTK9BwL9b31h2lyZG3/Knq/FsvRR
+uMYF86yfC00QwppYW1H3qebE5hrMkX95LeubPbgVy3nK/Fl01kW5aNNMKGY6BlCU
+AegQbeiPGXPbMToUsryP0/H1vF6dODTHUn6ZpomWgiyKFr49O/b2ejBocFoLeO9H
+OxRLRJ8hUJ0aqJtsda5PWQ/jsocwXYiuKSqUHJHqH8q9D/6WOGFiTId2X2kd5tbp
+cQIDAQAB
+-----END PUBLIC KEY-----";
+
+    const RSA_SIGNATURE: &str = "A93N8xqa7LVWWfg_Fv2riYC9YieXEgBYtN4tB9IC5g5ljrLXkY3196FwvkXEsZSzQOyMi2tpiZa4ibA4cg6LQNVEjXm3m8zolv-zlJJskQkm7QkDa1opDtroZl5TztCxzSTj1zuvNgd0Vy5ocG19ixI6RWxE8ouNrtH8kvRGTmjytJQKaC0ovrzieWOv2kNzibAA4LqLKZo95Xysbbj2S01xrdZHfXhZ9gR0bMZnsjimAZeS5nH8eFuH-b2TafyoXECZJMWFZt_vy26CUJW4_glDRByxXB_f_JsQ_dOs13lm9guNYcBfd9Wg2-qcuuk0QlXbSmMjeyRcxVrFJFOLsg";
+
+    const EC_PUBLIC_KEY: &str = "-----BEGIN PUBLIC KEY-----
+MFkwEwYHKoZIzj0CAQYIKoZIzj0DAQcDQgAE1Y4gXlW9a24U09fpi0MEM/HA0ROW
+yds5BPqe+uKY1dAKHks8vZi0AGKMQhS7vfOBJo1dpD5R4dvS1qfMlvMyrw==
+-----END PUBLIC KEY-----";
+
+    const EC_SIGNATURE: &str =
+        "ILKcCk0He0-2zWLwxyG4u_Y9Htz6LoHA_KQx5b4eR0cFB68vQmEXWnr20otuhB7Uxgd1UsCTqzFDvHcC1GujNg";
+
+    fn encode(value: Value) -> String {
+        general_purpose::URL_SAFE_NO_PAD.encode(value.to_string())
+    }
+
+    fn hs256_token(secret: &[u8], header: Value, claims: Value) -> String {
+        let signing_input = format!("{}.{}", encode(header), encode(claims));
+        let signature = crypto::sign(
+            signing_input.as_bytes(),
+            &EncodingKey::from_secret(secret),
+            jsonwebtoken::Algorithm::HS256,
+        )
+        .unwrap();
+        format!("{signing_input}.{signature}")
+    }
+
+    fn validation() -> JwtValidation {
+        JwtValidation {
+            algorithms: vec![Algorithm::HS256, Algorithm::RS256, Algorithm::ES256],
+            issuers: vec!["dakia".to_string()],
+            audiences: vec!["api".to_string()],
+            clock_skew: Duration::from_secs(30),
+        }
+    }
+
+    fn validate(token: &str) -> DakiaResult<Claims> {
+        let keys = [Jwk {
+            kid: Some("k1".to_string()),
+            key: VerificationKey::from_secret(b"secret"),
+        }];
+        Jwt::decode(token)?.validate(&keys, &validation(), NOW)
+    }
+
+    #[test]
+    fn test_hs256_validation() {
+        let header = serde_json::json!({"alg": "HS256", "kid": "k1"});
+        let claims = serde_json::json!({
+            "sub": "dakia",
+            "iss": "dakia",
+            "aud": ["web", "api"],
+            "exp": NOW - 10.0,
+            "nbf": NOW + 10.0,
+        });
+        let token = hs256_token(b"secret", header.clone(), claims);
+        let claims = validate(&token).unwrap();
+        assert_eq!(claims["sub"], "dakia");
+
+        let tampered_token = token.replacen('.', ".e30", 1);
+        assert!(validate(&tampered_token).is_err());
+        assert!(validate(&hs256_token(
+            b"other",
+            header.clone(),
+            serde_json::json!({})
+        ))
+        .is_err());
+
+        let invalid_claims = [
+            serde_json::json!({"iss": "dakia", "aud": "api", "exp": NOW - 31.0}),
+            serde_json::json!({"iss": "dakia", "aud": "api", "nbf": NOW + 31.0}),
+            serde_json::json!({"iss": "dakia", "aud": "api", "exp": "never"}),
+            serde_json::json!({"iss": "other", "aud": "api"}),
+            serde_json::json!({"iss": "dakia", "aud": "web"}),
+            serde_json::json!({"iss": "dakia"}),
+        ];
+        for claims in invalid_claims {
+            assert!(validate(&hs256_token(b"secret", header.clone(), claims)).is_err());
+        }
+
+        // key of different id is not used
+        let header = serde_json::json!({"alg": "HS256", "kid": "k2"});
+        let claims = serde_json::json!({"iss": "dakia", "aud": "api"});
+        assert!(validate(&hs256_token(b"secret", header, claims)).is_err());
+    }
+
+    #[test]
+    fn test_unsupported_algorithm() {
+        let header = encode(serde_json::json!({"alg": "none"}));
+        let claims = encode(serde_json::json!({"iss": "dakia", "aud": "api"}));
+        assert!(validate(&format!("{header}.{claims}.")).is_err());
+
+        // secret key is not used for token signed using public key algorithm
+        let header = serde_json::json!({"alg": "RS256"});
+        let claims = serde_json::json!({"iss": "dakia", "aud": "api"});
+        assert!(validate(&hs256_token(b"secret", header, claims)).is_err());
+    }
+
+    #[test]
+    fn test_rsa_signature() {
+        let public_key = VerificationKey::from_public_key_pem(RSA_PUBLIC_KEY).unwrap();
+        assert!(matches!(public_key, VerificationKey::Rsa(_)));
+        assert!(public_key.verify(b"dakia", RSA_SIGNATURE, Algorithm::RS256));
+        assert!(!public_key.verify(b"dakia!", RSA_SIGNATURE, Algorithm::RS256));
+    }
+
+    #[test]
+    fn test_ec_signature() {
+        let public_key = VerificationKey::from_public_key_pem(EC_PUBLIC_KEY).unwrap();
+        assert!(matches!(public_key, VerificationKey::Ec(_)));
+        assert!(public_key.verify(b"dakia", EC_SIGNATURE, Algorithm::ES256));
+        assert!(!public_key.verify(b"dakia!", EC_SIGNATURE, Algorithm::ES256));
+
+        let mut signature = general_purpose::URL_SAFE_NO_PAD
+            .decode(EC_SIGNATURE)
+            .unwrap();
+        signature[63] ^= 1;
+        let signature = general_purpose::URL_SAFE_NO_PAD.encode(signature);
+        assert!(!public_key.verify(b"dakia", &signature, Algorithm::ES256));
+
+        assert!(VerificationKey::from_public_key_pem("dakia").is_err());
+    }
+}
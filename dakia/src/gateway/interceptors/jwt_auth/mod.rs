@@ -0,0 +1,8 @@
+mod builder;
+mod interceptor;
+mod jwks;
+mod jwt;
+pub use builder::{extract_claims_to_headers, JwtAuthInterceptorBuilder};
+pub use interceptor::{bearer_token, forward_claims};
+pub use jwks::JwksCache;
+pub use jwt::{Algorithm, Claims, Jwk, Jwt, JwtValidation, VerificationKey};
@@ -1,9 +1,16 @@
+pub mod api_key;
 pub mod basic_auth;
 pub mod controller;
+pub mod ext_authz;
+pub mod hmac_auth;
+pub mod jwt_auth;
+pub mod oauth2_introspect;
+pub mod oidc;
 pub mod rate_limiter;
 pub mod request_id;
 pub mod request_rewrite;
 pub mod response_rewrite;
 pub mod server_version;
 pub mod short_circuit;
+pub mod upstream_selector;
 pub mod use_file;
@@ -0,0 +1,73 @@
+use std::{sync::Arc, time::Duration};
+
+use crate::{
+    config::source_config::InterceptorConfig,
+    error::{DakiaError, DakiaResult},
+    gateway::{
+        interceptor::Interceptor, interceptor_builder::InterceptorBuilder,
+        interceptors::jwt_auth::extract_claims_to_headers,
+    },
+    qe::query::{extract_key_i64_or_err, extract_key_str_or_err, extract_string_or_err, Query},
+};
+
+use super::{
+    interceptor::OAuth2IntrospectInterceptor,
+    introspection::{ClientCredentials, Introspection},
+};
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+const DEFAULT_CACHE_TTL: Duration = Duration::from_secs(300);
+
+#[derive(Default)]
+pub struct OAuth2IntrospectInterceptorBuilder {}
+
+impl OAuth2IntrospectInterceptorBuilder {
+    fn extract_string(config: &Query, key: &str) -> DakiaResult<Option<String>> {
+        config.get(key).map(extract_string_or_err).transpose()
+    }
+
+    fn extract_duration(config: &Query, key: &str) -> DakiaResult<Option<Duration>> {
+        if !config.contains_key(key) {
+            return Ok(None);
+        }
+
+        let millis = extract_key_i64_or_err(config, key)?;
+        Ok(Some(Duration::from_millis(millis as u64)))
+    }
+}
+
+impl InterceptorBuilder for OAuth2IntrospectInterceptorBuilder {
+    fn build(&self, _interceptor_config: InterceptorConfig) -> DakiaResult<Arc<dyn Interceptor>> {
+        let config = _interceptor_config
+            .config
+            .as_ref()
+            .ok_or(DakiaError::i_explain(format!(
+                "{:?} interceptor config not found.",
+                _interceptor_config.name
+            )))?;
+
+        let introspection_url = extract_key_str_or_err(config, "introspection_url")?;
+        // authorization server authenticates gateway with client credentials
+        let credentials = match Self::extract_string(config, "client_id")? {
+            Some(client_id) => Some(ClientCredentials {
+                client_id,
+                client_secret: extract_key_str_or_err(config, "client_secret")?.to_string(),
+            }),
+            None => None,
+        };
+
+        let introspection = Introspection::build(
+            introspection_url.to_string(),
+            credentials,
+            Self::extract_duration(config, "timeout")?.unwrap_or(DEFAULT_TIMEOUT),
+            Self::extract_duration(config, "cache_ttl")?.unwrap_or(DEFAULT_CACHE_TTL),
+        );
+
+        let interceptor = OAuth2IntrospectInterceptor::build(
+            _interceptor_config.filter.clone(),
+            introspection,
+            extract_claims_to_headers(config)?,
+        );
+        Ok(Arc::new(interceptor))
+    }
+}
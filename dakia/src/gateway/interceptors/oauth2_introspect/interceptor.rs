@@ -0,0 +1,85 @@
+use async_trait::async_trait;
+use http::StatusCode;
+
+use crate::{
+    gateway::{
+        interceptor::{Interceptor, InterceptorName, Phase, PhaseMask, PhaseResult},
+        interceptors::jwt_auth::{bearer_token, forward_claims},
+    },
+    proxy::http::Session,
+};
+
+use super::introspection::Introspection;
+
+// validates opaque bearer token with introspection endpoint of authorization server
+pub struct OAuth2IntrospectInterceptor {
+    filter: Option<String>,
+    introspection: Introspection,
+    // claims of introspection response are forwarded to upstream, pairs of claim and header name
+    claims_to_headers: Vec<(String, String)>,
+}
+
+impl OAuth2IntrospectInterceptor {
+    pub fn build(
+        filter: Option<String>,
+        introspection: Introspection,
+        claims_to_headers: Vec<(String, String)>,
+    ) -> Self {
+        Self {
+            filter,
+            introspection,
+            claims_to_headers,
+        }
+    }
+
+    fn write_unauthorized(&self, session: &mut Session, is_token_present: bool) -> PhaseResult {
+        let challenge = if is_token_present {
+            "Bearer error=\"invalid_token\""
+        } else {
+            "Bearer"
+        };
+
+        session.set_res_status(StatusCode::UNAUTHORIZED);
+        session.set_ds_res_header(
+            "WWW-Authenticate".to_string(),
+            challenge.as_bytes().to_vec(),
+        );
+        Ok(true)
+    }
+}
+
+#[async_trait]
+impl Interceptor for OAuth2IntrospectInterceptor {
+    fn name(&self) -> InterceptorName {
+        InterceptorName::OAuth2Introspect
+    }
+
+    fn phase_mask(&self) -> PhaseMask {
+        Phase::RequestFilter.mask() | Phase::PreUpstreamRequest.mask()
+    }
+
+    fn filter(&self) -> &Option<String> {
+        &self.filter
+    }
+
+    async fn request_filter(&self, _session: &mut Session) -> PhaseResult {
+        let token = match bearer_token(_session) {
+            Some(token) => token.to_string(),
+            None => return self.write_unauthorized(_session, false),
+        };
+
+        match self.introspection.introspect(&token).await? {
+            // claims are available to filters same as claims of jwt
+            Some(claims) => {
+                _session.set_jwt_claims(claims.as_ref().clone());
+                Ok(false)
+            }
+            None => self.write_unauthorized(_session, true),
+        }
+    }
+
+    async fn pre_upstream_request(&self, _session: &mut Session) -> PhaseResult {
+        forward_claims(_session, &self.claims_to_headers)?;
+        Ok(false)
+    }
+}
@@ -0,0 +1,208 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use base64::{engine::general_purpose, Engine};
+use bytes::Bytes;
+use dashmap::DashMap;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::{
+    error::{DakiaError, DakiaResult},
+    gateway::interceptors::jwt_auth::Claims,
+    shared::http_client::{self, HttpClientRequest},
+};
+
+// cached results are dropped when cache grows beyond this, expired results are dropped first
+const MAX_CACHED_TOKENS: usize = 10_000;
+
+pub struct ClientCredentials {
+    pub client_id: String,
+    pub client_secret: String,
+}
+
+impl ClientCredentials {
+    // credentials are form encoded before base64 encoding, as per rfc 6749
+    pub fn authorization(&self) -> Vec<u8> {
+        let client_id: String =
+            form_urlencoded::byte_serialize(self.client_id.as_bytes()).collect();
+        let client_secret: String =
+            form_urlencoded::byte_serialize(self.client_secret.as_bytes()).collect();
+        let credentials = format!("{client_id}:{client_secret}");
+        format!("Basic {}", general_purpose::STANDARD.encode(credentials)).into_bytes()
+    }
+}
+
+struct CachedIntrospection {
+    claims: Arc<Claims>,
+    expires_at: Instant,
+}
+
+// active introspection results are cached by hash of token until token expires or cache ttl elapses
+pub struct Introspection {
+    url: String,
+    credentials: Option<ClientCredentials>,
+    timeout: Duration,
+    cache_ttl: Duration,
+    cache: DashMap<[u8; 32], CachedIntrospection>,
+}
+
+impl Introspection {
+    pub fn build(
+        url: String,
+        credentials: Option<ClientCredentials>,
+        timeout: Duration,
+        cache_ttl: Duration,
+    ) -> Self {
+        Self {
+            url,
+            credentials,
+            timeout,
+            cache_ttl,
+            cache: DashMap::new(),
+        }
+    }
+
+    // returns claims of active token, none if token is not active
+    pub async fn introspect(&self, token: &str) -> DakiaResult<Option<Arc<Claims>>> {
+        let token_hash: [u8; 32] = Sha256::digest(token.as_bytes()).into();
+        if let Some(cached) = self.cache.get(&token_hash) {
+            if cached.expires_at > Instant::now() {
+                return Ok(Some(cached.claims.clone()));
+            }
+        }
+
+        let claims = self.fetch(token).await?;
+        if claims.get("active") != Some(&Value::Bool(true)) {
+            self.cache.remove(&token_hash);
+            return Ok(None);
+        }
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let cache_ttl = match claims.get("exp").and_then(Value::as_u64) {
+            Some(exp) if exp <= now => return Ok(None),
+            Some(exp) => self.cache_ttl.min(Duration::from_secs(exp - now)),
+            None => self.cache_ttl,
+        };
+
+        let claims = Arc::new(claims);
+        if !cache_ttl.is_zero() {
+            self.evict_if_full();
+            self.cache.insert(
+                token_hash,
+                CachedIntrospection {
+                    claims: claims.clone(),
+                    expires_at: Instant::now() + cache_ttl,
+                },
+            );
+        }
+
+        Ok(Some(claims))
+    }
+
+    fn evict_if_full(&self) {
+        if self.cache.len() < MAX_CACHED_TOKENS {
+            return;
+        }
+
+        let now = Instant::now();
+        self.cache.retain(|_, cached| cached.expires_at > now);
+        if self.cache.len() >= MAX_CACHED_TOKENS {
+            self.cache.clear();
+        }
+    }
+
+    async fn fetch(&self, token: &str) -> DakiaResult<Claims> {
+        let body: String = form_urlencoded::Serializer::new(String::new())
+            .append_pair("token", token)
+            .append_pair("token_type_hint", "access_token")
+            .finish();
+
+        let mut request = HttpClientRequest::get(&self.url, self.timeout);
+        request.method = "POST".to_string();
+        request.body = Some(Bytes::from(body));
+        request.headers.push((
+            "content-type".to_string(),
+            b"application/x-www-form-urlencoded".to_vec(),
+        ));
+        request
+            .headers
+            .push(("accept".to_string(), b"application/json".to_vec()));
+        if let Some(credentials) = &self.credentials {
+            request
+                .headers
+                .push(("authorization".to_string(), credentials.authorization()));
+        }
+
+        let response = http_client::send(request).await?;
+        if !response.header.status.is_success() {
+            return Err(DakiaError::i_explain(format!(
+                "introspection url {} responded with status {}",
+                self.url, response.header.status
+            )));
+        }
+
+        serde_json::from_slice(&response.body).map_err(|e| {
+            DakiaError::i_explain(format!(
+                "invalid introspection response from {} - {e}",
+                self.url
+            ))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use crate::shared::test_server;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_introspection() {
+        let requests = Arc::new(AtomicUsize::new(0));
+        let server_requests = requests.clone();
+        let address = test_server::serve(move |request| {
+            server_requests.fetch_add(1, Ordering::SeqCst);
+            assert_eq!(
+                request.header("authorization"),
+                Some("Basic ZGFraWE6cyUzQWNyZXQ=")
+            );
+
+            let body = String::from_utf8_lossy(&request.body).to_string();
+            let response = if body.starts_with("token=active&") {
+                r#"{"active":true,"sub":"dakia","scope":"read write","exp":4102444800}"#
+            } else {
+                r#"{"active":false}"#
+            };
+            (200, response.to_string())
+        })
+        .await;
+
+        let introspection = Introspection::build(
+            format!("{address}/introspect"),
+            Some(ClientCredentials {
+                client_id: "dakia".to_string(),
+                client_secret: "s:cret".to_string(),
+            }),
+            Duration::from_secs(5),
+            Duration::from_secs(60),
+        );
+
+        let claims = introspection.introspect("active").await.unwrap().unwrap();
+        assert_eq!(claims["sub"], "dakia");
+        // active result is served from cache
+        introspection.introspect("active").await.unwrap().unwrap();
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+
+        assert!(introspection.introspect("revoked").await.unwrap().is_none());
+        assert!(introspection.introspect("revoked").await.unwrap().is_none());
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+    }
+}
@@ -0,0 +1,5 @@
+mod builder;
+mod interceptor;
+mod introspection;
+pub use builder::OAuth2IntrospectInterceptorBuilder;
+pub use introspection::ClientCredentials;
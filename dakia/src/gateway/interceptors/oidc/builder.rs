@@ -0,0 +1,180 @@
+use std::{sync::Arc, time::Duration};
+
+use http::Uri;
+
+use crate::{
+    config::source_config::InterceptorConfig,
+    error::{DakiaError, DakiaResult},
+    gateway::{
+        interceptor::Interceptor,
+        interceptor_builder::InterceptorBuilder,
+        interceptors::{
+            jwt_auth::{extract_claims_to_headers, Algorithm, JwtValidation},
+            oauth2_introspect::ClientCredentials,
+        },
+    },
+    qe::query::{
+        extract_bool_or_err, extract_key_i64_or_err, extract_key_str_or_err, extract_string_or_err,
+        Composite, Query, Value,
+    },
+};
+
+use super::{
+    client::{OidcClient, ProviderMetadata},
+    cookie::CookieConfig,
+    interceptor::OidcInterceptor,
+};
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+const DEFAULT_COOKIE_NAME: &str = "dakia_oidc";
+const MIN_COOKIE_SECRET_SIZE: usize = 32;
+
+#[derive(Default)]
+pub struct OidcInterceptorBuilder {}
+
+impl OidcInterceptorBuilder {
+    fn extract_string(config: &Query, key: &str) -> DakiaResult<Option<String>> {
+        config.get(key).map(extract_string_or_err).transpose()
+    }
+
+    // value can be a single string or a list of strings
+    fn extract_strings(config: &Query, key: &str) -> DakiaResult<Vec<String>> {
+        match config.get(key) {
+            None => Ok(vec![]),
+            Some(Value::Composite(Composite::Vector(values))) => {
+                values.iter().map(extract_string_or_err).collect()
+            }
+            Some(value) => Ok(vec![extract_string_or_err(value)?]),
+        }
+    }
+
+    fn extract_duration(config: &Query, key: &str) -> DakiaResult<Option<Duration>> {
+        if !config.contains_key(key) {
+            return Ok(None);
+        }
+
+        let millis = extract_key_i64_or_err(config, key)?;
+        Ok(Some(Duration::from_millis(millis as u64)))
+    }
+
+    // endpoints are discovered from issuer unless all of them are configured
+    fn extract_metadata(config: &Query, issuer: &str) -> DakiaResult<Option<ProviderMetadata>> {
+        let endpoints = (
+            Self::extract_string(config, "authorization_endpoint")?,
+            Self::extract_string(config, "token_endpoint")?,
+            Self::extract_string(config, "jwks_uri")?,
+        );
+
+        match endpoints {
+            (Some(authorization_endpoint), Some(token_endpoint), Some(jwks_uri)) => {
+                Ok(Some(ProviderMetadata {
+                    issuer: issuer.to_string(),
+                    authorization_endpoint,
+                    token_endpoint,
+                    jwks_uri,
+                }))
+            }
+            (None, None, None) => Ok(None),
+            _ => Err(DakiaError::i_explain(
+                "authorization_endpoint, token_endpoint and jwks_uri must be configured together"
+                    .to_string(),
+            )),
+        }
+    }
+
+    fn extract_scopes(config: &Query) -> DakiaResult<Vec<String>> {
+        let mut scopes = Self::extract_strings(config, "scopes")?;
+        if !scopes.iter().any(|scope| scope == "openid") {
+            scopes.insert(0, "openid".to_string());
+        }
+        Ok(scopes)
+    }
+
+    fn extract_algorithms(config: &Query) -> DakiaResult<Vec<Algorithm>> {
+        let algorithms = Self::extract_strings(config, "algorithms")?;
+        if algorithms.is_empty() {
+            return Ok(vec![Algorithm::RS256, Algorithm::ES256, Algorithm::HS256]);
+        }
+
+        algorithms
+            .iter()
+            .map(|algorithm| Algorithm::try_from(algorithm.as_str()))
+            .collect()
+    }
+
+    fn extract_cookie_config(config: &Query) -> DakiaResult<CookieConfig> {
+        let secret = extract_key_str_or_err(config, "cookie_secret")?;
+        if secret.len() < MIN_COOKIE_SECRET_SIZE {
+            return Err(DakiaError::i_explain(format!(
+                "cookie_secret must have at least {MIN_COOKIE_SECRET_SIZE} characters"
+            )));
+        }
+
+        Ok(CookieConfig {
+            name: Self::extract_string(config, "cookie_name")?
+                .unwrap_or(DEFAULT_COOKIE_NAME.to_string()),
+            secret: secret.as_bytes().to_vec(),
+            is_secure: config
+                .get("cookie_secure")
+                .map(extract_bool_or_err)
+                .transpose()?
+                .unwrap_or(true),
+        })
+    }
+}
+
+impl InterceptorBuilder for OidcInterceptorBuilder {
+    fn build(&self, _interceptor_config: InterceptorConfig) -> DakiaResult<Arc<dyn Interceptor>> {
+        let config = _interceptor_config
+            .config
+            .as_ref()
+            .ok_or(DakiaError::i_explain(format!(
+                "{:?} interceptor config not found.",
+                _interceptor_config.name
+            )))?;
+
+        let issuer = extract_key_str_or_err(config, "issuer")?.to_string();
+        let client_id = extract_key_str_or_err(config, "client_id")?.to_string();
+        let redirect_uri = extract_key_str_or_err(config, "redirect_uri")?.to_string();
+        let callback_path = match redirect_uri.parse::<Uri>() {
+            Ok(uri) if uri.scheme().is_some() && uri.host().is_some() => uri.path().to_string(),
+            _ => {
+                return Err(DakiaError::i_explain(format!(
+                    "redirect_uri {redirect_uri} must be an absolute url"
+                )))
+            }
+        };
+
+        let validation = JwtValidation {
+            algorithms: Self::extract_algorithms(config)?,
+            issuers: vec![issuer.clone()],
+            audiences: vec![client_id.clone()],
+            clock_skew: Self::extract_duration(config, "clock_skew")?.unwrap_or_default(),
+        };
+
+        let client = OidcClient::build(
+            issuer.clone(),
+            ClientCredentials {
+                client_id,
+                client_secret: extract_key_str_or_err(config, "client_secret")?.to_string(),
+            },
+            redirect_uri,
+            Self::extract_scopes(config)?,
+            Self::extract_duration(config, "timeout")?.unwrap_or(DEFAULT_TIMEOUT),
+            validation,
+            Self::extract_metadata(config, &issuer)?,
+        );
+
+        let interceptor = OidcInterceptor::build(
+            _interceptor_config.filter.clone(),
+            client,
+            callback_path,
+            Self::extract_string(config, "logout_path")?,
+            Self::extract_string(config, "post_logout_redirect_uri")?.unwrap_or("/".to_string()),
+            Self::extract_cookie_config(config)?,
+            Self::extract_duration(config, "session_ttl")?,
+            extract_claims_to_headers(config)?,
+        );
+        Ok(Arc::new(interceptor))
+    }
+}
@@ -0,0 +1,286 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use bytes::Bytes;
+use serde::Deserialize;
+use serde_json::Value;
+use tokio::sync::OnceCell;
+
+use crate::{
+    error::{DakiaError, DakiaResult},
+    gateway::interceptors::{
+        jwt_auth::{Claims, Jwk, JwksCache, Jwt, JwtValidation, VerificationKey},
+        oauth2_introspect::ClientCredentials,
+    },
+    shared::http_client::{self, HttpClientRequest, HttpClientResponse},
+};
+
+const JWKS_REFRESH_INTERVAL: Duration = Duration::from_secs(3600);
+
+// endpoints of identity provider, discovered from issuer unless configured
+#[derive(Deserialize)]
+pub struct ProviderMetadata {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    pub jwks_uri: String,
+}
+
+struct Provider {
+    authorization_endpoint: String,
+    token_endpoint: String,
+    jwks_cache: JwksCache,
+}
+
+impl From<ProviderMetadata> for Provider {
+    fn from(metadata: ProviderMetadata) -> Self {
+        Provider {
+            authorization_endpoint: metadata.authorization_endpoint,
+            token_endpoint: metadata.token_endpoint,
+            jwks_cache: JwksCache::build(metadata.jwks_uri, JWKS_REFRESH_INTERVAL),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    id_token: String,
+}
+
+pub struct OidcClient {
+    issuer: String,
+    credentials: ClientCredentials,
+    redirect_uri: String,
+    scope: String,
+    timeout: Duration,
+    validation: JwtValidation,
+    provider: OnceCell<Provider>,
+}
+
+impl OidcClient {
+    pub fn build(
+        issuer: String,
+        credentials: ClientCredentials,
+        redirect_uri: String,
+        scopes: Vec<String>,
+        timeout: Duration,
+        validation: JwtValidation,
+        metadata: Option<ProviderMetadata>,
+    ) -> Self {
+        Self {
+            issuer,
+            credentials,
+            redirect_uri,
+            scope: scopes.join(" "),
+            timeout,
+            validation,
+            provider: OnceCell::new_with(metadata.map(Provider::from)),
+        }
+    }
+
+    // provider is discovered on first login, failed discovery is retried by next login
+    async fn provider(&self) -> DakiaResult<&Provider> {
+        self.provider
+            .get_or_try_init(|| async {
+                let discovery_url = format!(
+                    "{}/.well-known/openid-configuration",
+                    self.issuer.trim_end_matches('/')
+                );
+                let response = Self::check_response(
+                    http_client::send(HttpClientRequest::get(&discovery_url, self.timeout)).await?,
+                )?;
+
+                let metadata: ProviderMetadata =
+                    serde_json::from_slice(&response.body).map_err(|e| {
+                        DakiaError::i_explain(format!("invalid provider metadata - {e}"))
+                    })?;
+                if metadata.issuer != self.issuer {
+                    return Err(DakiaError::i_explain(format!(
+                        "provider metadata is of issuer {}, expected {}",
+                        metadata.issuer, self.issuer
+                    )));
+                }
+
+                Ok(Provider::from(metadata))
+            })
+            .await
+    }
+
+    fn check_response(response: HttpClientResponse) -> DakiaResult<HttpClientResponse> {
+        if response.header.status.is_success() {
+            return Ok(response);
+        }
+
+        Err(DakiaError::i_explain(format!(
+            "identity provider responded with status {} - {}",
+            response.header.status,
+            String::from_utf8_lossy(&response.body)
+        )))
+    }
+
+    pub async fn authorization_url(&self, state: &str, nonce: &str) -> DakiaResult<String> {
+        let authorization_endpoint = &self.provider().await?.authorization_endpoint;
+        let query: String = form_urlencoded::Serializer::new(String::new())
+            .append_pair("response_type", "code")
+            .append_pair("client_id", &self.credentials.client_id)
+            .append_pair("redirect_uri", &self.redirect_uri)
+            .append_pair("scope", &self.scope)
+            .append_pair("state", state)
+            .append_pair("nonce", nonce)
+            .finish();
+
+        let separator = if authorization_endpoint.contains('?') {
+            '&'
+        } else {
+            '?'
+        };
+        Ok(format!("{authorization_endpoint}{separator}{query}"))
+    }
+
+    // authorization code is exchanged for id token, claims of validated id token are returned
+    pub async fn exchange_code(&self, code: &str, nonce: &str) -> DakiaResult<Claims> {
+        let provider = self.provider().await?;
+        let body: String = form_urlencoded::Serializer::new(String::new())
+            .append_pair("grant_type", "authorization_code")
+            .append_pair("code", code)
+            .append_pair("redirect_uri", &self.redirect_uri)
+            .finish();
+
+        let mut request = HttpClientRequest::get(&provider.token_endpoint, self.timeout);
+        request.method = "POST".to_string();
+        request.body = Some(Bytes::from(body));
+        request.headers.push((
+            "content-type".to_string(),
+            b"application/x-www-form-urlencoded".to_vec(),
+        ));
+        request
+            .headers
+            .push(("accept".to_string(), b"application/json".to_vec()));
+        request.headers.push((
+            "authorization".to_string(),
+            self.credentials.authorization(),
+        ));
+
+        let response = Self::check_response(http_client::send(request).await?)?;
+        let token_response: TokenResponse = serde_json::from_slice(&response.body)
+            .map_err(|e| DakiaError::i_explain(format!("invalid token response - {e}")))?;
+
+        let id_token = Jwt::decode(&token_response.id_token)?;
+        let jwks_keys = provider.jwks_cache.keys(id_token.kid()).await?;
+        // jwks has public keys only, HS256 id tokens are signed with client secret
+        let client_secret_key = Jwk {
+            kid: None,
+            key: VerificationKey::from_secret(self.credentials.client_secret.as_bytes()),
+        };
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs_f64();
+
+        let claims = id_token.validate(
+            jwks_keys.iter().chain([&client_secret_key]),
+            &self.validation,
+            now,
+        )?;
+        if claims.get("nonce") != Some(&Value::String(nonce.to_string())) {
+            return Err(DakiaError::i_explain(
+                "nonce of id token does not match login".to_string(),
+            ));
+        }
+
+        Ok(claims)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use base64::{engine::general_purpose, Engine};
+    use jsonwebtoken::{crypto, EncodingKey};
+
+    use crate::{gateway::interceptors::jwt_auth::Algorithm, shared::test_server};
+
+    use super::*;
+
+    fn sign_hs256(claims: &str, secret: &[u8]) -> String {
+        let signing_input = format!(
+            "{}.{}",
+            general_purpose::URL_SAFE_NO_PAD.encode(r#"{"alg":"HS256","typ":"JWT"}"#),
+            general_purpose::URL_SAFE_NO_PAD.encode(claims)
+        );
+        let signature = crypto::sign(
+            signing_input.as_bytes(),
+            &EncodingKey::from_secret(secret),
+            jsonwebtoken::Algorithm::HS256,
+        )
+        .unwrap();
+        format!("{signing_input}.{signature}")
+    }
+
+    #[tokio::test]
+    async fn test_oidc_client() {
+        // issuer is known only after mock identity provider starts, it's shared with handler
+        let issuer = std::sync::Arc::new(std::sync::OnceLock::<String>::new());
+        let handler_issuer = issuer.clone();
+        let address = test_server::serve(move |request| {
+            let issuer = handler_issuer.get().unwrap();
+            match request.path() {
+                "/.well-known/openid-configuration" => (
+                    200,
+                    format!(
+                        r#"{{"issuer":"{issuer}","authorization_endpoint":"{issuer}/authorize","token_endpoint":"{issuer}/token","jwks_uri":"{issuer}/jwks"}}"#
+                    ),
+                ),
+                // symmetric key of jwks must not be used to verify id token
+                "/jwks" => (200, r#"{"keys":[{"kty":"oct","k":"Zm9yZ2Vk"}]}"#.to_string()),
+                "/token" => {
+                    let body = String::from_utf8_lossy(&request.body).to_string();
+                    let secret: &[u8] = if body.contains("code=valid-code") {
+                        b"secret"
+                    } else if body.contains("code=forged-code") {
+                        b"forged"
+                    } else {
+                        return (400, r#"{"error":"invalid_grant"}"#.to_string());
+                    };
+                    let claims = format!(
+                        r#"{{"iss":"{issuer}","aud":"dakia","sub":"alice","nonce":"n-1","exp":4102444800}}"#
+                    );
+                    let id_token = sign_hs256(&claims, secret);
+                    (200, format!(r#"{{"id_token":"{id_token}","token_type":"Bearer"}}"#))
+                }
+                _ => (404, "{}".to_string()),
+            }
+        })
+        .await;
+        issuer.set(address.clone()).unwrap();
+
+        let client = OidcClient::build(
+            address.clone(),
+            ClientCredentials {
+                client_id: "dakia".to_string(),
+                client_secret: "secret".to_string(),
+            },
+            "https://dakia.example/callback".to_string(),
+            vec!["openid".to_string(), "email".to_string()],
+            Duration::from_secs(5),
+            JwtValidation {
+                algorithms: vec![Algorithm::HS256, Algorithm::RS256],
+                issuers: vec![address.clone()],
+                audiences: vec!["dakia".to_string()],
+                clock_skew: Duration::ZERO,
+            },
+            None,
+        );
+
+        let authorization_url = client.authorization_url("s-1", "n-1").await.unwrap();
+        assert_eq!(
+            authorization_url,
+            format!("{address}/authorize?response_type=code&client_id=dakia&redirect_uri=https%3A%2F%2Fdakia.example%2Fcallback&scope=openid+email&state=s-1&nonce=n-1")
+        );
+
+        let claims = client.exchange_code("valid-code", "n-1").await.unwrap();
+        assert_eq!(claims["sub"], "alice");
+
+        assert!(client.exchange_code("valid-code", "n-2").await.is_err());
+        assert!(client.exchange_code("invalid-code", "n-1").await.is_err());
+        assert!(client.exchange_code("forged-code", "n-1").await.is_err());
+    }
+}
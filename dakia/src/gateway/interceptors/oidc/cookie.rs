@@ -0,0 +1,115 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use base64::{engine::general_purpose, Engine};
+use rand::{rngs::OsRng, RngCore};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    error::{DakiaError, DakiaResult},
+    gateway::interceptors::jwt_auth::Claims,
+    shared::crypto::{open, seal},
+};
+
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+// url safe random value, used for state and nonce of login
+pub fn random_token() -> String {
+    let mut token = [0u8; 16];
+    OsRng.fill_bytes(&mut token);
+    general_purpose::URL_SAFE_NO_PAD.encode(token)
+}
+
+// login in progress, kept in a cookie until identity provider redirects back
+#[derive(Serialize, Deserialize)]
+pub struct LoginState {
+    pub state: String,
+    pub nonce: String,
+    pub redirect_to: String,
+    pub expires_at: u64,
+}
+
+// claims of id token, kept in a cookie after login
+#[derive(Serialize, Deserialize)]
+pub struct LoginSession {
+    pub claims: Claims,
+    pub expires_at: u64,
+}
+
+pub struct CookieConfig {
+    pub name: String,
+    pub secret: Vec<u8>,
+    pub is_secure: bool,
+}
+
+impl CookieConfig {
+    pub fn state_name(&self) -> String {
+        format!("{}_state", self.name)
+    }
+
+    // value is encrypted, so that client can neither read nor modify it
+    pub fn seal<T: Serialize>(&self, value: &T) -> DakiaResult<String> {
+        let json = serde_json::to_vec(value)
+            .map_err(|e| DakiaError::i_explain(format!("failed to serialize cookie - {e}")))?;
+        Ok(general_purpose::URL_SAFE_NO_PAD.encode(seal(&self.secret, &json)))
+    }
+
+    pub fn open<T: DeserializeOwned>(&self, cookie: &str) -> DakiaResult<T> {
+        let sealed = general_purpose::URL_SAFE_NO_PAD
+            .decode(cookie)
+            .map_err(|_| DakiaError::i_explain("cookie is not base64url encoded".to_string()))?;
+        let json = open(&self.secret, &sealed)?;
+        serde_json::from_slice(&json)
+            .map_err(|e| DakiaError::i_explain(format!("invalid cookie - {e}")))
+    }
+
+    // cookie is removed by browser if max age is zero
+    pub fn build_set_cookie(&self, name: &str, value: &str, max_age: Duration) -> String {
+        let mut set_cookie = format!(
+            "{name}={value}; Path=/; HttpOnly; SameSite=Lax; Max-Age={}",
+            max_age.as_secs()
+        );
+        if self.is_secure {
+            set_cookie.push_str("; Secure");
+        }
+        set_cookie
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cookie() {
+        let cookie_config = CookieConfig {
+            name: "dakia_oidc".to_string(),
+            secret: b"0123456789abcdef0123456789abcdef".to_vec(),
+            is_secure: true,
+        };
+
+        let login_state = LoginState {
+            state: random_token(),
+            nonce: random_token(),
+            redirect_to: "/orders?page=2".to_string(),
+            expires_at: unix_now() + 600,
+        };
+        let cookie = cookie_config.seal(&login_state).unwrap();
+        let opened: LoginState = cookie_config.open(&cookie).unwrap();
+        assert_eq!(opened.state, login_state.state);
+        assert_eq!(opened.redirect_to, "/orders?page=2");
+
+        // state can not be used as session
+        assert!(cookie_config.open::<LoginSession>(&cookie).is_err());
+        assert!(cookie_config.open::<LoginState>(&cookie[1..]).is_err());
+
+        assert_eq!(
+            cookie_config.build_set_cookie("dakia_oidc", "", Duration::ZERO),
+            "dakia_oidc=; Path=/; HttpOnly; SameSite=Lax; Max-Age=0; Secure"
+        );
+    }
+}
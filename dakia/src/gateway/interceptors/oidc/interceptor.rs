@@ -0,0 +1,241 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use http::StatusCode;
+use log::{debug, warn};
+use serde_json::Value;
+use subtle::ConstantTimeEq;
+
+use crate::{
+    gateway::{
+        interceptor::{Interceptor, InterceptorName, Phase, PhaseMask, PhaseResult},
+        interceptors::jwt_auth::forward_claims,
+    },
+    proxy::http::Session,
+};
+
+use super::{
+    client::OidcClient,
+    cookie::{random_token, unix_now, CookieConfig, LoginSession, LoginState},
+};
+
+// login has to be completed within this time
+const LOGIN_STATE_TTL: Duration = Duration::from_secs(600);
+// used if neither session ttl is configured nor id token has expiry
+const DEFAULT_SESSION_TTL: Duration = Duration::from_secs(3600);
+
+// logs in browser users with authorization code flow, login session is kept in an encrypted cookie
+pub struct OidcInterceptor {
+    filter: Option<String>,
+    client: OidcClient,
+    // path of redirect uri, identity provider redirects here after login
+    callback_path: String,
+    logout_path: Option<String>,
+    post_logout_redirect_uri: String,
+    cookie_config: CookieConfig,
+    session_ttl: Option<Duration>,
+    claims_to_headers: Vec<(String, String)>,
+}
+
+impl OidcInterceptor {
+    #[allow(clippy::too_many_arguments)]
+    pub fn build(
+        filter: Option<String>,
+        client: OidcClient,
+        callback_path: String,
+        logout_path: Option<String>,
+        post_logout_redirect_uri: String,
+        cookie_config: CookieConfig,
+        session_ttl: Option<Duration>,
+        claims_to_headers: Vec<(String, String)>,
+    ) -> Self {
+        Self {
+            filter,
+            client,
+            callback_path,
+            logout_path,
+            post_logout_redirect_uri,
+            cookie_config,
+            session_ttl,
+            claims_to_headers,
+        }
+    }
+
+    fn write_redirect(&self, session: &mut Session, location: &str) -> PhaseResult {
+        session.set_res_status(StatusCode::FOUND);
+        session.set_ds_res_header("Location".to_string(), location.as_bytes().to_vec());
+        Ok(true)
+    }
+
+    fn write_unauthorized(&self, session: &mut Session) -> PhaseResult {
+        session.set_res_status(StatusCode::UNAUTHORIZED);
+        Ok(true)
+    }
+
+    fn clear_cookie(&self, session: &mut Session, name: &str) {
+        let set_cookie = self
+            .cookie_config
+            .build_set_cookie(name, "", Duration::ZERO);
+        session.add_ds_res_cookie(set_cookie);
+    }
+
+    fn login_session(&self, session: &Session) -> Option<LoginSession> {
+        let cookie = session.ds_req_cookie(&self.cookie_config.name)?;
+        let login_session: LoginSession = self.cookie_config.open(cookie).ok()?;
+        (login_session.expires_at > unix_now()).then_some(login_session)
+    }
+
+    // only navigations are redirected to login, other requests can not follow redirect to identity provider
+    async fn start_login(&self, session: &mut Session<'_>) -> PhaseResult {
+        let method = session.ds_req_method()?;
+        if method != "GET" && method != "HEAD" {
+            return self.write_unauthorized(session);
+        }
+
+        let redirect_to = local_redirect(session.ds_req_path(), session.ds_req_query()?);
+        let login_state = LoginState {
+            state: random_token(),
+            nonce: random_token(),
+            redirect_to,
+            expires_at: unix_now() + LOGIN_STATE_TTL.as_secs(),
+        };
+
+        let authorization_url = self
+            .client
+            .authorization_url(&login_state.state, &login_state.nonce)
+            .await?;
+        let set_cookie = self.cookie_config.build_set_cookie(
+            &self.cookie_config.state_name(),
+            &self.cookie_config.seal(&login_state)?,
+            LOGIN_STATE_TTL,
+        );
+        session.add_ds_res_cookie(set_cookie);
+        self.write_redirect(session, &authorization_url)
+    }
+
+    fn query_param(session: &Session, name: &str) -> Option<String> {
+        session.ds_req_query_params().get(name)?.first().cloned()
+    }
+
+    async fn complete_login(&self, session: &mut Session<'_>) -> PhaseResult {
+        let state_name = self.cookie_config.state_name();
+        let login_state = session
+            .ds_req_cookie(&state_name)
+            .and_then(|cookie| self.cookie_config.open::<LoginState>(cookie).ok())
+            .filter(|login_state| login_state.expires_at > unix_now());
+        self.clear_cookie(session, &state_name);
+
+        if let Some(error) = Self::query_param(session, "error") {
+            debug!("identity provider rejected login - {error}");
+            return self.write_unauthorized(session);
+        }
+
+        let (login_state, code) = match (
+            login_state,
+            Self::query_param(session, "state"),
+            Self::query_param(session, "code"),
+        ) {
+            (Some(login_state), Some(state), Some(code))
+                if bool::from(login_state.state.as_bytes().ct_eq(state.as_bytes())) =>
+            {
+                (login_state, code)
+            }
+            _ => {
+                debug!("rejecting login callback with missing or mismatched state");
+                return self.write_unauthorized(session);
+            }
+        };
+
+        let claims = match self.client.exchange_code(&code, &login_state.nonce).await {
+            Ok(claims) => claims,
+            Err(e) => {
+                warn!("failed to complete login - {:?}", e);
+                return self.write_unauthorized(session);
+            }
+        };
+
+        let now = unix_now();
+        let expires_at = match (self.session_ttl, claims.get("exp").and_then(Value::as_u64)) {
+            (Some(session_ttl), _) => now + session_ttl.as_secs(),
+            (None, Some(exp)) => exp,
+            (None, None) => now + DEFAULT_SESSION_TTL.as_secs(),
+        };
+        let login_session = LoginSession { claims, expires_at };
+
+        let set_cookie = self.cookie_config.build_set_cookie(
+            &self.cookie_config.name,
+            &self.cookie_config.seal(&login_session)?,
+            Duration::from_secs(expires_at.saturating_sub(now)),
+        );
+        session.add_ds_res_cookie(set_cookie);
+        self.write_redirect(session, &login_state.redirect_to)
+    }
+}
+
+// browsers follow path starting with "//" or "/\" to another host, so leading slashes are collapsed
+fn local_redirect(path: &str, query: Option<&str>) -> String {
+    let path = path.trim_start_matches(['/', '\\']);
+    match query {
+        Some(query) => format!("/{path}?{query}"),
+        None => format!("/{path}"),
+    }
+}
+
+#[async_trait]
+impl Interceptor for OidcInterceptor {
+    fn name(&self) -> InterceptorName {
+        InterceptorName::Oidc
+    }
+
+    fn phase_mask(&self) -> PhaseMask {
+        Phase::RequestFilter.mask() | Phase::PreUpstreamRequest.mask()
+    }
+
+    fn filter(&self) -> &Option<String> {
+        &self.filter
+    }
+
+    async fn request_filter(&self, _session: &mut Session) -> PhaseResult {
+        let path = _session.ds_req_path();
+        if self.logout_path.as_deref() == Some(path) {
+            let cookie_name = self.cookie_config.name.clone();
+            self.clear_cookie(_session, &cookie_name);
+            return self.write_redirect(_session, &self.post_logout_redirect_uri);
+        }
+
+        if path == self.callback_path {
+            return self.complete_login(_session).await;
+        }
+
+        match self.login_session(_session) {
+            // claims are available to filters same as claims of jwt
+            Some(login_session) => {
+                _session.set_jwt_claims(login_session.claims);
+                Ok(false)
+            }
+            None => self.start_login(_session).await,
+        }
+    }
+
+    async fn pre_upstream_request(&self, _session: &mut Session) -> PhaseResult {
+        forward_claims(_session, &self.claims_to_headers)?;
+        Ok(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_local_redirect() {
+        assert_eq!(
+            local_redirect("/orders/1", Some("page=2")),
+            "/orders/1?page=2"
+        );
+        assert_eq!(local_redirect("/", None), "/");
+        assert_eq!(local_redirect("//evil.example/x", None), "/evil.example/x");
+        assert_eq!(local_redirect("/\\evil.example", None), "/evil.example");
+        assert_eq!(local_redirect("", None), "/");
+    }
+}
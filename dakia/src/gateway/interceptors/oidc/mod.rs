@@ -0,0 +1,5 @@
+mod builder;
+mod client;
+mod cookie;
+mod interceptor;
+pub use builder::OidcInterceptorBuilder;
@@ -2,13 +2,15 @@ use std::{sync::Arc, time::Duration};
 
 use crate::{
     config::source_config::InterceptorConfig,
-    error::DakiaResult,
+    error::{DakiaError, DakiaResult},
     gateway::{
         interceptor::Interceptor,
         interceptor_builder::InterceptorBuilder,
-        interceptors::rate_limiter::interceptor::{RateLimit, RateLimiterInterceptor},
+        interceptors::rate_limiter::interceptor::{
+            RateLimit, RateLimitKey, RateLimiterInterceptor,
+        },
     },
-    qe::query::extract_key_i64_or_err,
+    qe::query::{extract_key_i64_or_err, extract_string_or_err},
 };
 
 pub struct RateLimiterInterceptorBuilder {}
@@ -33,10 +35,22 @@ impl InterceptorBuilder for RateLimiterInterceptorBuilder {
         let refill_rate = extract_key_i64_or_err(config, "refill_rate")?;
         let refill_interval = extract_key_i64_or_err(config, "refill_interval")?;
 
+        let key = match config.get("key").map(extract_string_or_err).transpose()? {
+            None => RateLimitKey::Ip,
+            Some(key) if key == "ip" => RateLimitKey::Ip,
+            Some(key) if key == "consumer" => RateLimitKey::Consumer,
+            Some(key) => {
+                return Err(DakiaError::i_explain(format!(
+                    "rate limit key must be ip or consumer, found {key}"
+                )))
+            }
+        };
+
         let rate_limit = RateLimit {
             capacity: capacity as u32,
             refill_rate: refill_rate as u32,
             refill_interval: Duration::from_millis(refill_interval as u64),
+            key,
         };
 
         let interceptor = RateLimiterInterceptor::build(rate_limit);
@@ -39,10 +39,17 @@ impl Bucket {
     }
 }
 
+// requests are limited per client ip or per consumer identified by api_key interceptor
+pub enum RateLimitKey {
+    Ip,
+    Consumer,
+}
+
 pub struct RateLimit {
     pub capacity: u32,
     pub refill_rate: u32,
     pub refill_interval: Duration,
+    pub key: RateLimitKey,
 }
 
 pub struct RateLimiterInterceptor {
@@ -71,6 +78,14 @@ impl Interceptor for RateLimiterInterceptor {
     }
 
     async fn request_filter(&self, _session: &mut Session) -> PhaseResult {
+        // requests without consumer are limited per ip, api_key interceptor must be declared before
+        let consumer_name = match self.rate_limit.key {
+            RateLimitKey::Consumer => _session
+                .consumer()
+                .map(|consumer| format!("consumer:{}", consumer.name)),
+            RateLimitKey::Ip => None,
+        };
+
         let socket_addr = _session
             .ds_socket_addr()
             .map_or("".to_string(), |socket_addr| socket_addr.to_string())
@@ -78,6 +93,7 @@ impl Interceptor for RateLimiterInterceptor {
             .next()
             .map(String::from)
             .map_or("".to_string(), |ip| ip);
+        let socket_addr = consumer_name.unwrap_or(socket_addr);
 
         let mut bucket = match self.buckets.get_mut(&socket_addr) {
             Some(bucket) => bucket,
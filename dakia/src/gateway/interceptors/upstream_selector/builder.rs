@@ -0,0 +1,72 @@
+use std::{sync::Arc, time::Duration};
+
+use crate::{
+    config::{source_config::InterceptorConfig, InetAddress},
+    error::{DakiaError, DakiaResult},
+    gateway::{interceptor::Interceptor, interceptor_builder::InterceptorBuilder},
+    qe::query::{extract_key_i64_or_err, extract_string_or_err, Query},
+};
+
+use super::interceptor::{UpstreamSelection, UpstreamSelectorInterceptor};
+
+#[derive(Default)]
+pub struct UpstreamSelectorInterceptorBuilder {}
+
+impl UpstreamSelectorInterceptorBuilder {
+    fn extract_string(config: &Query, key: &str) -> DakiaResult<Option<String>> {
+        config.get(key).map(extract_string_or_err).transpose()
+    }
+
+    fn extract_duration(config: &Query, key: &str) -> DakiaResult<Option<Duration>> {
+        if !config.contains_key(key) {
+            return Ok(None);
+        }
+
+        let millis = extract_key_i64_or_err(config, key)?;
+        Ok(Some(Duration::from_millis(millis as u64)))
+    }
+
+    fn extract_node(config: &Query) -> DakiaResult<Option<InetAddress>> {
+        let node = match Self::extract_string(config, "node")? {
+            Some(node) => node,
+            None => return Ok(None),
+        };
+
+        let invalid_node_err =
+            || DakiaError::i_explain(format!("expected node in host:port format, found {node}"));
+        let (host, port) = node.rsplit_once(':').ok_or_else(invalid_node_err)?;
+        let port = port.parse().map_err(|_| invalid_node_err())?;
+
+        Ok(Some(InetAddress {
+            host: host.to_string(),
+            port,
+        }))
+    }
+}
+
+impl InterceptorBuilder for UpstreamSelectorInterceptorBuilder {
+    fn build(&self, _interceptor_config: InterceptorConfig) -> DakiaResult<Arc<dyn Interceptor>> {
+        let config = _interceptor_config
+            .config
+            .as_ref()
+            .ok_or(DakiaError::i_explain(format!(
+                "{:?} interceptor config not found.",
+                _interceptor_config.name
+            )))?;
+
+        let upstream_selection = UpstreamSelection {
+            upstream: Self::extract_string(config, "upstream")?,
+            node: Self::extract_node(config)?,
+            sni: Self::extract_string(config, "sni")?,
+            connection_timeout: Self::extract_duration(config, "connection_timeout")?,
+            read_timeout: Self::extract_duration(config, "read_timeout")?,
+            write_timeout: Self::extract_duration(config, "write_timeout")?,
+        };
+
+        let interceptor = UpstreamSelectorInterceptor::build(
+            _interceptor_config.filter.clone(),
+            upstream_selection,
+        );
+        Ok(Arc::new(interceptor))
+    }
+}
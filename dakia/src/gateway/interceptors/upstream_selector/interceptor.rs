@@ -0,0 +1,72 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+
+use crate::{
+    config::InetAddress,
+    gateway::interceptor::{Interceptor, InterceptorName, Phase, PhaseMask, PhaseResult},
+    proxy::http::Session,
+};
+
+pub struct UpstreamSelection {
+    pub upstream: Option<String>,
+    pub node: Option<InetAddress>,
+    pub sni: Option<String>,
+    pub connection_timeout: Option<Duration>,
+    pub read_timeout: Option<Duration>,
+    pub write_timeout: Option<Duration>,
+}
+
+// overrides upstream peer of requests matching the filter, e.g. requests of a tenant can be sent to its dedicated upstream
+pub struct UpstreamSelectorInterceptor {
+    filter: Option<String>,
+    upstream_selection: UpstreamSelection,
+}
+
+impl UpstreamSelectorInterceptor {
+    pub fn build(filter: Option<String>, upstream_selection: UpstreamSelection) -> Self {
+        Self {
+            filter,
+            upstream_selection,
+        }
+    }
+}
+
+#[async_trait]
+impl Interceptor for UpstreamSelectorInterceptor {
+    fn name(&self) -> InterceptorName {
+        InterceptorName::UpstreamSelector
+    }
+
+    fn phase_mask(&self) -> PhaseMask {
+        Phase::UpstreamPeerSelection.mask()
+    }
+
+    fn filter(&self) -> &Option<String> {
+        &self.filter
+    }
+
+    async fn upstream_peer_selection(&self, _session: &mut Session) -> PhaseResult {
+        let upstream_selection = &self.upstream_selection;
+
+        if let Some(upstream) = &upstream_selection.upstream {
+            _session.set_upstream(upstream.clone());
+        }
+
+        if let Some(node) = &upstream_selection.node {
+            _session.pin_upstream_node(node.clone());
+        }
+
+        if let Some(sni) = &upstream_selection.sni {
+            _session.set_us_peer_sni(sni.clone());
+        }
+
+        _session.set_us_peer_timeouts(
+            upstream_selection.connection_timeout,
+            upstream_selection.read_timeout,
+            upstream_selection.write_timeout,
+        );
+
+        Ok(false)
+    }
+}
@@ -0,0 +1,134 @@
+use std::sync::OnceLock;
+
+use hmac::{Hmac, Mac};
+use pingora::lb::Backend;
+use rand::{rngs::OsRng, RngCore};
+use sha2::Sha256;
+
+use crate::{config::source_config::AffinityCookieConfig, shared::crypto::stable_hash};
+
+use super::{node_state::is_selectable, LB};
+
+// bytes of mac kept in affinity id
+const AFFINITY_ID_SIZE: usize = 16;
+
+// used if no secret is configured, cookies issued with it are valid only for lifetime of the process
+static PROCESS_SECRET: OnceLock<[u8; 32]> = OnceLock::new();
+
+fn cookie_secret(cookie_config: &AffinityCookieConfig) -> &[u8] {
+    match &cookie_config.secret {
+        Some(secret) => secret.as_bytes(),
+        None => PROCESS_SECRET.get_or_init(|| {
+            let mut secret = [0u8; 32];
+            OsRng.fill_bytes(&mut secret);
+            secret
+        }),
+    }
+}
+
+// mac of backend address keyed by secret, it's stored in affinity cookie so that backend address is not exposed to clients
+pub fn backend_affinity_id(cookie_config: &AffinityCookieConfig, backend: &Backend) -> String {
+    // safe to unwrap, hmac accepts key of any length
+    let mac = <Hmac<Sha256> as Mac>::new_from_slice(cookie_secret(cookie_config))
+        .unwrap()
+        .chain_update(backend.addr.to_string())
+        .finalize()
+        .into_bytes();
+    hex::encode(&mac[..AFFINITY_ID_SIZE])
+}
+
+// returns backend identified by affinity id, if it's still present, healthy and not draining
+pub fn find_backend(
+    lb: &LB,
+    cookie_config: &AffinityCookieConfig,
+    affinity_id: &str,
+) -> Option<Backend> {
+    let backends = lb.backends().get_backend();
+    backends
+        .iter()
+        .find(|backend| backend_affinity_id(cookie_config, backend) == affinity_id)
+        .filter(|backend| is_selectable(lb, backend))
+        .cloned()
+}
+
+// rendezvous hashing, a key keeps mapping to the same backend unless that backend leaves the healthy set
+pub fn select_backend_by_key(lb: &LB, key: &[u8]) -> Option<Backend> {
+    let backends = lb.backends().get_backend();
+    backends
+        .iter()
+        .filter(|backend| is_selectable(lb, backend))
+        .max_by_key(|backend| stable_hash(&[key, backend.addr.to_string().as_bytes()]))
+        .cloned()
+}
+
+pub fn find_cookie<'a>(cookie_header: &'a [u8], cookie_name: &str) -> Option<&'a str> {
+    let cookie_header = std::str::from_utf8(cookie_header).ok()?;
+    cookie_header.split(';').find_map(|cookie| {
+        let (name, value) = cookie.trim().split_once('=')?;
+        (name == cookie_name).then_some(value)
+    })
+}
+
+pub fn build_set_cookie(cookie_config: &AffinityCookieConfig, value: &str) -> String {
+    let path = cookie_config.path.as_deref().unwrap_or("/");
+    let mut set_cookie = format!("{}={}; Path={}; HttpOnly", cookie_config.name, value, path);
+
+    if let Some(ttl) = cookie_config.ttl {
+        set_cookie.push_str(&format!("; Max-Age={ttl}"));
+    }
+
+    set_cookie
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_cookie() {
+        let cookie_header = b"theme=dark; dakia_affinity=00ab; lang=en";
+        assert_eq!(find_cookie(cookie_header, "dakia_affinity"), Some("00ab"));
+        assert_eq!(find_cookie(cookie_header, "lang"), Some("en"));
+        assert_eq!(find_cookie(cookie_header, "session"), None);
+    }
+
+    #[test]
+    fn test_select_backend_by_key_is_stable() {
+        let lb = LB::try_from_iter(["127.0.0.1:3000", "127.0.0.1:3001", "127.0.0.1:3002"]).unwrap();
+        let backend = select_backend_by_key(&lb, b"user-42").unwrap();
+
+        for _ in 0..10 {
+            assert_eq!(select_backend_by_key(&lb, b"user-42").unwrap(), backend);
+        }
+    }
+
+    #[test]
+    fn test_backend_affinity_id() {
+        let lb = LB::try_from_iter(["127.0.0.1:3000", "127.0.0.1:3001"]).unwrap();
+        let backend = select_backend_by_key(&lb, b"user-42").unwrap();
+        let cookie_config = |secret: Option<&str>| AffinityCookieConfig {
+            name: "dakia_affinity".to_string(),
+            secret: secret.map(str::to_string),
+            ttl: None,
+            path: None,
+        };
+
+        let affinity_id = backend_affinity_id(&cookie_config(Some("secret")), &backend);
+        assert_eq!(affinity_id.len(), AFFINITY_ID_SIZE * 2);
+        assert_eq!(
+            find_backend(&lb, &cookie_config(Some("secret")), &affinity_id),
+            Some(backend.clone())
+        );
+        // id issued with another secret identifies no backend
+        assert_eq!(
+            find_backend(&lb, &cookie_config(Some("other")), &affinity_id),
+            None
+        );
+
+        let affinity_id = backend_affinity_id(&cookie_config(None), &backend);
+        assert_eq!(
+            find_backend(&lb, &cookie_config(None), &affinity_id),
+            Some(backend)
+        );
+    }
+}
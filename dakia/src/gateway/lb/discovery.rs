@@ -0,0 +1,401 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    net::{IpAddr, SocketAddr as InetSocketAddr},
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use hickory_resolver::{
+    config::{ResolverConfig, ResolverOpts},
+    TokioAsyncResolver,
+};
+use http::Extensions;
+use log::{debug, warn};
+use pingora::{
+    lb::{discovery::ServiceDiscovery, Backend},
+    protocols::l4::socket::SocketAddr,
+    server::ShutdownWatch,
+    services::background::BackgroundService,
+    Error, ErrorType,
+};
+
+use super::update_lb;
+use crate::{
+    config::{
+        source_config::{DiscoveryConfig, DnsRecordType, UpstreamConfig, UpstreamNodeConfig},
+        InetAddress,
+    },
+    error::{DakiaError, DakiaResult},
+    gateway::state::GatewayState,
+    shared::dakia_state::DAKIA_STATE_STORE,
+};
+
+const DEFAULT_REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+const DISCOVERY_TICK: Duration = Duration::from_secs(1);
+
+// upstream node config is attached to backend, so that tls and sni are available for discovered backends as well
+pub fn build_backend(addr: InetSocketAddr, node_config: UpstreamNodeConfig) -> Backend {
+    let weight = node_config.weight.unwrap_or(1).max(1) as usize;
+    let mut ext = Extensions::new();
+    ext.insert(node_config);
+
+    Backend {
+        addr: SocketAddr::Inet(addr),
+        weight,
+        ext,
+    }
+}
+
+fn build_node_config(
+    addr: &InetSocketAddr,
+    weight: Option<u16>,
+    tls: bool,
+    sni: &Option<String>,
+) -> UpstreamNodeConfig {
+    UpstreamNodeConfig {
+        address: InetAddress {
+            host: addr.ip().to_string(),
+            port: addr.port(),
+        },
+        tls,
+        sni: sni.clone(),
+        weight,
+        drain: None,
+    }
+}
+
+pub async fn build_static_backends(
+    upstream_config: &UpstreamConfig,
+) -> DakiaResult<BTreeSet<Backend>> {
+    let mut backends = BTreeSet::new();
+
+    for node_config in &upstream_config.upstream_nodes {
+        let addrs = tokio::net::lookup_host(node_config.address.get_formatted_address()).await?;
+        for addr in addrs {
+            backends.insert(build_backend(addr, node_config.clone()));
+        }
+    }
+
+    Ok(backends)
+}
+
+// empty result is treated as failure, so that load balancer keeps previously discovered backends
+fn ensure_discovered(backends: &BTreeSet<Backend>, source: &str) -> pingora::Result<()> {
+    if backends.is_empty() {
+        return Err(Error::explain(
+            ErrorType::InternalError,
+            format!("no backends discovered from {source}"),
+        ));
+    }
+    Ok(())
+}
+
+pub struct DnsDiscovery {
+    name: String,
+    record_type: DnsRecordType,
+    port: Option<u16>,
+    tls: bool,
+    sni: Option<String>,
+    // connections of resolver are re-established if tokio runtime they were spawned in is gone
+    resolver: TokioAsyncResolver,
+}
+
+impl DnsDiscovery {
+    fn build_resolver() -> TokioAsyncResolver {
+        TokioAsyncResolver::tokio_from_system_conf().unwrap_or_else(|_| {
+            TokioAsyncResolver::tokio(ResolverConfig::default(), ResolverOpts::default())
+        })
+    }
+
+    async fn discover_a(
+        &self,
+        resolver: &TokioAsyncResolver,
+    ) -> pingora::Result<BTreeSet<Backend>> {
+        // port presence is validated while building discovery
+        let port = self.port.unwrap_or(80);
+        let lookup = resolver
+            .ipv4_lookup(self.name.as_str())
+            .await
+            .map_err(|e| Error::because(ErrorType::InternalError, "dns A lookup failed", e))?;
+
+        let mut backends = BTreeSet::new();
+        for record in lookup.iter() {
+            let addr = InetSocketAddr::new(IpAddr::V4(record.0), port);
+            let node_config = build_node_config(&addr, None, self.tls, &self.sni);
+            backends.insert(build_backend(addr, node_config));
+        }
+
+        Ok(backends)
+    }
+
+    async fn discover_srv(
+        &self,
+        resolver: &TokioAsyncResolver,
+    ) -> pingora::Result<BTreeSet<Backend>> {
+        let lookup = resolver
+            .srv_lookup(self.name.as_str())
+            .await
+            .map_err(|e| Error::because(ErrorType::InternalError, "dns SRV lookup failed", e))?;
+
+        let mut backends = BTreeSet::new();
+        for srv in lookup.iter() {
+            let ip_lookup = match resolver.lookup_ip(srv.target().clone()).await {
+                Ok(ip_lookup) => ip_lookup,
+                Err(e) => {
+                    warn!("failed to resolve SRV target {} - {}", srv.target(), e);
+                    continue;
+                }
+            };
+
+            for ip in ip_lookup.iter() {
+                let addr = InetSocketAddr::new(ip, srv.port());
+                let weight = Some(srv.weight().max(1));
+                let node_config = build_node_config(&addr, weight, self.tls, &self.sni);
+                backends.insert(build_backend(addr, node_config));
+            }
+        }
+
+        Ok(backends)
+    }
+}
+
+#[async_trait]
+impl ServiceDiscovery for DnsDiscovery {
+    async fn discover(&self) -> pingora::Result<(BTreeSet<Backend>, HashMap<u64, bool>)> {
+        let backends = match self.record_type {
+            DnsRecordType::A => self.discover_a(&self.resolver).await?,
+            DnsRecordType::Srv => self.discover_srv(&self.resolver).await?,
+        };
+        ensure_discovered(&backends, &self.name)?;
+
+        debug!("discovered {} backends for {}", backends.len(), self.name);
+        Ok((backends, HashMap::new()))
+    }
+}
+
+pub struct FileDiscovery {
+    path: String,
+}
+
+#[async_trait]
+impl ServiceDiscovery for FileDiscovery {
+    async fn discover(&self) -> pingora::Result<(BTreeSet<Backend>, HashMap<u64, bool>)> {
+        let content = tokio::fs::read_to_string(&self.path).await.map_err(|e| {
+            Error::because(ErrorType::InternalError, "failed to read nodes file", e)
+        })?;
+
+        // json is valid yaml, so both formats are supported
+        let node_configs: Vec<UpstreamNodeConfig> =
+            serde_yaml::from_str(&content).map_err(|e| {
+                Error::because(ErrorType::InternalError, "failed to parse nodes file", e)
+            })?;
+
+        let mut backends = BTreeSet::new();
+        for node_config in node_configs {
+            let addrs = tokio::net::lookup_host(node_config.address.get_formatted_address())
+                .await
+                .map_err(|e| {
+                    Error::because(
+                        ErrorType::InternalError,
+                        "failed to resolve node address",
+                        e,
+                    )
+                })?;
+
+            for addr in addrs {
+                backends.insert(build_backend(addr, node_config.clone()));
+            }
+        }
+        ensure_discovered(&backends, &self.path)?;
+
+        debug!("discovered {} backends from {}", backends.len(), self.path);
+        Ok((backends, HashMap::new()))
+    }
+}
+
+pub fn build_discovery(
+    discovery_config: &DiscoveryConfig,
+) -> DakiaResult<Box<dyn ServiceDiscovery + Send + Sync>> {
+    match (&discovery_config.dns, &discovery_config.file) {
+        (Some(dns), None) => {
+            let record_type = discovery_config
+                .record_type
+                .clone()
+                .unwrap_or(DnsRecordType::Srv);
+
+            if let (DnsRecordType::A, None) = (&record_type, discovery_config.port) {
+                return Err(DakiaError::i_explain(format!(
+                    "port is required for dns A record discovery of {dns}"
+                )));
+            }
+
+            Ok(Box::new(DnsDiscovery {
+                name: dns.clone(),
+                record_type,
+                port: discovery_config.port,
+                tls: discovery_config.tls.unwrap_or(false),
+                sni: discovery_config.sni.clone(),
+                resolver: DnsDiscovery::build_resolver(),
+            }))
+        }
+        (None, Some(file)) => Ok(Box::new(FileDiscovery { path: file.clone() })),
+        _ => Err(DakiaError::i_explain(
+            "exactly one of dns or file is required in upstream discovery config",
+        )),
+    }
+}
+
+pub fn get_refresh_interval(discovery_config: &DiscoveryConfig) -> DakiaResult<Duration> {
+    match &discovery_config.refresh {
+        Some(refresh) => humantime::parse_duration(refresh).map_err(|e| {
+            DakiaError::i_explain(format!(
+                "invalid discovery refresh interval {refresh} - {e}"
+            ))
+        }),
+        None => Ok(DEFAULT_REFRESH_INTERVAL),
+    }
+}
+
+// re-resolves backends of load balancers which has an update frequency, selector of load balancer is swapped in place
+pub struct DiscoveryService {}
+
+impl DiscoveryService {
+    pub fn build() -> Self {
+        Self {}
+    }
+
+    async fn refresh_gateway_lbs(
+        gateway_state: &GatewayState,
+        last_refreshed_at: &mut HashMap<String, Instant>,
+        refreshed_keys: &mut Vec<String>,
+    ) {
+        let gateway_config = gateway_state.gateway_config();
+
+        for upstream_config in &gateway_config.upstreams {
+            let lb = match gateway_state.lb_registry().get(&upstream_config.name).await {
+                Ok(Some(lb)) => lb,
+                _ => continue,
+            };
+
+            let update_frequency = match lb.update_frequency {
+                Some(update_frequency) => update_frequency,
+                None => continue,
+            };
+
+            // version is part of the key, so that load balancers of new gateway state are refreshed independently
+            let key = format!(
+                "{}:{}:{}",
+                gateway_config.name,
+                gateway_state.version(),
+                upstream_config.name
+            );
+            refreshed_keys.push(key.clone());
+
+            let is_due = last_refreshed_at
+                .get(&key)
+                .is_none_or(|refreshed_at| refreshed_at.elapsed() >= update_frequency);
+            if !is_due {
+                continue;
+            }
+
+            if let Err(e) = update_lb(&gateway_config.name, &upstream_config.name, &lb).await {
+                warn!(
+                    "failed to refresh backends of upstream {} - {:?}",
+                    upstream_config.name, e
+                );
+            }
+            last_refreshed_at.insert(key, Instant::now());
+        }
+    }
+}
+
+#[async_trait]
+impl BackgroundService for DiscoveryService {
+    async fn start(&self, mut shutdown: ShutdownWatch) {
+        let mut last_refreshed_at: HashMap<String, Instant> = HashMap::new();
+
+        loop {
+            tokio::select! {
+                _ = shutdown.changed() => break,
+                _ = tokio::time::sleep(DISCOVERY_TICK) => {}
+            }
+
+            let gateway_state_stores = match DAKIA_STATE_STORE.get_gateway_stores() {
+                Ok(gateway_state_stores) => gateway_state_stores,
+                Err(e) => {
+                    warn!("failed to get gateway states for discovery - {:?}", e);
+                    continue;
+                }
+            };
+
+            let mut refreshed_keys: Vec<String> = vec![];
+            for gateway_state_store in gateway_state_stores {
+                let gateway_state = gateway_state_store.get_state();
+                Self::refresh_gateway_lbs(
+                    &gateway_state,
+                    &mut last_refreshed_at,
+                    &mut refreshed_keys,
+                )
+                .await;
+            }
+
+            // forget load balancers of replaced gateway states
+            last_refreshed_at.retain(|key, _| refreshed_keys.contains(key));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn discover_file(name: &str, content: &str) -> pingora::Result<BTreeSet<Backend>> {
+        let path = std::env::temp_dir().join(format!("dakia-{}-{name}", std::process::id()));
+        std::fs::write(&path, content).unwrap();
+        let discovery = FileDiscovery {
+            path: path.to_string_lossy().to_string(),
+        };
+        let result = discovery.discover().await;
+        std::fs::remove_file(&path).unwrap();
+        result.map(|(backends, _)| backends)
+    }
+
+    #[tokio::test]
+    async fn test_file_discovery() {
+        let content = r#"
+            - address:
+                host: 127.0.0.1
+                port: 3001
+              tls: false
+              weight: 3
+            - address:
+                host: 127.0.0.2
+                port: 3002
+              tls: true
+              sni: payment.internal
+        "#;
+        let backends = discover_file("nodes.yaml", content).await.unwrap();
+        assert_eq!(backends.len(), 2);
+
+        let backend = backends
+            .iter()
+            .find(|backend| backend.addr.to_string() == "127.0.0.2:3002")
+            .unwrap();
+        let node_config = backend.ext.get::<UpstreamNodeConfig>().unwrap();
+        assert!(node_config.tls);
+        assert_eq!(node_config.sni.as_deref(), Some("payment.internal"));
+
+        let content = r#"[{"address": {"host": "127.0.0.1", "port": 3001}, "tls": false}]"#;
+        assert_eq!(discover_file("nodes.json", content).await.unwrap().len(), 1);
+
+        assert!(discover_file("invalid.yaml", "- host: 127.0.0.1")
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_empty_discovery() {
+        // previously discovered backends are kept when discovery returns nothing
+        assert!(discover_file("empty.json", "[]").await.is_err());
+    }
+}
@@ -1,9 +1,15 @@
+pub mod affinity;
+pub mod discovery;
+pub mod node_state;
+
 use std::{collections::HashMap, sync::Arc};
 
 use async_trait::async_trait;
+use log::warn;
 use pingora::lb::{
+    discovery::Static,
     selection::{algorithms::RoundRobin, weighted::Weighted},
-    LoadBalancer,
+    Backends, LoadBalancer,
 };
 
 use tokio::sync::RwLock;
@@ -14,7 +20,7 @@ use crate::{
     shared::registry::Registry,
 };
 
-type LB = LoadBalancer<Weighted<RoundRobin>>;
+pub type LB = LoadBalancer<Weighted<RoundRobin>>;
 
 pub struct LoadBalancerRegistry {
     registry: RwLock<HashMap<String, Arc<LB>>>,
@@ -44,15 +50,37 @@ impl LoadBalancerRegistry {
     }
 }
 
-pub fn build_lb(upstream_config: &UpstreamConfig) -> DakiaResult<LB> {
-    let addrs: Vec<String> = upstream_config
-        .upstream_nodes
-        .iter()
-        .map(|node| node.address.get_formatted_address())
-        .collect();
+pub async fn build_lb(gateway_name: &str, upstream_config: &UpstreamConfig) -> DakiaResult<LB> {
+    let discovery_config = match &upstream_config.discovery {
+        Some(discovery_config) => discovery_config,
+        None => {
+            let backends = discovery::build_static_backends(upstream_config).await?;
+            let lb: LB = LoadBalancer::from_backends(Backends::new(Static::new(backends)));
+            update_lb(gateway_name, &upstream_config.name, &lb).await?;
+            return Ok(lb);
+        }
+    };
+
+    let service_discovery = discovery::build_discovery(discovery_config)?;
+    let mut lb: LB = LoadBalancer::from_backends(Backends::new(service_discovery));
+    lb.update_frequency = Some(discovery::get_refresh_interval(discovery_config)?);
+
+    // gateway should start even if discovery source is not reachable yet, backends are refreshed in background
+    if let Err(e) = update_lb(gateway_name, &upstream_config.name, &lb).await {
+        warn!(
+            "initial discovery of upstream {} failed - {:?}",
+            upstream_config.name, e
+        );
+    }
 
-    let lb: LoadBalancer<Weighted<RoundRobin>> = LoadBalancer::try_from_iter(addrs)?;
     Ok(lb)
 }
 
+// discovers backends of load balancer and keeps track of joined nodes for slow start
+pub async fn update_lb(gateway_name: &str, upstream_name: &str, lb: &LB) -> DakiaResult<()> {
+    lb.update().await?;
+    node_state::sync_nodes(gateway_name, upstream_name, &lb.backends().get_backend());
+    Ok(())
+}
+
 pub type LbRegistryType = Arc<dyn Registry<Arc<LB>> + Send + Sync>;
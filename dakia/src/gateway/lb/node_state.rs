@@ -0,0 +1,264 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    time::{Duration, Instant},
+};
+
+use dashmap::DashMap;
+use once_cell::sync::Lazy;
+use pingora::lb::Backend;
+use prometheus::IntGauge;
+use rand::Rng;
+
+use crate::{
+    config::{
+        source_config::{UpstreamConfig, UpstreamNodeConfig},
+        InetAddress,
+    },
+    error::DakiaResult,
+    shared::metrics::UPSTREAM_NODE_IN_FLIGHT_REQUESTS,
+};
+
+use super::LB;
+
+// join time of upstream nodes which are warming up, keyed by gateway:upstream and then by node address
+// it lives outside of gateway state, so that rebuilding gateway state doesn't restart slow start of known nodes
+static NODE_JOINED_AT: Lazy<DashMap<String, HashMap<String, Option<Instant>>>> =
+    Lazy::new(DashMap::new);
+
+fn upstream_key(gateway_name: &str, upstream_name: &str) -> String {
+    format!("{gateway_name}:{upstream_name}")
+}
+
+pub fn is_draining(backend: &Backend) -> bool {
+    backend
+        .ext
+        .get::<UpstreamNodeConfig>()
+        .is_some_and(|node_config| node_config.drain.unwrap_or(false))
+}
+
+// draining nodes keep serving in-flight requests, but they don't receive new requests
+pub fn is_selectable(lb: &LB, backend: &Backend) -> bool {
+    lb.backends().ready(backend) && !is_draining(backend)
+}
+
+// in-flight requests of node summed over addresses its host resolves to, requests of previous gateway states are counted as well
+pub async fn in_flight_requests(
+    gateway_name: &str,
+    upstream_name: &str,
+    address: &InetAddress,
+) -> DakiaResult<i64> {
+    let addrs = tokio::net::lookup_host(address.get_formatted_address()).await?;
+    let in_flight_requests = addrs
+        .map(|addr| {
+            UPSTREAM_NODE_IN_FLIGHT_REQUESTS
+                .with_label_values(&[gateway_name, upstream_name, &addr.to_string()])
+                .get()
+        })
+        .sum();
+    Ok(in_flight_requests)
+}
+
+fn in_flight_requests_gauge(gateway_name: &str, upstream_name: &str, addr: &str) -> IntGauge {
+    UPSTREAM_NODE_IN_FLIGHT_REQUESTS.with_label_values(&[gateway_name, upstream_name, addr])
+}
+
+// label set of node which left the upstream is removed, so that node churn doesn't grow the metric
+// requests of previous gateway states may still be in flight on it, it's removed by the last of them in that case
+fn remove_in_flight_requests_gauge(gateway_name: &str, upstream_name: &str, addr: &str) {
+    if in_flight_requests_gauge(gateway_name, upstream_name, addr).get() <= 0 {
+        // label set is known to exist, as it's created by the lookup above
+        let _ = UPSTREAM_NODE_IN_FLIGHT_REQUESTS.remove_label_values(&[
+            gateway_name,
+            upstream_name,
+            addr,
+        ]);
+    }
+}
+
+pub fn start_request(gateway_name: &str, upstream_name: &str, addr: &str) {
+    in_flight_requests_gauge(gateway_name, upstream_name, addr).inc();
+}
+
+pub fn finish_request(gateway_name: &str, upstream_name: &str, addr: &str) {
+    in_flight_requests_gauge(gateway_name, upstream_name, addr).dec();
+
+    let is_known_node = NODE_JOINED_AT
+        .get(&upstream_key(gateway_name, upstream_name))
+        .is_some_and(|joined_at| joined_at.contains_key(addr));
+    if !is_known_node {
+        remove_in_flight_requests_gauge(gateway_name, upstream_name, addr);
+    }
+}
+
+// records nodes which joined the upstream since last sync and forgets nodes which left it
+// nodes of an upstream seen for the first time are considered warm, as there is no existing node to take their traffic
+pub fn sync_nodes(gateway_name: &str, upstream_name: &str, backends: &BTreeSet<Backend>) {
+    let key = upstream_key(gateway_name, upstream_name);
+    let is_new_upstream = !NODE_JOINED_AT.contains_key(&key);
+    let mut joined_at = NODE_JOINED_AT.entry(key).or_default();
+
+    let addrs: Vec<String> = backends
+        .iter()
+        .map(|backend| backend.addr.to_string())
+        .collect();
+
+    // nodes removed by controller leave the upstream when gateway state is rebuilt
+    joined_at.retain(|addr, _| {
+        let is_kept = addrs.contains(addr);
+        if !is_kept {
+            remove_in_flight_requests_gauge(gateway_name, upstream_name, addr);
+        }
+        is_kept
+    });
+
+    let now = Instant::now();
+    for addr in addrs {
+        joined_at
+            .entry(addr)
+            .or_insert(if is_new_upstream { None } else { Some(now) });
+    }
+}
+
+// fraction of weight a node should get, it grows linearly from zero to one within slow start window
+fn effective_weight_ratio(
+    gateway_name: &str,
+    upstream_name: &str,
+    backend: &Backend,
+    slow_start: Duration,
+) -> f64 {
+    let joined_at = NODE_JOINED_AT
+        .get(&upstream_key(gateway_name, upstream_name))
+        .and_then(|joined_at| joined_at.get(&backend.addr.to_string()).copied())
+        .flatten();
+
+    match joined_at {
+        Some(joined_at) if !slow_start.is_zero() => {
+            (joined_at.elapsed().as_secs_f64() / slow_start.as_secs_f64()).min(1.0)
+        }
+        _ => 1.0,
+    }
+}
+
+pub fn select(lb: &LB, gateway_name: &str, upstream_config: &UpstreamConfig) -> Option<Backend> {
+    if let Some(slow_start) = upstream_config.slow_start {
+        let slow_start = Duration::from_millis(slow_start);
+
+        // warming up node is skipped with probability proportional to remaining slow start window
+        let backend = lb.select_with(b"", 256, |backend, _| {
+            if !is_selectable(lb, backend) {
+                return false;
+            }
+
+            let ratio =
+                effective_weight_ratio(gateway_name, &upstream_config.name, backend, slow_start);
+            ratio >= 1.0 || rand::thread_rng().gen_bool(ratio)
+        });
+
+        if backend.is_some() {
+            return backend;
+        }
+    }
+
+    // every selectable node may be warming up, in that case nodes are selected as usual
+    lb.select_with(b"", 256, |backend, _| is_selectable(lb, backend))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn backends(addrs: &[&str]) -> BTreeSet<Backend> {
+        addrs
+            .iter()
+            .map(|addr| Backend::new(addr).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_slow_start_of_joined_node() {
+        let slow_start = Duration::from_secs(60);
+        sync_nodes("test", "payment", &backends(&["127.0.0.1:3000"]));
+        sync_nodes(
+            "test",
+            "payment",
+            &backends(&["127.0.0.1:3000", "127.0.0.1:3001"]),
+        );
+
+        let initial_node = Backend::new("127.0.0.1:3000").unwrap();
+        let joined_node = Backend::new("127.0.0.1:3001").unwrap();
+        assert_eq!(
+            effective_weight_ratio("test", "payment", &initial_node, slow_start),
+            1.0
+        );
+        assert!(effective_weight_ratio("test", "payment", &joined_node, slow_start) < 0.1);
+
+        // node which leaves and comes back warms up again
+        sync_nodes("test", "payment", &backends(&["127.0.0.1:3001"]));
+        sync_nodes(
+            "test",
+            "payment",
+            &backends(&["127.0.0.1:3000", "127.0.0.1:3001"]),
+        );
+        assert!(effective_weight_ratio("test", "payment", &initial_node, slow_start) < 0.1);
+    }
+
+    #[tokio::test]
+    async fn test_in_flight_requests() {
+        let address = InetAddress {
+            host: "127.0.0.1".to_string(),
+            port: 3005,
+        };
+        assert_eq!(
+            in_flight_requests("test", "search", &address)
+                .await
+                .unwrap(),
+            0
+        );
+
+        let gauge = UPSTREAM_NODE_IN_FLIGHT_REQUESTS.with_label_values(&[
+            "test",
+            "search",
+            "127.0.0.1:3005",
+        ]);
+        gauge.inc();
+        gauge.inc();
+        assert_eq!(
+            in_flight_requests("test", "search", &address)
+                .await
+                .unwrap(),
+            2
+        );
+        gauge.dec();
+        gauge.dec();
+    }
+
+    fn has_in_flight_requests_gauge(addr: &str) -> bool {
+        // removed gauge is created again by lookup, so it's removed to check if it existed
+        UPSTREAM_NODE_IN_FLIGHT_REQUESTS
+            .remove_label_values(&["test", "orders", addr])
+            .is_ok()
+    }
+
+    #[test]
+    fn test_in_flight_requests_gauge_of_left_node() {
+        sync_nodes(
+            "test",
+            "orders",
+            &backends(&["127.0.0.1:3000", "127.0.0.1:3001"]),
+        );
+        start_request("test", "orders", "127.0.0.1:3000");
+        start_request("test", "orders", "127.0.0.1:3001");
+        finish_request("test", "orders", "127.0.0.1:3001");
+
+        sync_nodes("test", "orders", &backends(&[]));
+        assert!(!has_in_flight_requests_gauge("127.0.0.1:3001"));
+
+        // node which left with requests in flight keeps its gauge till they are finished
+        assert_eq!(
+            in_flight_requests_gauge("test", "orders", "127.0.0.1:3000").get(),
+            1
+        );
+        finish_request("test", "orders", "127.0.0.1:3000");
+        assert!(!has_in_flight_requests_gauge("127.0.0.1:3000"));
+    }
+}
@@ -1,10 +1,15 @@
+pub mod circuit_breaker;
+pub mod consumer;
 pub mod filter;
 pub mod interceptor;
 pub mod interceptor_builder;
 pub mod interceptors;
 pub mod lb;
+pub mod path_rewrite;
 pub mod registry_builder;
+pub mod router_index;
 pub mod state;
+pub mod traffic_split;
 
 use super::Proxy;
 use pingora::{server::configuration::ServerConf, services::listening::Service};
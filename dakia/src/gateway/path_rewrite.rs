@@ -0,0 +1,76 @@
+use std::collections::HashMap;
+
+fn ensure_leading_slash(path: String) -> String {
+    if path.starts_with('/') {
+        path
+    } else {
+        format!("/{path}")
+    }
+}
+
+// prefix is stripped only at segment boundary, so /api is not stripped from /apis
+pub fn strip_prefix(path: &str, prefix: &str) -> Option<String> {
+    let rest = path.strip_prefix(prefix)?;
+    let is_segment_boundary = rest.is_empty() || rest.starts_with('/') || prefix.ends_with('/');
+    if !is_segment_boundary {
+        return None;
+    }
+
+    Some(ensure_leading_slash(rest.to_string()))
+}
+
+// placeholders like {name} are replaced with captured values, unknown placeholders are replaced with empty string
+pub fn render_path_template(template: &str, captures: &HashMap<String, String>) -> String {
+    let mut path = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        let end = match rest[start..].find('}') {
+            Some(end) => start + end,
+            None => break,
+        };
+
+        path.push_str(&rest[..start]);
+        if let Some(value) = captures.get(&rest[start + 1..end]) {
+            path.push_str(value);
+        }
+        rest = &rest[end + 1..];
+    }
+    path.push_str(rest);
+
+    ensure_leading_slash(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_prefix() {
+        assert_eq!(
+            strip_prefix("/api/users", "/api"),
+            Some("/users".to_string())
+        );
+        assert_eq!(strip_prefix("/api", "/api"), Some("/".to_string()));
+        assert_eq!(
+            strip_prefix("/api/users", "/api/"),
+            Some("/users".to_string())
+        );
+        assert_eq!(strip_prefix("/apis", "/api"), None);
+        assert_eq!(strip_prefix("/users", "/api"), None);
+    }
+
+    #[test]
+    fn test_render_path_template() {
+        let captures = HashMap::from([
+            ("svc".to_string(), "orders".to_string()),
+            ("rest".to_string(), "v1/items".to_string()),
+        ]);
+        assert_eq!(render_path_template("/{rest}", &captures), "/v1/items");
+        assert_eq!(
+            render_path_template("/{svc}/internal/{rest}", &captures),
+            "/orders/internal/v1/items"
+        );
+        assert_eq!(render_path_template("{unknown}", &captures), "/");
+    }
+}
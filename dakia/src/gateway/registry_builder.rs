@@ -4,7 +4,7 @@ use crate::{
     config::source_config::GatewayConfig,
     error::DakiaResult,
     shared::{
-        pattern_matcher::Pcre2PatternMatcher,
+        host_matcher::HostPatternMatcher,
         pattern_registry::{PatternRegistry, PatternRegistryType},
         registry::Registry,
     },
@@ -18,9 +18,9 @@ pub async fn build_ds_host_pattern_registry(
     let pattern_registry = PatternRegistry::build();
     for ds in &gateway_config.downstreams {
         let ds_addr = ds.get_formatted_address();
-        let pcre2pattern_matcher = Pcre2PatternMatcher::build(&ds_addr)?;
+        let host_pattern_matcher = HostPatternMatcher::build(&ds.host, ds.port)?;
         let _ = pattern_registry
-            .register(ds_addr, Arc::new(pcre2pattern_matcher))
+            .register(ds_addr, Arc::new(host_pattern_matcher))
             .await;
     }
 
@@ -30,7 +30,7 @@ pub async fn build_ds_host_pattern_registry(
 pub async fn build_lb_registry(gateway_config: &GatewayConfig) -> DakiaResult<LbRegistryType> {
     let lb_registry = LoadBalancerRegistry::build();
     for upstream_config in &gateway_config.upstreams {
-        let lb = build_lb(upstream_config)?;
+        let lb = build_lb(&gateway_config.name, upstream_config).await?;
         let arc_lb = Arc::new(lb);
 
         let _ = lb_registry
@@ -0,0 +1,181 @@
+use crate::{config::source_config::RouterConfig, shared::mutable_registry::Registry};
+
+use super::filter::{Filter, PathCondition};
+
+#[derive(Debug, Clone, Default)]
+struct RadixNode {
+    // label of edge from parent, root has an empty label
+    label: Vec<u8>,
+    children: Vec<RadixNode>,
+    // positions of routers whose path is exactly or starts with the path upto this node
+    exact_routers: Vec<usize>,
+    prefix_routers: Vec<usize>,
+}
+
+fn common_prefix_len(a: &[u8], b: &[u8]) -> usize {
+    a.iter().zip(b).take_while(|(a, b)| a == b).count()
+}
+
+impl RadixNode {
+    fn insert(&mut self, key: &[u8], is_prefix: bool, router_position: usize) {
+        if key.is_empty() {
+            if is_prefix {
+                self.prefix_routers.push(router_position);
+            } else {
+                self.exact_routers.push(router_position);
+            }
+            return;
+        }
+
+        let child = self
+            .children
+            .iter_mut()
+            .find(|child| child.label.first() == key.first());
+
+        let child = match child {
+            Some(child) => child,
+            None => {
+                self.children.push(RadixNode {
+                    label: key.to_vec(),
+                    ..Default::default()
+                });
+                // safe to unwrap, child is pushed right above
+                let child = self.children.last_mut().unwrap();
+                return child.insert(&[], is_prefix, router_position);
+            }
+        };
+
+        let prefix_len = common_prefix_len(&child.label, key);
+        if prefix_len < child.label.len() {
+            // edge is split, so that common part of both keys becomes a node
+            let suffix_node = RadixNode {
+                label: child.label.split_off(prefix_len),
+                children: std::mem::take(&mut child.children),
+                exact_routers: std::mem::take(&mut child.exact_routers),
+                prefix_routers: std::mem::take(&mut child.prefix_routers),
+            };
+            child.children.push(suffix_node);
+        }
+
+        child.insert(&key[prefix_len..], is_prefix, router_position);
+    }
+
+    fn collect(&self, key: &[u8], router_positions: &mut Vec<usize>) {
+        router_positions.extend(&self.prefix_routers);
+        if key.is_empty() {
+            router_positions.extend(&self.exact_routers);
+            return;
+        }
+
+        let child = self
+            .children
+            .iter()
+            .find(|child| key.starts_with(&child.label));
+
+        if let Some(child) = child {
+            child.collect(&key[child.label.len()..], router_positions);
+        }
+    }
+}
+
+// routers with a path $eq or $starts_with condition are indexed in a radix trie by that path
+// rest of the routers are evaluated for every request
+#[derive(Debug, Clone, Default)]
+pub struct RouterIndex {
+    root: RadixNode,
+    unindexed_routers: Vec<usize>,
+}
+
+impl RouterIndex {
+    pub fn build(routers: &[RouterConfig], filter_registry: &Registry<Filter>) -> Self {
+        let mut router_index = RouterIndex::default();
+
+        for (router_position, router_config) in routers.iter().enumerate() {
+            let path_condition = router_config
+                .filter
+                .as_ref()
+                .and_then(|filter_name| filter_registry.get(filter_name))
+                .and_then(Filter::path_condition);
+
+            match path_condition {
+                Some(PathCondition::Exact(path)) => {
+                    router_index.root.insert(path, false, router_position)
+                }
+                Some(PathCondition::Prefix(path)) => {
+                    router_index.root.insert(path, true, router_position)
+                }
+                None => router_index.unindexed_routers.push(router_position),
+            }
+        }
+
+        router_index
+    }
+
+    // positions of routers which can match the path, in declaration order
+    // their filters still needs to be evaluated, as filters can have conditions other than path
+    pub fn candidates(&self, path: &str) -> Vec<usize> {
+        let mut router_positions = self.unindexed_routers.clone();
+        self.root.collect(path.as_bytes(), &mut router_positions);
+        router_positions.sort_unstable();
+        router_positions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::source_config::GatewayConfig, gateway::filter::build_filter_registry};
+
+    fn build_router_index(filters: &str, routers: &str) -> RouterIndex {
+        let mut gateway_config = GatewayConfig {
+            filters: serde_yaml::from_str(filters).unwrap(),
+            ..Default::default()
+        };
+        let filter_registry = build_filter_registry(&mut gateway_config).unwrap();
+        let routers: Vec<RouterConfig> = serde_yaml::from_str(routers).unwrap();
+        RouterIndex::build(&routers, &filter_registry)
+    }
+
+    #[test]
+    fn test_router_candidates() {
+        let router_index = build_router_index(
+            r#"
+            - name: users
+              path: /users
+            - name: users_prefix
+              path:
+                $starts_with: /users/
+              method: GET
+            - name: user_admin
+              path:
+                $starts_with: /users/admin
+            - name: api
+              path:
+                $starts_with: /api
+            - name: regex
+              path:
+                $matches: ^/orders/[0-9]+$
+            "#,
+            r#"
+            - filter: users
+              upstream: a
+            - filter: users_prefix
+              upstream: a
+            - filter: user_admin
+              upstream: a
+            - filter: api
+              upstream: a
+            - filter: regex
+              upstream: a
+            - upstream: a
+            "#,
+        );
+
+        assert_eq!(router_index.candidates("/users"), vec![0, 4, 5]);
+        assert_eq!(router_index.candidates("/users/42"), vec![1, 4, 5]);
+        assert_eq!(router_index.candidates("/users/admin/1"), vec![1, 2, 4, 5]);
+        assert_eq!(router_index.candidates("/apis"), vec![3, 4, 5]);
+        assert_eq!(router_index.candidates("/orders/1"), vec![4, 5]);
+        assert_eq!(router_index.candidates("/"), vec![4, 5]);
+    }
+}
@@ -4,13 +4,19 @@ use crate::{
     shared::{mutable_registry::Registry, pattern_registry::PatternRegistryType},
 };
 use arc_swap::ArcSwap;
-use std::sync::Arc;
+use std::{cmp::Reverse, sync::Arc};
 
 use super::{
+    circuit_breaker::{build_circuit_breaker_registry, CircuitBreaker},
+    consumer::{build_consumer_registry, hash_api_key, Consumer},
     filter::{build_filter_registry, Filter},
     interceptor::Interceptor,
-    interceptor_builder::{utils::build_interceptors, InterceptorBuilderRegistry},
+    interceptor_builder::{
+        utils::{build_ds_interceptors, build_interceptors},
+        InterceptorBuilderRegistry,
+    },
     lb, registry_builder,
+    router_index::RouterIndex,
 };
 
 #[derive(Clone)]
@@ -21,10 +27,21 @@ pub struct GatewayState {
     lb_registry: lb::LbRegistryType,
     _interceptor_builder_registry: InterceptorBuilderRegistry,
     interceptors: Vec<Arc<dyn Interceptor>>,
+    // interceptors of each downstream, indexed as downstreams of gateway config
+    ds_interceptors: Vec<Vec<Arc<dyn Interceptor>>>,
     filter_registry: Registry<Filter>,
+    // router index of gateway and of each downstream which has its own routers
+    router_index: RouterIndex,
+    ds_router_indexes: Vec<Option<RouterIndex>>,
+    // true if any filter matches on body of request
+    is_ds_req_body_required: bool,
+    circuit_breaker_registry: Registry<Arc<CircuitBreaker>>,
+    // consumers indexed by hash of their api key
+    consumer_registry: Registry<Arc<Consumer>>,
 }
 
 impl GatewayState {
+    #[allow(clippy::too_many_arguments)]
     pub fn build(
         version: ConfigVersion,
         gateway_config: GatewayConfig,
@@ -32,8 +49,24 @@ impl GatewayState {
         lb_registry: lb::LbRegistryType,
         interceptor_builder_registry: InterceptorBuilderRegistry,
         interceptors: Vec<Arc<dyn Interceptor>>,
+        ds_interceptors: Vec<Vec<Arc<dyn Interceptor>>>,
         filter_registry: Registry<Filter>,
+        circuit_breaker_registry: Registry<Arc<CircuitBreaker>>,
+        consumer_registry: Registry<Arc<Consumer>>,
     ) -> Self {
+        let is_ds_req_body_required = filter_registry.values().any(Filter::requires_body);
+        let router_index = RouterIndex::build(&gateway_config.routers, &filter_registry);
+        let ds_router_indexes = gateway_config
+            .downstreams
+            .iter()
+            .map(|downstream_config| {
+                downstream_config
+                    .routers
+                    .as_ref()
+                    .map(|routers| RouterIndex::build(routers, &filter_registry))
+            })
+            .collect();
+
         Self {
             version,
             gateway_config,
@@ -41,7 +74,13 @@ impl GatewayState {
             lb_registry,
             _interceptor_builder_registry: interceptor_builder_registry,
             interceptors,
+            ds_interceptors,
             filter_registry,
+            router_index,
+            ds_router_indexes,
+            is_ds_req_body_required,
+            circuit_breaker_registry,
+            consumer_registry,
         }
     }
 
@@ -61,18 +100,58 @@ impl GatewayState {
         &self.interceptors
     }
 
+    pub fn ds_interceptors(&self, ds_index: Option<usize>) -> &[Arc<dyn Interceptor>] {
+        ds_index
+            .and_then(|ds_index| self.ds_interceptors.get(ds_index))
+            .map(|interceptors| interceptors.as_slice())
+            .unwrap_or_default()
+    }
+
+    // interceptors of downstream are executed after interceptors of gateway
+    pub fn request_interceptors(
+        &self,
+        ds_index: Option<usize>,
+    ) -> impl Iterator<Item = &Arc<dyn Interceptor>> {
+        self.interceptors()
+            .iter()
+            .chain(self.ds_interceptors(ds_index))
+    }
+
+    // index of routers returned by GatewayConfig::routers for the same downstream
+    pub fn router_index(&self, ds_index: Option<usize>) -> &RouterIndex {
+        ds_index
+            .and_then(|ds_index| self.ds_router_indexes.get(ds_index))
+            .and_then(|router_index| router_index.as_ref())
+            .unwrap_or(&self.router_index)
+    }
+
+    pub fn is_ds_req_body_required(&self) -> bool {
+        self.is_ds_req_body_required
+    }
+
     pub fn filter(&self, filter_name: &str) -> Option<&Filter> {
         self.filter_registry.get(filter_name)
     }
 
+    pub fn filter_names(&self) -> impl Iterator<Item = &String> {
+        self.filter_registry.keys()
+    }
+
     pub fn filter_or_err(&self, filter_name: &str) -> DakiaResult<&Filter> {
-        self.filter_registry
-            .get(filter_name)
+        self.filter(filter_name)
             .ok_or(DakiaError::i_explain(format!(
                 "expected filter {filter_name} not found in filter registry"
             )))
     }
 
+    pub fn circuit_breaker(&self, upstream_name: &str) -> Option<&Arc<CircuitBreaker>> {
+        self.circuit_breaker_registry.get(upstream_name)
+    }
+
+    pub fn find_consumer(&self, api_key: &str) -> Option<&Arc<Consumer>> {
+        self.consumer_registry.get(&hash_api_key(api_key))
+    }
+
     pub fn version(&self) -> ConfigVersion {
         self.version
     }
@@ -105,17 +184,41 @@ impl GatewayStateStore {
     }
 }
 
+// current state of the same gateway is given when state is rebuilt, runtime state like open circuits is carried over from it
 pub async fn build_gateway_state(
     mut gateway_config: GatewayConfig,
     version: ConfigVersion,
+    current_state: Option<&GatewayState>,
 ) -> DakiaResult<GatewayState> {
+    let ds_router_configs = gateway_config
+        .downstreams
+        .iter()
+        .filter_map(|downstream_config| downstream_config.routers.as_ref())
+        .flatten();
+    for router_config in gateway_config.routers.iter().chain(ds_router_configs) {
+        router_config.validate()?;
+    }
+
     let ds_host_pattern_registry =
         registry_builder::build_ds_host_pattern_registry(&gateway_config).await?;
     let lb_registry = registry_builder::build_lb_registry(&gateway_config).await?;
 
+    // stable sort, routers with same priority keep declaration order
+    gateway_config
+        .routers
+        .sort_by_key(|router_config| Reverse(router_config.priority.unwrap_or(0)));
+    for downstream_config in &mut gateway_config.downstreams {
+        if let Some(routers) = &mut downstream_config.routers {
+            routers.sort_by_key(|router_config| Reverse(router_config.priority.unwrap_or(0)));
+        }
+    }
+
     let interceptor_builder_registry = InterceptorBuilderRegistry::build();
     let filter_registry = build_filter_registry(&mut gateway_config)?;
     let interceptors = build_interceptors(&gateway_config, &interceptor_builder_registry)?;
+    let ds_interceptors = build_ds_interceptors(&gateway_config, &interceptor_builder_registry)?;
+    let circuit_breaker_registry = build_circuit_breaker_registry(&gateway_config, current_state);
+    let consumer_registry = build_consumer_registry(&gateway_config)?;
     let gateway_state = GatewayState::build(
         version,
         gateway_config,
@@ -123,8 +226,68 @@ pub async fn build_gateway_state(
         lb_registry,
         interceptor_builder_registry,
         interceptors,
+        ds_interceptors,
         filter_registry,
+        circuit_breaker_registry,
+        consumer_registry,
     );
 
     Ok(gateway_state)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gateway_config(min_requests: u32) -> GatewayConfig {
+        let yaml = format!(
+            r#"
+            name: root
+            bind_addresses:
+              - host: 127.0.0.1
+                port: 8080
+            downstreams:
+              - host: example.com
+            upstreams:
+              - name: web
+                default: true
+                upstream_nodes:
+                  - address:
+                      host: 127.0.0.1
+                      port: 3001
+                    tls: false
+                circuit_breaker:
+                  min_requests: {min_requests}
+                  open_timeout: 60000
+            "#
+        );
+        serde_yaml::from_str(&yaml).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_rebuild_keeps_open_circuit_breaker() {
+        let gateway_state = build_gateway_state(gateway_config(2), 0, None)
+            .await
+            .unwrap();
+        let circuit_breaker = gateway_state.circuit_breaker("web").unwrap();
+        circuit_breaker.record(false);
+        circuit_breaker.record(false);
+        assert!(!circuit_breaker.allow_request());
+
+        let rebuilt_state = build_gateway_state(gateway_config(2), 1, Some(&gateway_state))
+            .await
+            .unwrap();
+        let rebuilt_circuit_breaker = rebuilt_state.circuit_breaker("web").unwrap();
+        assert!(Arc::ptr_eq(circuit_breaker, rebuilt_circuit_breaker));
+        assert!(!rebuilt_circuit_breaker.allow_request());
+
+        // changed config gets a closed circuit breaker
+        let rebuilt_state = build_gateway_state(gateway_config(3), 2, Some(&gateway_state))
+            .await
+            .unwrap();
+        assert!(rebuilt_state
+            .circuit_breaker("web")
+            .unwrap()
+            .allow_request());
+    }
+}
@@ -0,0 +1,104 @@
+use rand::Rng;
+
+use crate::{config::source_config::WeightedUpstreamConfig, shared::crypto::stable_hash};
+
+fn total_weight(upstreams: &[WeightedUpstreamConfig]) -> u64 {
+    upstreams
+        .iter()
+        .map(|upstream| upstream.weight as u64)
+        .sum()
+}
+
+// upstreams own consecutive ranges of buckets in the order they are declared
+// so increasing weight of the last upstream only moves clients towards it, which is what canary release needs
+fn find_upstream_by_bucket(upstreams: &[WeightedUpstreamConfig], bucket: u64) -> Option<&str> {
+    let mut range_end = 0;
+    for upstream in upstreams {
+        range_end += upstream.weight as u64;
+        if bucket < range_end {
+            return Some(&upstream.name);
+        }
+    }
+
+    None
+}
+
+pub fn select_upstream(upstreams: &[WeightedUpstreamConfig]) -> Option<&str> {
+    let total_weight = total_weight(upstreams);
+    if total_weight == 0 {
+        return None;
+    }
+
+    let bucket = rand::thread_rng().gen_range(0..total_weight);
+    find_upstream_by_bucket(upstreams, bucket)
+}
+
+pub fn select_upstream_by_key<'a>(
+    upstreams: &'a [WeightedUpstreamConfig],
+    key: &[u8],
+) -> Option<&'a str> {
+    let total_weight = total_weight(upstreams);
+    if total_weight == 0 {
+        return None;
+    }
+
+    // hash is stable across builds, so that client stays on the same upstream across deploys
+    find_upstream_by_bucket(upstreams, stable_hash(&[key]) % total_weight)
+}
+
+// upstream is valid for a sticky client as long as it's still part of the split
+pub fn find_upstream<'a>(upstreams: &'a [WeightedUpstreamConfig], name: &str) -> Option<&'a str> {
+    upstreams
+        .iter()
+        .find(|upstream| upstream.name == name && upstream.weight > 0)
+        .map(|upstream| upstream.name.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn upstreams(v1_weight: u32, v2_weight: u32) -> Vec<WeightedUpstreamConfig> {
+        vec![
+            WeightedUpstreamConfig {
+                name: "v1".to_string(),
+                weight: v1_weight,
+            },
+            WeightedUpstreamConfig {
+                name: "v2".to_string(),
+                weight: v2_weight,
+            },
+        ]
+    }
+
+    #[test]
+    fn test_find_upstream_by_bucket() {
+        let upstreams = upstreams(95, 5);
+        assert_eq!(find_upstream_by_bucket(&upstreams, 0), Some("v1"));
+        assert_eq!(find_upstream_by_bucket(&upstreams, 94), Some("v1"));
+        assert_eq!(find_upstream_by_bucket(&upstreams, 95), Some("v2"));
+        assert_eq!(find_upstream_by_bucket(&upstreams, 100), None);
+    }
+
+    #[test]
+    fn test_zero_weight() {
+        assert_eq!(select_upstream(&upstreams(0, 0)), None);
+        assert_eq!(select_upstream(&upstreams(0, 1)), Some("v2"));
+        assert_eq!(find_upstream(&upstreams(0, 1), "v1"), None);
+    }
+
+    #[test]
+    fn test_select_upstream_by_key() {
+        // buckets of keys are pinned, so that sticky clients are not reshuffled by a new build
+        let split = upstreams(50, 50);
+        assert_eq!(select_upstream_by_key(&split, b"user-1"), Some("v1"));
+        assert_eq!(select_upstream_by_key(&split, b"user-2"), Some("v2"));
+        assert_eq!(select_upstream_by_key(&split, b"user-4"), Some("v1"));
+
+        // raising weight of canary only moves clients towards it
+        let split = upstreams(30, 70);
+        assert_eq!(select_upstream_by_key(&split, b"user-1"), Some("v2"));
+        assert_eq!(select_upstream_by_key(&split, b"user-2"), Some("v2"));
+        assert_eq!(select_upstream_by_key(&split, b"user-4"), Some("v1"));
+    }
+}
@@ -11,16 +11,20 @@ use std::{
 };
 
 use clap::Parser;
-use config::{DakiaArgs, DakiaConfig};
-use error::DakiaError;
+use config::{DakiaArgs, DakiaCommand, DakiaConfig, ExplainArgs};
+use error::{DakiaError, DakiaResult};
+use gateway::lb::discovery::DiscoveryService;
 use gateway::state::build_gateway_state;
 use gateway::state::GatewayStateStore;
 use gateway::HttpGateway;
 
-use pingora::server::{configuration::ServerConf, Server};
+use pingora::{
+    server::{configuration::ServerConf, Server},
+    services::{background::background_service, listening::Service},
+};
 use shared::{common::get_dakia_ascii_art, dakia_state::DAKIA_STATE_STORE};
 
-use proxy::http::Proxy;
+use proxy::http::{explain, ExplainRequest, Proxy};
 use shared::into::IntoRef;
 use tokio::runtime::Builder;
 
@@ -40,7 +44,9 @@ fn main() {
     // perform init steps
     init();
 
+    // io and time drivers are required for resolving upstreams through service discovery
     let runtime = Builder::new_current_thread()
+        .enable_all()
         .build()
         // if there is any error, just panic
         .unwrap();
@@ -59,9 +65,10 @@ fn main() {
             let cloned_gateway_config = gateway_config.clone();
 
             // dakia can not work without state, so unwrap is not a problem
-            let gateway_state = build_gateway_state(cloned_gateway_config, dakia_config.version)
-                .await
-                .unwrap();
+            let gateway_state =
+                build_gateway_state(cloned_gateway_config, dakia_config.version, None)
+                    .await
+                    .unwrap();
             let gateway_state_store = Arc::new(GatewayStateStore::new(gateway_state));
             let server_conf: ServerConf = dakia_config_cloned.into_ref();
 
@@ -100,6 +107,17 @@ fn main() {
         server.add_service(gateway);
     }
 
+    server.add_service(background_service(
+        "dakia discovery",
+        DiscoveryService::build(),
+    ));
+
+    if let Some(metrics_address) = &dakia_config.metrics_address {
+        let mut prometheus_service = Service::prometheus_http_service();
+        prometheus_service.add_tcp(&metrics_address.get_formatted_address());
+        server.add_service(prometheus_service);
+    }
+
     server.run_forever();
 }
 
@@ -107,7 +125,7 @@ fn init() {
     env_logger::init();
 }
 
-fn process_args(args: &DakiaArgs, dakia_config: &DakiaConfig) -> Result<(), Box<DakiaError>> {
+fn process_args(args: &DakiaArgs, dakia_config: &DakiaConfig) -> DakiaResult<()> {
     if args.version {
         // version will be printed along with dakia art in the very beginning, so just exist from here
         shared::common::exit();
@@ -125,6 +143,38 @@ fn process_args(args: &DakiaArgs, dakia_config: &DakiaConfig) -> Result<(), Box<
     if args.test {
         todo!();
     }
+
+    if let Some(DakiaCommand::Explain(explain_args)) = &args.command {
+        explain_request(explain_args, dakia_config)?;
+        shared::common::exit();
+    }
     // TODO: use kill -HUP pid
     Ok(())
 }
+
+// state of gateway is built from config on disk, so running server is not required
+fn explain_request(explain_args: &ExplainArgs, dakia_config: &DakiaConfig) -> DakiaResult<()> {
+    let gateway_config = match &explain_args.gateway {
+        Some(gateway_name) => dakia_config.find_gateway_config_or_err(gateway_name)?,
+        None => dakia_config
+            .gateways
+            .first()
+            .ok_or(DakiaError::i_explain("no gateway found in config"))?,
+    };
+
+    let runtime = Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .map_err(|e| DakiaError::i_explain(format!("failed to build runtime - {e}")))?;
+
+    let report = runtime.block_on(async {
+        let gateway_state =
+            build_gateway_state(gateway_config.clone(), dakia_config.version, None).await?;
+        explain(Arc::new(gateway_state), &ExplainRequest::from(explain_args)).await
+    })?;
+
+    let report = serde_yaml::to_string(&report)
+        .map_err(|e| DakiaError::i_explain(format!("failed to serialize explain report - {e}")))?;
+    println!("{report}");
+    Ok(())
+}
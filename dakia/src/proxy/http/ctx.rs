@@ -1,21 +1,101 @@
-use std::sync::Arc;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, OnceLock},
+    time::{Duration, Instant},
+};
 
-use crate::gateway::state::GatewayState;
+use bytes::Bytes;
 
-use super::HeaderBuffer;
+use crate::{
+    config::InetAddress,
+    gateway::{consumer::Consumer, state::GatewayState},
+};
+
+use super::{mirror::MirrorRequest, HeaderBuffer, QueryParams};
+
+// peer of upstream, it can be altered by interceptors in upstream_peer_selection phase
+#[derive(Default)]
+pub struct UpstreamPeerSelection {
+    pub upstream: Option<String>,
+    // request is sent to this node of upstream instead of the load balanced one
+    pub node: Option<InetAddress>,
+    pub sni: Option<String>,
+    pub connection_timeout: Option<Duration>,
+    pub read_timeout: Option<Duration>,
+    pub write_timeout: Option<Duration>,
+}
 
 pub struct DakiaHttpGatewayCtx {
     pub gateway_state: Arc<GatewayState>,
+    // index of downstream matched by host of request, its routers and interceptors are used for the request
+    pub ds_index: Option<usize>,
+    // query string of downstream request is parsed once, when it's accessed for the first time
+    pub ds_req_query_params: OnceLock<QueryParams>,
+    pub ds_req_cookies: OnceLock<HashMap<String, String>>,
+    // body of request buffered for filters, it's replayed to upstream by pingora
+    pub ds_req_body: Option<Bytes>,
+    pub ds_req_body_json: OnceLock<Option<serde_json::Value>>,
+    // buffered body is already drained from downstream, so it's served once by read_ds_req_body instead
+    pub is_ds_req_body_read: bool,
+    // claims of jwt validated by jwt_auth, token introspected by oauth2_introspect or login of oidc, filters can match them
+    pub jwt_claims: Option<serde_json::Map<String, serde_json::Value>>,
+    // consumer identified by api_key interceptor
+    pub consumer: Option<Arc<Consumer>>,
+    // user authenticated by basic_auth interceptor
+    pub authenticated_user: Option<String>,
+    // upstream headers granted by ext_authz interceptor, headers without value are removed
+    pub authz_us_req_headers: Vec<(String, Option<Vec<u8>>)>,
+    // results of named filters evaluated for the request, they stay valid till a part of request read by
+    // filters changes, see Session::reset_filter_results. ctx is only ever used by task of its request, but
+    // pingora requires it to be Sync, so results are kept in an uncontended mutex instead of a RefCell
+    pub filter_results: Mutex<HashMap<String, bool>>,
     pub ds_res_header_buffer: HeaderBuffer,
+    pub ds_res_cookies: Vec<String>,
+    // response header is written to downstream only once, even if multiple sessions are built for the request
+    pub is_ds_res_header_flushed: bool,
+    pub us_peer_selection: UpstreamPeerSelection,
     pub us_req_header_buffer: HeaderBuffer,
+    // rewritten path of upstream request as per router config
+    pub us_req_path: Option<String>,
+    // upstream selected for the request, outcome of the request is reported to its circuit breaker
+    pub upstream_name: Option<String>,
+    // address of upstream node serving the request, used to track in-flight requests of the node
+    pub upstream_node: Option<String>,
+    pub us_req_started_at: Option<Instant>,
+    pub us_res_status: Option<u16>,
+    pub us_res_latency: Option<Duration>,
+    pub mirror: Option<MirrorRequest>,
+    pub is_mirror_sampled: bool,
 }
 
 impl DakiaHttpGatewayCtx {
     pub fn new(gateway_state: Arc<GatewayState>) -> DakiaHttpGatewayCtx {
         DakiaHttpGatewayCtx {
             gateway_state,
+            ds_index: None,
+            ds_req_query_params: OnceLock::new(),
+            ds_req_cookies: OnceLock::new(),
+            ds_req_body: None,
+            ds_req_body_json: OnceLock::new(),
+            is_ds_req_body_read: false,
+            jwt_claims: None,
+            consumer: None,
+            authenticated_user: None,
+            authz_us_req_headers: vec![],
+            filter_results: Mutex::new(HashMap::new()),
             ds_res_header_buffer: HeaderBuffer::new(),
+            ds_res_cookies: vec![],
+            is_ds_res_header_flushed: false,
+            us_peer_selection: UpstreamPeerSelection::default(),
             us_req_header_buffer: HeaderBuffer::new(),
+            us_req_path: None,
+            upstream_name: None,
+            upstream_node: None,
+            us_req_started_at: None,
+            us_res_status: None,
+            us_res_latency: None,
+            mirror: None,
+            is_mirror_sampled: false,
         }
     }
 }
@@ -0,0 +1,355 @@
+use std::{io::Cursor, sync::Arc};
+
+use http::Uri;
+use pingora_proxy::Session as PSession;
+
+use crate::{
+    config::{source_config::find_router_config, ExplainArgs},
+    error::{DakiaError, DakiaResult},
+    gateway::{
+        filter::exec_named_filter,
+        interceptor::{is_phase_enabled, Phase},
+        state::GatewayState,
+    },
+};
+
+use super::{
+    helpers::{find_ds_index, resolve_router_path, resolve_router_upstream},
+    proxy::MAX_DS_REQ_BODY_SIZE,
+    DakiaHttpGatewayCtx, Session,
+};
+
+const PHASES: [Phase; 7] = [
+    Phase::Init,
+    Phase::RequestFilter,
+    Phase::UpstreamProxyFilter,
+    Phase::UpstreamPeerSelection,
+    Phase::PreUpstreamRequest,
+    Phase::PostUpstreamResponse,
+    Phase::PreDownstreamResponse,
+];
+
+fn default_method() -> String {
+    "GET".to_string()
+}
+
+// synthetic request, it's never sent to any upstream
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct ExplainRequest {
+    // gateway which receives the request, used only by controller to explain request of other gateway
+    pub gateway: Option<String>,
+    #[serde(default = "default_method")]
+    pub method: String,
+    pub url: String,
+    // headers in "name: value" format
+    #[serde(default)]
+    pub headers: Vec<String>,
+    pub body: Option<String>,
+}
+
+impl From<&ExplainArgs> for ExplainRequest {
+    fn from(explain_args: &ExplainArgs) -> Self {
+        ExplainRequest {
+            gateway: explain_args.gateway.clone(),
+            method: explain_args.method.clone(),
+            url: explain_args.url.clone(),
+            headers: explain_args.headers.clone(),
+            body: explain_args.data.clone(),
+        }
+    }
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct FilterExplain {
+    pub name: String,
+    pub matched: bool,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct RouterExplain {
+    // position of router after routers are sorted by priority
+    pub position: usize,
+    pub filter: Option<String>,
+    pub upstream: String,
+    // path of upstream request, None if router keeps downstream path as it is
+    pub us_req_path: Option<String>,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct InterceptorExplain {
+    pub name: String,
+    pub filter: Option<String>,
+    // interceptor is executed in phase only if its filter is matched
+    pub matched: bool,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct PhaseExplain {
+    pub phase: String,
+    pub interceptors: Vec<InterceptorExplain>,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct ExplainReport {
+    pub gateway: String,
+    // host pattern of matched downstream, request is rejected with 403 if it's None
+    pub downstream: Option<String>,
+    pub filters: Vec<FilterExplain>,
+    pub router: Option<RouterExplain>,
+    pub phases: Vec<PhaseExplain>,
+}
+
+// host of url is sent as host header, unless host header is given explicitly
+fn build_raw_request(request: &ExplainRequest) -> DakiaResult<Vec<u8>> {
+    let uri = request
+        .url
+        .parse::<Uri>()
+        .map_err(|e| DakiaError::i_explain(format!("invalid url {} - {e}", request.url)))?;
+
+    let path_and_query = uri
+        .path_and_query()
+        .map(|path_and_query| path_and_query.as_str())
+        .unwrap_or("/");
+
+    let mut raw_request = format!("{} {} HTTP/1.1\r\n", request.method, path_and_query);
+    let mut has_host = false;
+
+    for header in &request.headers {
+        let (name, value) = header.split_once(':').ok_or(DakiaError::i_explain(format!(
+            "invalid header {header}, expected name: value"
+        )))?;
+        has_host |= name.trim().eq_ignore_ascii_case("host");
+        raw_request.push_str(&format!("{}: {}\r\n", name.trim(), value.trim()));
+    }
+
+    if !has_host {
+        if let Some(authority) = uri.authority() {
+            raw_request.push_str(&format!("host: {authority}\r\n"));
+        }
+    }
+
+    let body = request.body.as_deref().unwrap_or_default();
+    if !body.is_empty() {
+        raw_request.push_str(&format!("content-length: {}\r\n", body.len()));
+    }
+
+    raw_request.push_str("\r\n");
+    raw_request.push_str(body);
+    Ok(raw_request.into_bytes())
+}
+
+// evaluates request the same way as proxy does, but interceptors are only listed not executed
+pub async fn explain(
+    gateway_state: Arc<GatewayState>,
+    request: &ExplainRequest,
+) -> DakiaResult<ExplainReport> {
+    let raw_request = build_raw_request(request)?;
+    let mut psession = PSession::new_h1(Box::new(Cursor::new(raw_request)));
+    if !psession.read_request().await? {
+        return Err(DakiaError::i_explain("failed to read synthetic request"));
+    }
+
+    let gateway_config = gateway_state.gateway_config();
+    let mut ctx = DakiaHttpGatewayCtx::new(gateway_state.clone());
+    if let Some(host) = psession.req_header().headers.get("host") {
+        ctx.ds_index = find_ds_index(
+            gateway_config,
+            gateway_state.pattern_registry(),
+            host.as_bytes(),
+        )
+        .await?;
+    }
+
+    let mut session = Session::build(Phase::Init, &mut psession, &mut ctx);
+    if gateway_state.is_ds_req_body_required() {
+        session.buffer_ds_req_body(MAX_DS_REQ_BODY_SIZE).await?;
+    }
+
+    let mut filter_names: Vec<&String> = gateway_state.filter_names().collect();
+    filter_names.sort();
+    let mut filters = vec![];
+    for filter_name in filter_names {
+        filters.push(FilterExplain {
+            name: filter_name.clone(),
+            matched: exec_named_filter(filter_name, &session)?,
+        });
+    }
+
+    let ds_index = session.ctx().ds_index;
+    let mut report = ExplainReport {
+        gateway: gateway_config.name.clone(),
+        downstream: ds_index
+            .map(|ds_index| gateway_config.downstreams[ds_index].get_formatted_address()),
+        filters,
+        router: None,
+        phases: vec![],
+    };
+
+    // request is rejected before any router or interceptor is considered
+    if ds_index.is_none() {
+        return Ok(report);
+    }
+
+    let routers = gateway_config.routers(ds_index);
+    let router = find_router_config(&session)?.map(|router_config| {
+        let position = routers
+            .iter()
+            .position(|cur_router_config| std::ptr::eq(cur_router_config, router_config))
+            .unwrap_or_default();
+        (position, router_config.clone())
+    });
+
+    if let Some((position, router_config)) = router {
+        report.router = Some(RouterExplain {
+            position,
+            filter: router_config.filter.clone(),
+            upstream: resolve_router_upstream(&mut session, &router_config)?,
+            us_req_path: resolve_router_path(&session, &router_config)?,
+        });
+    }
+
+    let interceptors: Vec<_> = gateway_state.request_interceptors(ds_index).collect();
+
+    for phase in PHASES {
+        let mut phase_explain = PhaseExplain {
+            phase: phase.to_string(),
+            interceptors: vec![],
+        };
+
+        for interceptor in &interceptors {
+            if !is_phase_enabled(interceptor.phase_mask(), &phase) {
+                continue;
+            }
+
+            let matched = match interceptor.filter() {
+                Some(filter_name) => exec_named_filter(filter_name, &session)?,
+                None => true,
+            };
+
+            phase_explain.interceptors.push(InterceptorExplain {
+                name: interceptor.name().as_str().to_string(),
+                filter: interceptor.filter().clone(),
+                matched,
+            });
+        }
+
+        report.phases.push(phase_explain);
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{config::source_config::GatewayConfig, gateway::state::build_gateway_state};
+
+    use super::*;
+
+    fn explain_request(url: &str) -> ExplainRequest {
+        ExplainRequest {
+            gateway: None,
+            method: "GET".to_string(),
+            url: url.to_string(),
+            headers: vec![],
+            body: None,
+        }
+    }
+
+    fn init_interceptors(report: &ExplainReport) -> Vec<&str> {
+        report.phases[0]
+            .interceptors
+            .iter()
+            .map(|interceptor| interceptor.name.as_str())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_downstream_routers_and_interceptors() {
+        let yaml = r#"
+            name: root
+            bind_addresses:
+              - host: 127.0.0.1
+                port: 8080
+            downstreams:
+              - host: api.example.com
+                routers:
+                  - upstream: api
+                interceptors:
+                  - name: request_id
+                    enabled: true
+              - host: example.com
+            upstreams:
+              - name: web
+                default: true
+                upstream_nodes:
+                  - address:
+                      host: 127.0.0.1
+                      port: 3001
+                    tls: false
+              - name: api
+                default: false
+                upstream_nodes:
+                  - address:
+                      host: 127.0.0.1
+                      port: 3002
+                    tls: false
+            routers:
+              - upstream: web
+            interceptors:
+              - name: response_rewrite
+                enabled: true
+                rewrite:
+                  header.x-gateway: root
+        "#;
+        let gateway_config: GatewayConfig = serde_yaml::from_str(yaml).unwrap();
+        let gateway_state = Arc::new(build_gateway_state(gateway_config, 0, None).await.unwrap());
+
+        // routers of downstream replace routers of gateway, its interceptors run after interceptors of gateway
+        let report = explain(
+            gateway_state.clone(),
+            &explain_request("http://api.example.com/x"),
+        )
+        .await
+        .unwrap();
+        assert_eq!(report.downstream.as_deref(), Some("api.example.com"));
+        assert_eq!(report.router.as_ref().unwrap().upstream, "api");
+        assert_eq!(
+            init_interceptors(&report),
+            ["response_rewrite", "request_id"]
+        );
+
+        let report = explain(gateway_state, &explain_request("http://example.com/x"))
+            .await
+            .unwrap();
+        assert_eq!(report.downstream.as_deref(), Some("example.com"));
+        assert_eq!(report.router.as_ref().unwrap().upstream, "web");
+        assert_eq!(init_interceptors(&report), ["response_rewrite"]);
+    }
+
+    #[test]
+    fn test_build_raw_request() {
+        let request = ExplainRequest {
+            gateway: None,
+            method: "POST".to_string(),
+            url: "http://example.com:8080/payment/x?id=1".to_string(),
+            headers: vec!["x-user: 42".to_string()],
+            body: Some("{}".to_string()),
+        };
+        let raw_request = String::from_utf8(build_raw_request(&request).unwrap()).unwrap();
+        assert_eq!(
+            raw_request,
+            "POST /payment/x?id=1 HTTP/1.1\r\nx-user: 42\r\nhost: example.com:8080\r\ncontent-length: 2\r\n\r\n{}"
+        );
+
+        let request = ExplainRequest {
+            headers: vec!["Host: api.example.com".to_string()],
+            body: None,
+            ..request
+        };
+        let raw_request = String::from_utf8(build_raw_request(&request).unwrap()).unwrap();
+        assert_eq!(
+            raw_request,
+            "POST /payment/x?id=1 HTTP/1.1\r\nHost: api.example.com\r\n\r\n"
+        );
+    }
+}
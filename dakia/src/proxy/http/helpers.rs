@@ -1,11 +1,23 @@
-use pingora::lb::Backend;
+use pingora::{lb::Backend, prelude::HttpPeer};
 
 use crate::{
-    config::{source_config::GatewayConfig, InetAddress},
-    error::{DakiaError, DakiaResult},
+    config::{
+        source_config::{GatewayConfig, RouterConfig, UpstreamConfig, UpstreamNodeConfig},
+        InetAddress,
+    },
+    error::{DakiaError, DakiaResult, ErrorType},
+    gateway::{
+        filter::capture_path_params,
+        lb::{affinity, node_state, LB},
+        path_rewrite,
+        state::GatewayState,
+        traffic_split,
+    },
     shared::pattern_registry::PatternRegistryType,
 };
 
+use super::{ctx::UpstreamPeerSelection, DakiaHttpGatewayCtx, Session};
+
 fn get_ds_addrs(gateway_config: &GatewayConfig) -> Vec<String> {
     // safe to unwrap
     gateway_config
@@ -15,14 +27,15 @@ fn get_ds_addrs(gateway_config: &GatewayConfig) -> Vec<String> {
         .collect()
 }
 
-pub async fn is_valid_ds_host(
+// returns index of downstream whose host matches with host of request
+pub async fn find_ds_index(
     dakia_config: &GatewayConfig,
     ds_host_pattern_registry: &PatternRegistryType,
     ds_host: &[u8],
-) -> DakiaResult<bool> {
+) -> DakiaResult<Option<usize>> {
     let ds_addrs = get_ds_addrs(dakia_config);
 
-    for ds_addr in ds_addrs {
+    for (ds_index, ds_addr) in ds_addrs.into_iter().enumerate() {
         let pattern = ds_host_pattern_registry
             .get(&ds_addr)
             .await?
@@ -43,11 +56,58 @@ pub async fn is_valid_ds_host(
         })?;
 
         if is_matched {
-            return Ok(true);
+            return Ok(Some(ds_index));
         }
     }
 
-    Ok(false)
+    Ok(None)
+}
+
+// discovered backends carry their node config, static backends are looked up in gateway config
+pub fn build_peer(backend: &Backend, upstream_config: &UpstreamConfig) -> DakiaResult<HttpPeer> {
+    let upstream_node_config = match backend.ext.get::<UpstreamNodeConfig>() {
+        Some(upstream_node_config) => upstream_node_config,
+        None => {
+            let inet_address = get_inet_addr_from_backend(backend);
+            upstream_config.find_upstream_node_config_or_err(inet_address)?
+        }
+    };
+
+    let tls = upstream_node_config.tls;
+    let sni = upstream_node_config.clone().sni.unwrap_or("".to_string());
+
+    Ok(HttpPeer::new(backend.addr.clone(), tls, sni))
+}
+
+// pinned node must be part of upstream, it's used even if load balancer would skip it
+pub fn find_pinned_backend(lb: &LB, node: &InetAddress) -> DakiaResult<Backend> {
+    let node_addr = node.get_formatted_address();
+    lb.backends()
+        .get_backend()
+        .iter()
+        .find(|backend| backend.addr.to_string() == node_addr)
+        .cloned()
+        .ok_or(DakiaError::i_explain(format!(
+            "pinned node {node_addr} not found in upstream"
+        )))
+}
+
+pub fn apply_peer_selection(peer: &mut HttpPeer, us_peer_selection: &UpstreamPeerSelection) {
+    if let Some(sni) = &us_peer_selection.sni {
+        peer.sni = sni.clone();
+    }
+
+    if us_peer_selection.connection_timeout.is_some() {
+        peer.options.connection_timeout = us_peer_selection.connection_timeout;
+    }
+
+    if us_peer_selection.read_timeout.is_some() {
+        peer.options.read_timeout = us_peer_selection.read_timeout;
+    }
+
+    if us_peer_selection.write_timeout.is_some() {
+        peer.options.write_timeout = us_peer_selection.write_timeout;
+    }
 }
 
 pub fn get_inet_addr_from_backend(backend: &Backend) -> InetAddress {
@@ -60,3 +120,219 @@ pub fn get_inet_addr_from_backend(backend: &Backend) -> InetAddress {
         port: parts[1].parse().unwrap(),
     }
 }
+
+// returns the upstream which should receive the request considering circuit breaker state of the upstream
+pub fn acquire_upstream(gateway_state: &GatewayState, upstream_name: &str) -> DakiaResult<String> {
+    let circuit_breaker = match gateway_state.circuit_breaker(upstream_name) {
+        Some(circuit_breaker) => circuit_breaker,
+        None => return Ok(upstream_name.to_string()),
+    };
+
+    if circuit_breaker.allow_request() {
+        return Ok(upstream_name.to_string());
+    }
+
+    if let Some(fallback_upstream) = circuit_breaker.fallback_upstream() {
+        // circuit of fallback upstream is respected, but fallback of fallback upstream is not followed
+        let is_allowed = gateway_state
+            .circuit_breaker(fallback_upstream)
+            .is_none_or(|circuit_breaker| circuit_breaker.allow_request());
+
+        if is_allowed {
+            return Ok(fallback_upstream.clone());
+        }
+    }
+
+    Err(DakiaError::explain(
+        ErrorType::ProxyError(503),
+        format!("circuit is open for upstream {upstream_name}"),
+    ))
+}
+
+pub fn track_upstream_node(ctx: &mut DakiaHttpGatewayCtx, backend: &Backend) {
+    let upstream_name = match &ctx.upstream_name {
+        Some(upstream_name) => upstream_name,
+        None => return,
+    };
+
+    let node = backend.addr.to_string();
+    node_state::start_request(
+        &ctx.gateway_state.gateway_config().name,
+        upstream_name,
+        &node,
+    );
+    ctx.upstream_node = Some(node);
+}
+
+pub fn report_upstream_outcome(ctx: &mut DakiaHttpGatewayCtx, is_upstream_error: bool) {
+    let upstream_name = match ctx.upstream_name.take() {
+        Some(upstream_name) => upstream_name,
+        None => return,
+    };
+
+    let node = ctx.upstream_node.take();
+    if let Some(node) = &node {
+        node_state::finish_request(
+            &ctx.gateway_state.gateway_config().name,
+            &upstream_name,
+            node,
+        );
+    }
+
+    if let Some(circuit_breaker) = ctx.gateway_state.circuit_breaker(&upstream_name) {
+        // request failed before a node was selected, so it says nothing about health of upstream
+        if node.is_none() {
+            circuit_breaker.release();
+            return;
+        }
+
+        let is_failure =
+            is_upstream_error || circuit_breaker.is_failure(ctx.us_res_status, ctx.us_res_latency);
+        circuit_breaker.record(!is_failure);
+    }
+}
+
+pub fn select_any_backend(
+    lb: &LB,
+    gateway_name: &str,
+    upstream_config: &UpstreamConfig,
+) -> DakiaResult<Backend> {
+    node_state::select(lb, gateway_name, upstream_config).ok_or(DakiaError::i_explain(format!(
+        "no healthy backend available for upstream {}",
+        upstream_config.name
+    )))
+}
+
+// selects backend honoring affinity of upstream, affinity header takes precedence over affinity cookie
+// if backend bound to affinity key is not healthy, backend is selected as usual
+pub fn select_backend(
+    session: &mut Session,
+    lb: &LB,
+    upstream_config: &UpstreamConfig,
+) -> DakiaResult<Backend> {
+    let gateway_name = session.ctx().gateway_state.gateway_config().name.clone();
+    let affinity_config = match &upstream_config.affinity {
+        Some(affinity_config) => affinity_config,
+        None => return select_any_backend(lb, &gateway_name, upstream_config),
+    };
+
+    if let Some(header_name) = &affinity_config.header {
+        if let Some(affinity_key) = session.ds_req_header(header_name)? {
+            if let Some(backend) = affinity::select_backend_by_key(lb, affinity_key) {
+                return Ok(backend);
+            }
+        }
+    }
+
+    let cookie_config = match &affinity_config.cookie {
+        Some(cookie_config) => cookie_config,
+        None => return select_any_backend(lb, &gateway_name, upstream_config),
+    };
+
+    let affinity_id = session
+        .ds_req_header("cookie")?
+        .and_then(|cookie_header| affinity::find_cookie(cookie_header, &cookie_config.name))
+        .map(|affinity_id| affinity_id.to_string());
+
+    if let Some(backend) =
+        affinity_id.and_then(|affinity_id| affinity::find_backend(lb, cookie_config, &affinity_id))
+    {
+        return Ok(backend);
+    }
+
+    let backend = select_any_backend(lb, &gateway_name, upstream_config)?;
+    session.add_ds_res_cookie(affinity::build_set_cookie(
+        cookie_config,
+        &affinity::backend_affinity_id(cookie_config, &backend),
+    ));
+
+    Ok(backend)
+}
+
+// resolves upstream of router, upstream of traffic split is chosen by affinity key if present otherwise as per weight
+pub fn resolve_router_upstream(
+    session: &mut Session,
+    router_config: &RouterConfig,
+) -> DakiaResult<String> {
+    let upstreams = match (&router_config.upstream, &router_config.upstreams) {
+        (Some(upstream), None) => return Ok(upstream.clone()),
+        (None, Some(upstreams)) => upstreams,
+        _ => {
+            return Err(DakiaError::i_explain(
+                "exactly one of upstream or upstreams is required in router config",
+            ))
+        }
+    };
+
+    let no_upstream_err =
+        || DakiaError::i_explain("all upstreams of traffic split have zero weight");
+    let affinity_config = match &router_config.affinity {
+        Some(affinity_config) => affinity_config,
+        None => {
+            return traffic_split::select_upstream(upstreams)
+                .map(|upstream| upstream.to_string())
+                .ok_or_else(no_upstream_err)
+        }
+    };
+
+    if let Some(header_name) = &affinity_config.header {
+        if let Some(affinity_key) = session.ds_req_header(header_name)? {
+            return traffic_split::select_upstream_by_key(upstreams, affinity_key)
+                .map(|upstream| upstream.to_string())
+                .ok_or_else(no_upstream_err);
+        }
+    }
+
+    let cookie_config = match &affinity_config.cookie {
+        Some(cookie_config) => cookie_config,
+        None => {
+            return traffic_split::select_upstream(upstreams)
+                .map(|upstream| upstream.to_string())
+                .ok_or_else(no_upstream_err)
+        }
+    };
+
+    let sticky_upstream = session
+        .ds_req_header("cookie")?
+        .and_then(|cookie_header| affinity::find_cookie(cookie_header, &cookie_config.name))
+        .and_then(|upstream_name| traffic_split::find_upstream(upstreams, upstream_name))
+        .map(|upstream| upstream.to_string());
+
+    if let Some(upstream) = sticky_upstream {
+        return Ok(upstream);
+    }
+
+    let upstream = traffic_split::select_upstream(upstreams)
+        .ok_or_else(no_upstream_err)?
+        .to_string();
+    session.add_ds_res_cookie(affinity::build_set_cookie(cookie_config, &upstream));
+
+    Ok(upstream)
+}
+
+// path of upstream request as per rewrite_path or strip_prefix of router, None keeps downstream path as it is
+pub fn resolve_router_path(
+    session: &Session,
+    router_config: &RouterConfig,
+) -> DakiaResult<Option<String>> {
+    let path = session.ds_req_path();
+
+    if let Some(rewrite_path) = &router_config.rewrite_path {
+        let captures = match &router_config.filter {
+            Some(filter_name) => {
+                let filter = session.ctx().gateway_state.filter_or_err(filter_name)?;
+                capture_path_params(filter, path)?
+            }
+            None => Default::default(),
+        };
+        return Ok(Some(path_rewrite::render_path_template(
+            rewrite_path,
+            &captures,
+        )));
+    }
+
+    Ok(router_config
+        .strip_prefix
+        .as_ref()
+        .and_then(|prefix| path_rewrite::strip_prefix(path, prefix)))
+}
@@ -0,0 +1,148 @@
+use std::{sync::Arc, time::Duration};
+
+use bytes::{Bytes, BytesMut};
+use log::{debug, warn};
+use once_cell::sync::Lazy;
+use pingora::connectors::http::Connector;
+use pingora_http::RequestHeader;
+use rand::Rng;
+
+use crate::{
+    config::source_config::RouterConfig,
+    error::{DakiaError, DakiaResult},
+    gateway::state::GatewayState,
+};
+
+use super::{
+    helpers::{build_peer, select_any_backend},
+    DakiaHttpGatewayCtx,
+};
+
+// mirroring is skipped for requests having bigger body, as whole body is buffered before it's mirrored
+const MAX_MIRROR_BODY_SIZE: usize = 1024 * 1024;
+const MIRROR_TIMEOUT: Duration = Duration::from_secs(10);
+
+static MIRROR_CONNECTOR: Lazy<Connector> = Lazy::new(|| Connector::new(None));
+
+pub struct MirrorRequest {
+    upstream: String,
+    header: Option<RequestHeader>,
+    body: BytesMut,
+}
+
+// decides whether request is mirrored, it's decided once even if upstream peer is selected again on retry
+pub fn sample(ctx: &mut DakiaHttpGatewayCtx, router_config: &RouterConfig) {
+    if ctx.is_mirror_sampled {
+        return;
+    }
+    ctx.is_mirror_sampled = true;
+
+    let mirror_config = match &router_config.mirror {
+        Some(mirror_config) => mirror_config,
+        None => return,
+    };
+
+    let percentage = mirror_config.percentage.unwrap_or(100).min(100);
+    if rand::thread_rng().gen_range(0..100) >= percentage {
+        return;
+    }
+
+    ctx.mirror = Some(MirrorRequest {
+        upstream: mirror_config.upstream.clone(),
+        header: None,
+        body: BytesMut::new(),
+    });
+}
+
+// header is captured after interceptors are executed, so mirror receives the same request as upstream
+pub fn capture_header(ctx: &mut DakiaHttpGatewayCtx, upstream_request: &RequestHeader) {
+    if let Some(mirror) = ctx.mirror.as_mut() {
+        mirror.header = Some(upstream_request.clone());
+    }
+}
+
+pub fn capture_body(ctx: &mut DakiaHttpGatewayCtx, body: &Option<Bytes>, end_of_stream: bool) {
+    let mirror = match ctx.mirror.as_mut() {
+        Some(mirror) => mirror,
+        None => return,
+    };
+
+    if let Some(body) = body {
+        if mirror.body.len() + body.len() > MAX_MIRROR_BODY_SIZE {
+            debug!(
+                "request body is too large to be mirrored to {}",
+                mirror.upstream
+            );
+            ctx.mirror = None;
+            return;
+        }
+        mirror.body.extend_from_slice(body);
+    }
+
+    if !end_of_stream {
+        return;
+    }
+
+    if let Some(mirror) = ctx.mirror.take() {
+        let gateway_state = ctx.gateway_state.clone();
+
+        // mirror request is sent in background, so it never affects response or latency of client
+        tokio::spawn(async move {
+            let upstream = mirror.upstream.clone();
+            if let Err(e) = send(gateway_state, mirror).await {
+                warn!(
+                    "failed to mirror request to upstream {} - {:?}",
+                    upstream, e
+                );
+            }
+        });
+    }
+}
+
+async fn send(gateway_state: Arc<GatewayState>, mirror: MirrorRequest) -> DakiaResult<()> {
+    let header = mirror.header.ok_or(DakiaError::i_explain(
+        "request header was not captured for mirroring",
+    ))?;
+
+    let gateway_config = gateway_state.gateway_config();
+    let upstream_config = gateway_config
+        .find_upstream_config(&mirror.upstream, false)
+        .ok_or(DakiaError::i_explain(format!(
+            "mirror upstream {} not found",
+            mirror.upstream
+        )))?;
+
+    let lb = gateway_state
+        .lb_registry()
+        .get(&mirror.upstream)
+        .await?
+        .ok_or(DakiaError::i_explain(format!(
+            "load balacer not found for mirror upstream {}",
+            mirror.upstream
+        )))?;
+
+    let backend = select_any_backend(&lb, &gateway_config.name, upstream_config)?;
+    let mut peer = build_peer(&backend, upstream_config)?;
+    peer.options.connection_timeout = Some(MIRROR_TIMEOUT);
+    peer.options.read_timeout = Some(MIRROR_TIMEOUT);
+    peer.options.write_timeout = Some(MIRROR_TIMEOUT);
+
+    let (mut http_session, _) = MIRROR_CONNECTOR.get_http_session(&peer).await?;
+    http_session.write_request_header(Box::new(header)).await?;
+    if !mirror.body.is_empty() {
+        http_session
+            .write_request_body(mirror.body.freeze(), true)
+            .await?;
+    }
+    http_session.finish_request_body().await?;
+
+    // response is discarded, it's read completely so that connection can be reused
+    http_session.read_response_header().await?;
+    while http_session.read_response_body().await?.is_some() {}
+
+    MIRROR_CONNECTOR
+        .release_http_session(http_session, &peer, None)
+        .await;
+
+    Ok(())
+}
@@ -1,8 +1,11 @@
 mod ctx;
+mod explain;
 mod helpers;
+mod mirror;
 mod proxy;
 mod session;
 
 pub use ctx::DakiaHttpGatewayCtx;
-pub use proxy::Proxy;
-pub use session::{HeaderBuffer, Session};
+pub use explain::{explain, ExplainRequest};
+pub use proxy::{Proxy, MAX_DS_REQ_BODY_SIZE};
+pub use session::{HeaderBuffer, QueryParams, Session};
@@ -1,19 +1,26 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Instant};
 
 use crate::{
     config::source_config::find_router_config_or_err,
     error::{DakiaError, DakiaResult},
     gateway::{interceptor::Phase, state::GatewayStateStore},
-    proxy::http::helpers::get_inet_addr_from_backend,
 };
 
 use super::{
-    helpers::is_valid_ds_host,
+    ctx::UpstreamPeerSelection,
+    helpers::{
+        acquire_upstream, apply_peer_selection, build_peer, find_ds_index, find_pinned_backend,
+        report_upstream_outcome, resolve_router_path, resolve_router_upstream, select_backend,
+        track_upstream_node,
+    },
+    mirror,
     session::{self},
     DakiaHttpGatewayCtx,
 };
 use async_trait::async_trait;
+use bytes::Bytes;
 use http::StatusCode;
+use log::debug;
 use pingora::{
     prelude::HttpPeer,
     proxy::{ProxyHttp, Session},
@@ -22,6 +29,9 @@ use pingora::{
 };
 use pingora_http::{RequestHeader, ResponseHeader};
 
+// body of request is buffered upto this size for filters and ext_authz, it's the retry buffer size of pingora
+pub const MAX_DS_REQ_BODY_SIZE: usize = 64 * 1024;
+
 #[derive(Clone)]
 pub struct Proxy {
     gateway_state_store: Arc<GatewayStateStore>,
@@ -50,7 +60,21 @@ impl ProxyHttp for Proxy {
         _session: &mut Session,
         _ctx: &mut Self::CTX,
     ) -> Result<(), Box<Error>> {
+        // downstream is resolved before executing any phase, so that interceptors of downstream are executed in every phase
+        if let Some(host) = _session.req_header().headers.get("host") {
+            _ctx.ds_index = find_ds_index(
+                _ctx.gateway_state.gateway_config(),
+                _ctx.gateway_state.pattern_registry(),
+                host.as_bytes(),
+            )
+            .await?;
+        }
+
         let mut session = session::Session::build(Phase::Init, _session, _ctx);
+        if session.ctx().gateway_state.is_ds_req_body_required() {
+            session.buffer_ds_req_body(MAX_DS_REQ_BODY_SIZE).await?;
+        }
+
         session.execute_interceptors_phase().await?;
         Ok(())
     }
@@ -64,15 +88,8 @@ impl ProxyHttp for Proxy {
         let host = session.ds_req_header("host")?;
 
         match host {
-            Some(host) => {
-                let is_valid_ds_host = is_valid_ds_host(
-                    &session.ctx().gateway_state.gateway_config(),
-                    &self.gateway_state_store.get_state().pattern_registry(),
-                    host,
-                )
-                .await?;
-
-                if !is_valid_ds_host {
+            Some(_) => {
+                if session.ctx().ds_index.is_none() {
                     session.set_res_status(StatusCode::FORBIDDEN);
                     session.flush_ds_res_header().await?;
                     return Ok(true);
@@ -107,10 +124,40 @@ impl ProxyHttp for Proxy {
         _session: &mut Session,
         _ctx: &mut Self::CTX,
     ) -> Result<Box<HttpPeer>, Box<Error>> {
-        let session = session::Session::build(Phase::UpstreamProxyFilter, _session, _ctx);
+        // upstream_peer is called again when a failed connection is retried, so previous attempt is reported as failure
+        report_upstream_outcome(_ctx, true);
+        _ctx.us_peer_selection = UpstreamPeerSelection::default();
+
+        let mut session = session::Session::build(Phase::UpstreamPeerSelection, _session, _ctx);
+
+        let router_config = find_router_config_or_err(&session)?.clone();
+        let router_upstream = resolve_router_upstream(&mut session, &router_config)?;
+        session.set_upstream(router_upstream);
+        let us_req_path = resolve_router_path(&session, &router_config)?;
+
+        if session.execute_interceptors_phase().await? {
+            return Err(Error::explain(
+                HTTPStatus(0),
+                "request is short circuited in upstream_peer_selection phase",
+            ));
+        }
+
+        let router_upstream =
+            session
+                .us_peer_selection()
+                .upstream
+                .clone()
+                .ok_or(DakiaError::i_explain(
+                    "upstream is not selected for request",
+                ))?;
+        let upstream_name = acquire_upstream(&session.ctx().gateway_state, &router_upstream)?;
+        let upstream_name = &upstream_name;
 
-        let router_config = find_router_config_or_err(&session)?;
-        let upstream_name = &router_config.upstream;
+        _ctx.us_req_path = us_req_path;
+        _ctx.upstream_name = Some(upstream_name.clone());
+        _ctx.us_req_started_at = Some(Instant::now());
+        _ctx.us_res_status = None;
+        _ctx.us_res_latency = None;
 
         let gateway_state = self.gateway_state_store.get_state();
         let lb_registry = gateway_state.lb_registry();
@@ -125,21 +172,26 @@ impl ProxyHttp for Proxy {
             "load balacer not found for upstream {upstream_name}"
         )))?;
 
-        let backend = lb.select(b"", 256).unwrap(); // hash doesn't matter
-
-        let inet_address = get_inet_addr_from_backend(&backend);
-
-        let upstream_node_config = gateway_state
+        let upstream_config = gateway_state
             .gateway_config()
-            .find_upstream_config_or_err(upstream_name, true)
-            .map(|a| a.find_upstream_node_config_or_err(inet_address))??;
+            .find_upstream_config_or_err(upstream_name, true)?;
 
-        let tls = upstream_node_config.tls;
-        let sni = upstream_node_config.clone().sni.unwrap_or("".to_string());
+        let backend = match &_ctx.us_peer_selection.node {
+            Some(node) => find_pinned_backend(&lb, node)?,
+            None => {
+                let mut session =
+                    session::Session::build(Phase::UpstreamPeerSelection, _session, _ctx);
+                select_backend(&mut session, &lb, upstream_config)?
+            }
+        };
+        track_upstream_node(_ctx, &backend);
 
-        let peer = Box::new(HttpPeer::new(backend.addr, tls, sni));
+        mirror::sample(_ctx, &router_config);
 
-        Ok(peer)
+        let mut peer = build_peer(&backend, upstream_config)?;
+        apply_peer_selection(&mut peer, &_ctx.us_peer_selection);
+
+        Ok(Box::new(peer))
     }
 
     async fn upstream_request_filter(
@@ -153,9 +205,30 @@ impl ProxyHttp for Proxy {
     {
         let mut session = session::Session::build(Phase::PreUpstreamRequest, _session, _ctx);
         session.upstream_request(_upstream_request);
+
+        // path is rewritten before interceptors, so that interceptors can further modify it
+        if let Some(us_req_path) = session.ctx().us_req_path.clone() {
+            session.set_us_req_path(&us_req_path)?;
+        }
+
         session.execute_interceptors_phase().await?;
         session.flush_us_req_header()?;
 
+        mirror::capture_header(_ctx, _upstream_request);
+        Ok(())
+    }
+
+    async fn request_body_filter(
+        &self,
+        _session: &mut Session,
+        _body: &mut Option<Bytes>,
+        _end_of_stream: bool,
+        _ctx: &mut Self::CTX,
+    ) -> Result<(), Box<Error>>
+    where
+        Self::CTX: Send + Sync,
+    {
+        mirror::capture_body(_ctx, _body, _end_of_stream);
         Ok(())
     }
 
@@ -202,10 +275,35 @@ impl ProxyHttp for Proxy {
     where
         Self::CTX: Send + Sync,
     {
+        _ctx.us_res_status = Some(_upstream_response.status.as_u16());
+        _ctx.us_res_latency = _ctx
+            .us_req_started_at
+            .map(|started_at| started_at.elapsed());
+
         let mut session = session::Session::build(Phase::PostUpstreamResponse, _session, _ctx);
         session.upstream_response(_upstream_response);
         session.execute_interceptors_phase().await?;
         session.flush_ds_res_header().await?;
         Ok(())
     }
+
+    async fn logging(&self, _session: &mut Session, _e: Option<&Error>, _ctx: &mut Self::CTX)
+    where
+        Self::CTX: Send + Sync,
+    {
+        let is_upstream_error = _e.is_some_and(|e| e.esource() == &ErrorSource::Upstream);
+        report_upstream_outcome(_ctx, is_upstream_error);
+
+        if let Some(consumer) = &_ctx.consumer {
+            debug!(
+                "request {} {} of consumer {} completed with status {:?}",
+                _session.req_header().method,
+                _session.req_header().uri,
+                consumer.name,
+                _session
+                    .response_written()
+                    .map(|header| header.status.as_u16())
+            );
+        }
+    }
 }
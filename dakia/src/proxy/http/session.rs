@@ -1,20 +1,31 @@
-use std::{collections::HashMap, mem::take};
+use std::{
+    collections::HashMap,
+    mem::take,
+    net::IpAddr,
+    sync::{Arc, OnceLock, PoisonError},
+    time::Duration,
+};
 
-use bytes::Bytes;
+use bytes::{Bytes, BytesMut};
 use http::{uri::PathAndQuery, StatusCode, Uri};
 use pingora::protocols::l4::socket::SocketAddr;
 use pingora_http::{RequestHeader as PRequestHeader, ResponseHeader as PResponseHeader};
 use pingora_proxy::Session as PSession;
 
 use crate::{
+    config::InetAddress,
     error::{DakiaError, DakiaResult},
-    gateway::interceptor::{
-        executor::{exec_hook, exec_phase},
-        Hook, Phase, PhaseResult,
+    gateway::{
+        consumer::Consumer,
+        interceptor::{
+            executor::{exec_hook, exec_phase},
+            Hook, Phase, PhaseResult,
+        },
     },
+    shared::host_matcher::split_host_port,
 };
 
-use super::DakiaHttpGatewayCtx;
+use super::{ctx::UpstreamPeerSelection, DakiaHttpGatewayCtx};
 
 pub struct Session<'a> {
     psession: &'a mut PSession,
@@ -23,7 +34,6 @@ pub struct Session<'a> {
     phase: Phase,
     ds_status_code: StatusCode,
     ctx: &'a mut DakiaHttpGatewayCtx,
-    ds_header_flushed: bool,
 }
 
 impl<'a> Session<'a> {
@@ -39,7 +49,6 @@ impl<'a> Session<'a> {
             upstream_response: None,
             ds_status_code: StatusCode::OK,
             ctx,
-            ds_header_flushed: false,
         }
     }
 
@@ -56,6 +65,49 @@ impl<'a> Session<'a> {
     pub fn ds_socket_addr(&self) -> Option<&SocketAddr> {
         self.psession.client_addr()
     }
+
+    // ipv4-mapped ipv6 address is converted into ipv4 address
+    pub fn ds_client_ip(&self) -> Option<IpAddr> {
+        match self.ds_socket_addr()? {
+            SocketAddr::Inet(socket_addr) => Some(socket_addr.ip().to_canonical()),
+            SocketAddr::Unix(_) => None,
+        }
+    }
+}
+
+impl<'a> Session<'a> {
+    // host of request without port, http/2 requests carry it in uri instead of host header
+    pub fn ds_req_host(&self) -> Option<&str> {
+        let req_header = self.psession.as_downstream().req_header();
+        let host = match req_header.headers.get("host") {
+            Some(host) => host.to_str().ok()?,
+            None => return req_header.uri.host(),
+        };
+
+        split_host_port(host).ok().map(|(host, _)| host)
+    }
+
+    // port of the listener which accepted the request
+    pub fn ds_req_port(&self) -> Option<u16> {
+        match self.psession.server_addr()? {
+            SocketAddr::Inet(socket_addr) => Some(socket_addr.port()),
+            SocketAddr::Unix(_) => None,
+        }
+    }
+
+    // scheme of the listener which accepted the request
+    pub fn ds_req_scheme(&self) -> &str {
+        let is_tls = self
+            .psession
+            .digest()
+            .is_some_and(|digest| digest.ssl_digest.is_some());
+
+        if is_tls {
+            "https"
+        } else {
+            "http"
+        }
+    }
 }
 
 impl<'a> Session<'a> {
@@ -86,11 +138,79 @@ impl<'a> Session<'a> {
     }
 }
 
+impl<'a> Session<'a> {
+    // replaces path of upstream request, query of downstream request is kept
+    pub fn set_us_req_path(&mut self, path: &str) -> DakiaResult<()> {
+        let path_and_query = match self.ds_req_query()? {
+            Some(query) => format!("{path}?{query}"),
+            None => path.to_string(),
+        };
+
+        let uri = Uri::try_from(path_and_query.as_str()).map_err(|e| {
+            DakiaError::i_explain(format!("invalid upstream request path {path} - {e}"))
+        })?;
+        self.set_us_req_uri(uri)
+    }
+}
+
 impl<'a> Session<'a> {
     pub fn ds_req_query(&self) -> DakiaResult<Option<&str>> {
         Ok(self.psession.as_downstream().req_header().uri.query())
     }
 
+    // values of repeated keys are kept in the order they appear in query string
+    pub fn ds_req_query_params(&self) -> &QueryParams {
+        self.ctx.ds_req_query_params.get_or_init(|| {
+            let query = self
+                .psession
+                .as_downstream()
+                .req_header()
+                .uri
+                .query()
+                .unwrap_or_default();
+
+            let mut query_params = QueryParams::new();
+            for (key, value) in form_urlencoded::parse(query.as_bytes()) {
+                query_params
+                    .entry(key.into_owned())
+                    .or_default()
+                    .push(value.into_owned());
+            }
+            query_params
+        })
+    }
+
+    // first value is used if a cookie is repeated
+    pub fn ds_req_cookie(&self, cookie_name: &str) -> Option<&str> {
+        let cookies = self.ctx.ds_req_cookies.get_or_init(|| {
+            let mut cookies = HashMap::new();
+            let cookie_headers = self
+                .psession
+                .as_downstream()
+                .req_header()
+                .headers
+                .get_all("cookie");
+
+            for cookie_header in cookie_headers {
+                let cookie_header = match cookie_header.to_str() {
+                    Ok(cookie_header) => cookie_header,
+                    Err(_) => continue,
+                };
+
+                for cookie in cookie_header.split(';') {
+                    if let Some((name, value)) = cookie.trim().split_once('=') {
+                        cookies
+                            .entry(name.to_string())
+                            .or_insert_with(|| value.to_string());
+                    }
+                }
+            }
+            cookies
+        });
+
+        cookies.get(cookie_name).map(String::as_str)
+    }
+
     pub fn us_req_query(&self) -> DakiaResult<Option<&str>> {
         Ok(self.upstream_request.as_ref().unwrap().uri.query())
     }
@@ -123,6 +243,10 @@ impl<'a> Session<'a> {
         }
     }
 
+    pub fn ds_req_headers(&self) -> &http::HeaderMap {
+        &self.psession.as_downstream().req_header().headers
+    }
+
     pub fn ds_req_header(&self, header_name: &str) -> DakiaResult<Option<&[u8]>> {
         let header_value = self
             .psession
@@ -145,12 +269,32 @@ impl<'a> Session<'a> {
             .insert(header_name, header_value);
     }
 
+    // header set by client is removed as well, it must be called in PreUpstreamRequest phase
+    pub fn remove_us_req_header(&mut self, header_name: &str) -> DakiaResult<()> {
+        self.ctx.us_req_header_buffer.remove(header_name);
+        match self.upstream_request.as_mut() {
+            Some(upstream_request) => {
+                upstream_request.remove_header(header_name);
+                Ok(())
+            }
+            None => Err(DakiaError::i_explain(format!(
+                "upstream request headers can be removed only in {} phase",
+                Phase::PreUpstreamRequest
+            ))),
+        }
+    }
+
     pub fn set_ds_res_header(&mut self, header_name: String, header_value: Vec<u8>) {
         self.ctx
             .ds_res_header_buffer
             .insert(header_name, header_value);
     }
 
+    // multiple cookies can be set on a response, unlike other headers which are replaced
+    pub fn add_ds_res_cookie(&mut self, cookie: String) {
+        self.ctx.ds_res_cookies.push(cookie);
+    }
+
     async fn flush_header_to_ds(&mut self) -> DakiaResult<()> {
         let mut header = PResponseHeader::build(self.ds_status_code, None).unwrap();
 
@@ -159,6 +303,11 @@ impl<'a> Session<'a> {
             header.insert_header(header_name, header_value)?;
         }
 
+        let cookies = take(&mut self.ctx.ds_res_cookies);
+        for cookie in cookies.into_iter() {
+            header.append_header("set-cookie", cookie)?;
+        }
+
         self.psession
             .write_response_header(Box::new(header), false)
             .await?;
@@ -180,15 +329,21 @@ impl<'a> Session<'a> {
             upstream_response.insert_header(header_name, header_value)?;
         }
 
+        // appended, so that cookies set by upstream are preserved
+        let cookies = take(&mut self.ctx.ds_res_cookies);
+        for cookie in cookies.into_iter() {
+            upstream_response.append_header("set-cookie", cookie)?;
+        }
+
         Ok(())
     }
 
     pub async fn flush_ds_res_header(&mut self) -> DakiaResult<()> {
-        if self.ds_header_flushed {
+        if self.ctx.is_ds_res_header_flushed {
             return Ok(());
         }
 
-        self.ds_header_flushed = true;
+        self.ctx.is_ds_res_header_flushed = true;
 
         let cur_hook = Hook::PreDownstreamResponseHeaderFlush;
         // TODO: allow to configure keepalive once bug is fixed in pingora itself
@@ -240,7 +395,7 @@ impl<'a> Session<'a> {
         body: Option<Bytes>,
         end_of_stream: bool,
     ) -> DakiaResult<()> {
-        if !self.ds_header_flushed {
+        if !self.ctx.is_ds_res_header_flushed {
             self.flush_ds_res_header().await?;
         }
 
@@ -251,11 +406,138 @@ impl<'a> Session<'a> {
     }
 }
 
+impl<'a> Session<'a> {
+    pub fn us_peer_selection(&self) -> &UpstreamPeerSelection {
+        &self.ctx.us_peer_selection
+    }
+
+    pub fn set_upstream(&mut self, upstream_name: String) {
+        self.ctx.us_peer_selection.upstream = Some(upstream_name);
+    }
+
+    pub fn pin_upstream_node(&mut self, address: InetAddress) {
+        self.ctx.us_peer_selection.node = Some(address);
+    }
+
+    pub fn set_us_peer_sni(&mut self, sni: String) {
+        self.ctx.us_peer_selection.sni = Some(sni);
+    }
+
+    pub fn set_us_peer_timeouts(
+        &mut self,
+        connection_timeout: Option<Duration>,
+        read_timeout: Option<Duration>,
+        write_timeout: Option<Duration>,
+    ) {
+        let us_peer_selection = &mut self.ctx.us_peer_selection;
+        us_peer_selection.connection_timeout = connection_timeout;
+        us_peer_selection.read_timeout = read_timeout;
+        us_peer_selection.write_timeout = write_timeout;
+    }
+}
+
 impl<'a> Session<'a> {
     pub async fn read_ds_req_body(&mut self) -> DakiaResult<Option<Bytes>> {
+        if let Some(body) = &self.ctx.ds_req_body {
+            if !self.ctx.is_ds_req_body_read {
+                self.ctx.is_ds_req_body_read = true;
+                return Ok(Some(body.clone()));
+            }
+        }
+
         let body = self.psession.downstream_session.read_request_body().await?;
         Ok(body)
     }
+
+    // body is buffered only if its length is known and within max size
+    // max size must not exceed retry buffer of pingora, as buffered body is replayed to upstream from it
+    pub async fn buffer_ds_req_body(&mut self, max_size: usize) -> DakiaResult<()> {
+        let content_length = self
+            .ds_req_header("content-length")?
+            .and_then(|content_length| std::str::from_utf8(content_length).ok())
+            .and_then(|content_length| content_length.parse::<usize>().ok());
+
+        match content_length {
+            Some(content_length) if content_length > 0 && content_length <= max_size => {}
+            _ => return Ok(()),
+        }
+
+        self.psession.downstream_session.enable_retry_buffering();
+
+        let mut body = BytesMut::new();
+        while let Some(chunk) = self.read_ds_req_body().await? {
+            body.extend_from_slice(&chunk);
+        }
+
+        self.ctx.ds_req_body = Some(body.freeze());
+        self.ctx.ds_req_body_json = OnceLock::new();
+        self.ctx.is_ds_req_body_read = false;
+        self.reset_filter_results();
+        Ok(())
+    }
+
+    pub fn ds_req_body(&self) -> Option<&Bytes> {
+        self.ctx.ds_req_body.as_ref()
+    }
+
+    // body is parsed when a filter accesses it for the first time
+    pub fn ds_req_body_json(&self) -> Option<&serde_json::Value> {
+        self.ctx
+            .ds_req_body_json
+            .get_or_init(|| {
+                let body = self.ctx.ds_req_body.as_ref()?;
+                serde_json::from_slice(body).ok()
+            })
+            .as_ref()
+    }
+}
+
+impl<'a> Session<'a> {
+    // filters are evaluated again, as they can match claims
+    pub fn set_jwt_claims(&mut self, claims: serde_json::Map<String, serde_json::Value>) {
+        self.ctx.jwt_claims = Some(claims);
+        self.reset_filter_results();
+    }
+
+    // results of named filters are cached for the request, so every setter of a part which filters read
+    // must call this. method, path, query, headers, cookies, client address and listener of downstream
+    // request never change, only buffered body and jwt claims do. consumer and authenticated user are
+    // not read by filters, setters of upstream request and response do not affect them either
+    pub fn reset_filter_results(&mut self) {
+        self.ctx
+            .filter_results
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clear();
+    }
+
+    pub fn jwt_claim(&self, claim_name: &str) -> Option<&serde_json::Value> {
+        self.ctx.jwt_claims.as_ref()?.get(claim_name)
+    }
+
+    pub fn set_consumer(&mut self, consumer: Arc<Consumer>) {
+        self.ctx.consumer = Some(consumer);
+    }
+
+    pub fn consumer(&self) -> Option<&Arc<Consumer>> {
+        self.ctx.consumer.as_ref()
+    }
+
+    pub fn set_authenticated_user(&mut self, username: String) {
+        self.ctx.authenticated_user = Some(username);
+    }
+
+    pub fn authenticated_user(&self) -> Option<&str> {
+        self.ctx.authenticated_user.as_deref()
+    }
+
+    pub fn set_authz_us_req_headers(&mut self, headers: Vec<(String, Option<Vec<u8>>)>) {
+        self.ctx.authz_us_req_headers = headers;
+    }
+
+    pub fn take_authz_us_req_headers(&mut self) -> Vec<(String, Option<Vec<u8>>)> {
+        take(&mut self.ctx.authz_us_req_headers)
+    }
 }
 
 impl<'a> Session<'a> {
@@ -281,3 +563,4 @@ impl<'a> Session<'a> {
 }
 
 pub type HeaderBuffer = HashMap<String, Vec<u8>>;
+pub type QueryParams = HashMap<String, Vec<String>>;
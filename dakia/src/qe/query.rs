@@ -24,6 +24,14 @@ pub enum Operator {
     NotEndsWith,  // text not ends with
     Exists,       // value exists
     Matches,      // value matches specified regex
+    Gt,           // number greater than
+    Gte,          // number greater than or equal to
+    Lt,           // number less than
+    Lte,          // number less than or equal to
+    LenGt,        // text length greater than
+    LenLt,        // text length less than
+    Ieq,          // equal to, ignoring case
+    Icontains,    // substring present, ignoring case
 }
 
 impl TryFrom<&str> for Operator {
@@ -45,6 +53,14 @@ impl TryFrom<&str> for Operator {
             "$not_starts_with" => Ok(Self::NotStartWith),
             "$ends_with" => Ok(Self::EndsWith),
             "$not_ends_with" => Ok(Self::NotEndsWith),
+            "$gt" => Ok(Self::Gt),
+            "$gte" => Ok(Self::Gte),
+            "$lt" => Ok(Self::Lt),
+            "$lte" => Ok(Self::Lte),
+            "$len_gt" => Ok(Self::LenGt),
+            "$len_lt" => Ok(Self::LenLt),
+            "$ieq" => Ok(Self::Ieq),
+            "$icontains" => Ok(Self::Icontains),
             _ => return Err(*DakiaError::create_unknown_msg("Invalid operator!")),
         }
     }
@@ -172,6 +188,26 @@ pub fn extract_bool_or_err(val: &Value) -> DakiaResult<bool> {
     }
 }
 
+// numbers can be written as string as well, like "1.5", as query doesn't have float value
+pub fn extract_f64_or_err(val: &Value) -> DakiaResult<f64> {
+    let err = || DakiaError::i_explain(format!("Expected a number, found {:?}", val));
+    match val {
+        Value::Scaler(Scaler::I64(intval)) => Ok(*intval as f64),
+        Value::Scaler(Scaler::String(strval)) => strval.trim().parse::<f64>().map_err(|_| err()),
+        _ => Err(err()),
+    }
+}
+
+pub fn extract_usize_or_err(val: &Value) -> DakiaResult<usize> {
+    match val {
+        Value::Scaler(Scaler::I64(intval)) if *intval >= 0 => Ok(*intval as usize),
+        _ => Err(DakiaError::i_explain(format!(
+            "Expected a non-negative integer, found {:?}",
+            val
+        ))),
+    }
+}
+
 pub fn extract_key_i64_or_err(query: &Query, key: &str) -> DakiaResult<i64> {
     match query.get(key) {
         Some(val) => match val {
@@ -233,6 +269,18 @@ mod tests {
             Operator::NotEndsWith
         );
 
+        assert_eq!(Operator::try_from("$gt").unwrap(), Operator::Gt);
+        assert_eq!(Operator::try_from("$gte").unwrap(), Operator::Gte);
+        assert_eq!(Operator::try_from("$lt").unwrap(), Operator::Lt);
+        assert_eq!(Operator::try_from("$lte").unwrap(), Operator::Lte);
+        assert_eq!(Operator::try_from("$len_gt").unwrap(), Operator::LenGt);
+        assert_eq!(Operator::try_from("$len_lt").unwrap(), Operator::LenLt);
+        assert_eq!(Operator::try_from("$ieq").unwrap(), Operator::Ieq);
+        assert_eq!(
+            Operator::try_from("$icontains").unwrap(),
+            Operator::Icontains
+        );
+
         assert!(Operator::try_from("$invalid").is_err());
     }
 
@@ -0,0 +1,156 @@
+use std::net::IpAddr;
+
+use ipnet::IpNet;
+
+use crate::error::{DakiaError, DakiaResult};
+
+#[derive(Debug, Clone, Default)]
+struct PrefixNode {
+    children: [Option<usize>; 2],
+    // a network ends at this node, every address below it is part of the set
+    is_terminal: bool,
+}
+
+// binary trie of network prefixes, lookup takes at most one step per bit of address
+#[derive(Debug, Clone)]
+struct PrefixTrie {
+    nodes: Vec<PrefixNode>,
+}
+
+impl PrefixTrie {
+    fn new() -> Self {
+        Self {
+            nodes: vec![PrefixNode::default()],
+        }
+    }
+
+    fn insert(&mut self, bits: u128, prefix_len: u8, max_prefix_len: u8) {
+        let mut node_index = 0;
+        for depth in 0..prefix_len {
+            if self.nodes[node_index].is_terminal {
+                // a shorter network already covers this one
+                return;
+            }
+
+            let bit = ((bits >> (max_prefix_len - 1 - depth)) & 1) as usize;
+            node_index = match self.nodes[node_index].children[bit] {
+                Some(child_index) => child_index,
+                None => {
+                    self.nodes.push(PrefixNode::default());
+                    let child_index = self.nodes.len() - 1;
+                    self.nodes[node_index].children[bit] = Some(child_index);
+                    child_index
+                }
+            };
+        }
+
+        self.nodes[node_index].is_terminal = true;
+    }
+
+    fn contains(&self, bits: u128, max_prefix_len: u8) -> bool {
+        let mut node_index = 0;
+        for depth in 0..max_prefix_len {
+            if self.nodes[node_index].is_terminal {
+                return true;
+            }
+
+            let bit = ((bits >> (max_prefix_len - 1 - depth)) & 1) as usize;
+            node_index = match self.nodes[node_index].children[bit] {
+                Some(child_index) => child_index,
+                None => return false,
+            };
+        }
+
+        self.nodes[node_index].is_terminal
+    }
+}
+
+// set of ipv4 and ipv6 networks, ipv4-mapped ipv6 addresses are matched against ipv4 networks
+#[derive(Debug, Clone)]
+pub struct CidrSet {
+    v4: PrefixTrie,
+    v6: PrefixTrie,
+}
+
+impl CidrSet {
+    // a plain ip address is considered as a network of single address
+    pub fn build(cidrs: &[String]) -> DakiaResult<Self> {
+        let mut cidr_set = Self {
+            v4: PrefixTrie::new(),
+            v6: PrefixTrie::new(),
+        };
+
+        for cidr in cidrs {
+            let network = match cidr.parse::<IpNet>() {
+                Ok(network) => network,
+                Err(_) => cidr
+                    .parse::<IpAddr>()
+                    .map(IpNet::from)
+                    .map_err(|_| DakiaError::i_explain(format!("invalid cidr {cidr}")))?,
+            };
+
+            match network.trunc() {
+                IpNet::V4(network) => cidr_set.v4.insert(
+                    u32::from(network.network()) as u128,
+                    network.prefix_len(),
+                    32,
+                ),
+                IpNet::V6(network) => {
+                    cidr_set
+                        .v6
+                        .insert(u128::from(network.network()), network.prefix_len(), 128)
+                }
+            }
+        }
+
+        Ok(cidr_set)
+    }
+
+    pub fn contains(&self, ip: &IpAddr) -> bool {
+        match ip.to_canonical() {
+            IpAddr::V4(ip) => self.v4.contains(u32::from(ip) as u128, 32),
+            IpAddr::V6(ip) => self.v6.contains(u128::from(ip), 128),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cidr_set(cidrs: &[&str]) -> CidrSet {
+        let cidrs: Vec<String> = cidrs.iter().map(|cidr| cidr.to_string()).collect();
+        CidrSet::build(&cidrs).unwrap()
+    }
+
+    fn contains(cidr_set: &CidrSet, ip: &str) -> bool {
+        cidr_set.contains(&ip.parse().unwrap())
+    }
+
+    #[test]
+    fn test_cidr_set() {
+        let cidr_set = cidr_set(&["10.0.0.0/8", "192.168.1.7", "2001:db8::/32", "0.0.0.0/32"]);
+
+        assert!(contains(&cidr_set, "10.1.2.3"));
+        assert!(contains(&cidr_set, "192.168.1.7"));
+        assert!(contains(&cidr_set, "::ffff:10.0.0.1"));
+        assert!(contains(&cidr_set, "2001:db8:1::1"));
+        assert!(contains(&cidr_set, "0.0.0.0"));
+        assert!(!contains(&cidr_set, "11.0.0.1"));
+        assert!(!contains(&cidr_set, "192.168.1.8"));
+        assert!(!contains(&cidr_set, "2001:db9::1"));
+    }
+
+    #[test]
+    fn test_match_all() {
+        let cidr_set = cidr_set(&["0.0.0.0/0", "::/0"]);
+        assert!(contains(&cidr_set, "1.2.3.4"));
+        assert!(contains(&cidr_set, "::1"));
+    }
+
+    #[test]
+    fn test_invalid_cidr() {
+        assert!(CidrSet::build(&["10.0.0.0/33".to_string()]).is_err());
+        assert!(CidrSet::build(&["example.com".to_string()]).is_err());
+    }
+}
@@ -0,0 +1,30 @@
+use sha2::{Digest, Sha256};
+
+mod password;
+mod sealed;
+
+pub use password::PasswordHash;
+pub use sealed::{open, seal};
+
+// unlike DefaultHasher, value stays same across builds and processes, parts are length prefixed to keep them apart
+pub fn stable_hash(parts: &[&[u8]]) -> u64 {
+    let mut hasher = Sha256::new();
+    for part in parts {
+        hasher.update((part.len() as u64).to_be_bytes());
+        hasher.update(part);
+    }
+    // safe to unwrap, digest has 32 bytes
+    u64::from_be_bytes(hasher.finalize()[..8].try_into().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stable_hash() {
+        // value must never change, sticky clients would be moved otherwise
+        assert_eq!(stable_hash(&[b"dakia"]), 2013260400161295148);
+        assert_ne!(stable_hash(&[b"ab", b"c"]), stable_hash(&[b"a", b"bc"]));
+    }
+}
@@ -0,0 +1,147 @@
+use argon2::{Argon2, Params, PasswordVerifier, Version};
+use base64::{engine::general_purpose, Engine};
+use pwhash::{sha256_crypt, sha512_crypt};
+use sha1::{Digest, Sha1};
+use subtle::ConstantTimeEq;
+
+use crate::error::{DakiaError, DakiaResult};
+
+// bcrypt below this cost is rejected by bcrypt crate only at verification
+const MIN_BCRYPT_COST: u32 = 4;
+
+// password hash in one of formats supported by htpasswd files
+pub enum PasswordHash {
+    Bcrypt(String),
+    Argon2(String),
+    Sha256Crypt(String),
+    Sha512Crypt(String),
+    // {SHA} prefixed base64 of sha1 digest, kept for old htpasswd files
+    Sha1([u8; 20]),
+}
+
+fn parse_bcrypt(hash: &str) -> DakiaResult<PasswordHash> {
+    let hash_parts: bcrypt::HashParts = hash
+        .parse()
+        .map_err(|e| DakiaError::i_explain(format!("invalid bcrypt hash - {e}")))?;
+    if hash_parts.get_cost() < MIN_BCRYPT_COST {
+        return Err(DakiaError::i_explain(format!(
+            "bcrypt cost must be at least {MIN_BCRYPT_COST}"
+        )));
+    }
+    Ok(PasswordHash::Bcrypt(hash.to_string()))
+}
+
+// hashes without version are of version 1.0, argon2 crate assumes 1.3 for them
+fn parse_argon2(hash: &str) -> DakiaResult<PasswordHash> {
+    let mut parts: Vec<&str> = hash.split('$').collect();
+    if parts.get(2).is_some_and(|part| !part.starts_with("v=")) {
+        parts.insert(2, "v=16");
+    }
+    let hash = parts.join("$");
+
+    let invalid_hash =
+        |e: &dyn std::fmt::Display| DakiaError::i_explain(format!("invalid argon2 hash - {e}"));
+    let password_hash = argon2::PasswordHash::new(&hash).map_err(|e| invalid_hash(&e))?;
+    Params::try_from(&password_hash).map_err(|e| invalid_hash(&e))?;
+    if let Some(version) = password_hash.version {
+        Version::try_from(version).map_err(|e| invalid_hash(&e))?;
+    }
+    Ok(PasswordHash::Argon2(hash))
+}
+
+impl PasswordHash {
+    pub fn parse(hash: &str) -> DakiaResult<Self> {
+        if let Some(digest) = hash.strip_prefix("{SHA}") {
+            let digest = general_purpose::STANDARD
+                .decode(digest)
+                .ok()
+                .and_then(|digest| digest.try_into().ok())
+                .ok_or(DakiaError::i_explain("invalid {SHA} hash".to_string()))?;
+            return Ok(PasswordHash::Sha1(digest));
+        }
+
+        // $2x$ hashes are generated by buggy implementation, they are not supported
+        if ["$2a$", "$2b$", "$2y$"]
+            .iter()
+            .any(|prefix| hash.starts_with(prefix))
+        {
+            parse_bcrypt(hash)
+        } else if hash.starts_with("$argon2") {
+            parse_argon2(hash)
+        } else if hash.starts_with("$5$") {
+            Ok(PasswordHash::Sha256Crypt(hash.to_string()))
+        } else if hash.starts_with("$6$") {
+            Ok(PasswordHash::Sha512Crypt(hash.to_string()))
+        } else {
+            Err(DakiaError::i_explain(
+                "unsupported password hash, supported hashes are bcrypt, argon2, sha256-crypt, sha512-crypt and {SHA}"
+                    .to_string(),
+            ))
+        }
+    }
+
+    // verification is slow by design for all hashes except {SHA}
+    pub fn verify(&self, password: &[u8]) -> bool {
+        match self {
+            PasswordHash::Bcrypt(hash) => bcrypt::verify(password, hash).unwrap_or(false),
+            PasswordHash::Argon2(hash) => argon2::PasswordHash::new(hash)
+                .is_ok_and(|hash| Argon2::default().verify_password(password, &hash).is_ok()),
+            PasswordHash::Sha256Crypt(hash) => sha256_crypt::verify(password, hash),
+            PasswordHash::Sha512Crypt(hash) => sha512_crypt::verify(password, hash),
+            PasswordHash::Sha1(digest) => Sha1::digest(password).ct_eq(digest).into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_verified(hash: &str, password: &str) {
+        let password_hash = PasswordHash::parse(hash).unwrap();
+        assert!(password_hash.verify(password.as_bytes()), "{hash}");
+        assert!(!password_hash.verify(b"Password!"), "{hash}");
+    }
+
+    #[test]
+    fn test_password_hash() {
+        for hash in [
+            "{SHA}W6ph5Mm5Pz8GgiULbPgzG37mj9g=",
+            "$2y$04$Eg6ymrprWLh4oWAYzYQrOefheDzEk0dd22j1gQQ9H0SjsvJZBdJJe",
+            "$2b$04$Eg6ymrprWLh4oWAYzYQrOefheDzEk0dd22j1gQQ9H0SjsvJZBdJJe",
+            "$5$tcu3uCDlyqobrJOS$f5mEbUu/2HYYW76qwSrrJ0w5GDt1S6FkIUTJjif6EB1",
+            "$6$OMjuF6j9EclsPhex$fSwB9DE8UAn72d54C0za6aM4cL0Z8kw0YE0dJTiK2dwQF79cV4.sYfI4.eSaj/akcDFp9M7Fp7zA0PYdBTIpQ/",
+            "$argon2id$v=19$m=64,t=2,p=2$c29tZXNhbHR2YWx1ZQ$/2e+9/bHgFYxRHnrjNIo+s9TIndSyZPM09whBJwrnM4",
+            "$argon2id$v=16$m=64,t=2,p=2$c29tZXNhbHR2YWx1ZQ$zZohE9LFqKeuzNnKygCraO5/4k8FcgKFOIvi28PqEW4",
+            "$argon2i$v=19$m=64,t=2,p=2$c29tZXNhbHR2YWx1ZQ$9ziIyf67NOHh7oGUkcSfIJi+2Wm0iCwYUifbO7FVkX8",
+            "$argon2i$m=64,t=2,p=2$c29tZXNhbHR2YWx1ZQ$TvqzsKiUeuBWujjnF+y/1s99vm6DsBPudvxNAw8X/sw",
+            "$argon2d$v=16$m=64,t=2,p=2$c29tZXNhbHR2YWx1ZQ$C18iO/qAn+8YubZ605LCzhkoF7xrr9db74ASXV4SgJk",
+            "$argon2id$v=19$m=1100,t=1,p=3$c29tZXNhbHR2YWx1ZQ$pJQlTcs+2HxTq3DOVokV1k0pP8bNWqt29ZmHgBLH5hM",
+        ] {
+            assert_verified(hash, "password");
+        }
+
+        // test vectors of specifications
+        assert_verified(
+            "$2a$05$CCCCCCCCCCCCCCCCCCCCC.E5YPO9kmyuRGyh0XouQYb4YMJKvyOeW",
+            "U*U",
+        );
+        assert_verified(
+            "$5$rounds=10000$saltstringsaltst$3xv.VbSHBb41AL9AvLeujZkZRBAwqFMz2.opqey6IcA",
+            "Hello world!",
+        );
+        assert_verified("$6$rounds=1000$roundstoolow$kUMsbe306n21p9R.FRkW3IGn.S9NPN0x50YhH1xhLsPuWGsUSklZt58jaTfF4ZEQpyUNGc0dqbpBYYBaHHrsX.", "the minimum number is still observed");
+
+        for hash in [
+            // apache md5 is not supported
+            "$apr1$r31.....$HqJZimcKQFAMYayBlzkrA/",
+            "password",
+            "$2b$03$Eg6ymrprWLh4oWAYzYQrOefheDzEk0dd22j1gQQ9H0SjsvJZBdJJe",
+            "$2x$04$Eg6ymrprWLh4oWAYzYQrOefheDzEk0dd22j1gQQ9H0SjsvJZBdJJe",
+            "$argon2id$v=19$m=8,t=2,p=2$c29tZXNhbHR2YWx1ZQ$qVZJMmltaAxK6Xs",
+            "$argon2id$v=18$m=64,t=2,p=2$c29tZXNhbHR2YWx1ZQ$qVZJMmltaAxK6Xs",
+        ] {
+            assert!(PasswordHash::parse(hash).is_err(), "{hash}");
+        }
+    }
+}
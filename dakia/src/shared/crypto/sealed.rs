@@ -0,0 +1,74 @@
+use chacha20poly1305::{aead::Aead, ChaCha20Poly1305, KeyInit, Nonce};
+use hmac::{Hmac, Mac};
+use rand::{rngs::OsRng, RngCore};
+use sha2::Sha256;
+
+use crate::error::{DakiaError, DakiaResult};
+
+const NONCE_SIZE: usize = 12;
+const TAG_SIZE: usize = 16;
+
+// key of cipher is derived from secret, so that secret can be of any length
+fn cipher(secret: &[u8]) -> ChaCha20Poly1305 {
+    // safe to unwrap, hmac accepts key of any length
+    let key = <Hmac<Sha256> as Mac>::new_from_slice(secret)
+        .unwrap()
+        .chain_update(b"dakia sealed encryption")
+        .finalize()
+        .into_bytes();
+    ChaCha20Poly1305::new(&key)
+}
+
+// data is encrypted and authenticated with chacha20-poly1305, output is nonce followed by cipher text and tag
+pub fn seal(secret: &[u8], plain_text: &[u8]) -> Vec<u8> {
+    let mut nonce = [0u8; NONCE_SIZE];
+    OsRng.fill_bytes(&mut nonce);
+
+    // safe to unwrap, encryption fails only for data larger than 256 GiB
+    let cipher_text = cipher(secret)
+        .encrypt(Nonce::from_slice(&nonce), plain_text)
+        .unwrap();
+
+    let mut sealed = nonce.to_vec();
+    sealed.extend_from_slice(&cipher_text);
+    sealed
+}
+
+pub fn open(secret: &[u8], sealed: &[u8]) -> DakiaResult<Vec<u8>> {
+    if sealed.len() < NONCE_SIZE + TAG_SIZE {
+        return Err(DakiaError::i_explain(
+            "sealed data is too short".to_string(),
+        ));
+    }
+
+    let (nonce, cipher_text) = sealed.split_at(NONCE_SIZE);
+    cipher(secret)
+        .decrypt(Nonce::from_slice(nonce), cipher_text)
+        .map_err(|_| {
+            DakiaError::i_explain(
+                "sealed data is tampered or sealed with another secret".to_string(),
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seal_and_open() {
+        let sealed = seal(b"secret", b"dakia");
+        assert_eq!(sealed.len(), NONCE_SIZE + 5 + TAG_SIZE);
+        assert_ne!(&sealed[NONCE_SIZE..NONCE_SIZE + 5], b"dakia");
+        assert_eq!(open(b"secret", &sealed).unwrap(), b"dakia");
+        assert!(open(b"another secret", &sealed).is_err());
+
+        let mut tampered = sealed.clone();
+        tampered[NONCE_SIZE] ^= 1;
+        assert!(open(b"secret", &tampered).is_err());
+        assert!(open(b"secret", &sealed[..NONCE_SIZE + TAG_SIZE - 1]).is_err());
+
+        // nonce is random, so same data is sealed differently
+        assert_ne!(seal(b"secret", b"dakia"), sealed);
+    }
+}
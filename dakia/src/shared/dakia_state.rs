@@ -58,6 +58,15 @@ impl DakiaStateStore {
         }
     }
 
+    pub fn get_gateway_state(&self, gateway_name: &str) -> DakiaResult<Option<Arc<GatewayState>>> {
+        let gateway_state = self
+            .get_gateway_stores()?
+            .iter()
+            .map(|gateway_state_store| gateway_state_store.get_state())
+            .find(|gateway_state| gateway_state.gateway_config().name == gateway_name);
+        Ok(gateway_state)
+    }
+
     pub fn store_gateway_state_stores(
         &self,
         gateway_stores: Vec<Arc<GatewayStateStore>>,
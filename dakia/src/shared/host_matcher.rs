@@ -0,0 +1,143 @@
+use wildmatch::WildMatch;
+
+use crate::error::{BErrorStd, DakiaError, DakiaResult};
+
+use super::pattern_matcher::{PatternMatcher, Pcre2PatternMatcher};
+
+const REGEX_PREFIX: &str = "regex:";
+
+#[derive(Debug)]
+enum HostPattern {
+    Exact(String),
+    Wildcard(WildMatch),
+    Regex(Pcre2PatternMatcher),
+}
+
+// matches host header of request against host of downstream config
+// host can be exact (example.com), wildcard (*.example.com) or regex (regex:^api[0-9]+\.example\.com$)
+#[derive(Debug)]
+pub struct HostPatternMatcher {
+    pattern: HostPattern,
+    port: Option<u16>,
+}
+
+impl HostPatternMatcher {
+    pub fn build(host: &str, port: Option<u16>) -> DakiaResult<Self> {
+        let pattern = match host.strip_prefix(REGEX_PREFIX) {
+            // regex is anchored, so that a partial match of host is not considered as a match
+            Some(regex) => {
+                HostPattern::Regex(Pcre2PatternMatcher::build(&format!("(?i)^(?:{regex})$"))?)
+            }
+            None if host.contains(['*', '?']) => {
+                HostPattern::Wildcard(WildMatch::new_case_insensitive(host))
+            }
+            None => HostPattern::Exact(host.to_ascii_lowercase()),
+        };
+
+        Ok(Self { pattern, port })
+    }
+}
+
+// splits host header into host name and port, ipv6 address is enclosed within brackets like [::1]:8080
+pub fn split_host_port(host_header: &str) -> DakiaResult<(&str, Option<u16>)> {
+    let port_separator = match host_header.rfind(':') {
+        Some(index) if !host_header[index..].contains(']') => index,
+        _ => return Ok((host_header, None)),
+    };
+
+    let port = &host_header[port_separator + 1..];
+    let port = port
+        .parse::<u16>()
+        .map_err(|_| DakiaError::i_explain(format!("invalid port in host {host_header}")))?;
+
+    Ok((&host_header[..port_separator], Some(port)))
+}
+
+impl PatternMatcher for HostPatternMatcher {
+    fn is_match(&self, text: &[u8]) -> Result<bool, BErrorStd> {
+        let host_header = match std::str::from_utf8(text) {
+            Ok(host_header) => host_header.trim(),
+            Err(_) => return Ok(false),
+        };
+
+        let (host, port) = match split_host_port(host_header) {
+            Ok(host_port) => host_port,
+            Err(_) => return Ok(false),
+        };
+
+        // fully qualified host name can have a trailing dot
+        let host = host.strip_suffix('.').unwrap_or(host);
+
+        // request without port is sent to default port of scheme
+        let is_port_matched = match (self.port, port) {
+            (None, _) => true,
+            (Some(expected_port), Some(port)) => expected_port == port,
+            (Some(expected_port), None) => expected_port == 80 || expected_port == 443,
+        };
+        if !is_port_matched {
+            return Ok(false);
+        }
+
+        let is_matched = match &self.pattern {
+            HostPattern::Exact(expected_host) => host.eq_ignore_ascii_case(expected_host),
+            HostPattern::Wildcard(wildcard) => wildcard.matches(host),
+            HostPattern::Regex(regex) => regex.is_match(host.as_bytes())?,
+        };
+
+        Ok(is_matched)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_match(host: &str, port: Option<u16>, host_header: &str) -> bool {
+        HostPatternMatcher::build(host, port)
+            .unwrap()
+            .is_match(host_header.as_bytes())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_exact_host() {
+        assert!(is_match("example.com", None, "example.com"));
+        assert!(is_match("example.com", None, "Example.COM:8080"));
+        assert!(is_match("example.com", None, "example.com."));
+        assert!(!is_match("example.com", None, "exampleXcom"));
+        assert!(!is_match("example.com", None, "evilexample.com.attacker"));
+        assert!(!is_match("example.com", None, "api.example.com"));
+    }
+
+    #[test]
+    fn test_wildcard_host() {
+        assert!(is_match("*.example.com", None, "api.example.com"));
+        assert!(is_match("*.example.com", None, "API.Example.com:443"));
+        assert!(!is_match("*.example.com", None, "example.com"));
+        assert!(!is_match("*.example.com", None, "api.example.com.attacker"));
+    }
+
+    #[test]
+    fn test_regex_host() {
+        assert!(is_match(
+            r"regex:api[0-9]+\.example\.com",
+            None,
+            "API1.example.com"
+        ));
+        assert!(!is_match(
+            r"regex:api[0-9]+\.example\.com",
+            None,
+            "api1.example.com.attacker"
+        ));
+    }
+
+    #[test]
+    fn test_host_port() {
+        assert!(is_match("example.com", Some(8080), "example.com:8080"));
+        assert!(!is_match("example.com", Some(8080), "example.com:9090"));
+        assert!(!is_match("example.com", Some(8080), "example.com"));
+        assert!(is_match("example.com", Some(80), "example.com"));
+        assert!(is_match("[::1]", Some(8080), "[::1]:8080"));
+        assert!(!is_match("example.com", None, "example.com:http"));
+    }
+}
@@ -0,0 +1,127 @@
+use std::time::Duration;
+
+use bytes::{Bytes, BytesMut};
+use http::Uri;
+use once_cell::sync::Lazy;
+use pingora::{connectors::http::Connector, upstreams::peer::HttpPeer};
+use pingora_http::{RequestHeader, ResponseHeader};
+
+use crate::error::{DakiaError, DakiaResult};
+
+// responses are buffered completely, bigger responses are rejected
+const MAX_RESPONSE_BODY_SIZE: usize = 1024 * 1024;
+
+static HTTP_CLIENT_CONNECTOR: Lazy<Connector> = Lazy::new(|| Connector::new(None));
+
+pub struct HttpClientRequest {
+    pub method: String,
+    pub url: String,
+    pub headers: Vec<(String, Vec<u8>)>,
+    pub body: Option<Bytes>,
+    pub timeout: Duration,
+}
+
+impl HttpClientRequest {
+    pub fn get(url: &str, timeout: Duration) -> Self {
+        Self {
+            method: "GET".to_string(),
+            url: url.to_string(),
+            headers: vec![],
+            body: None,
+            timeout,
+        }
+    }
+}
+
+pub struct HttpClientResponse {
+    pub header: ResponseHeader,
+    pub body: Bytes,
+}
+
+async fn build_peer(uri: &Uri, timeout: Duration) -> DakiaResult<HttpPeer> {
+    let tls = match uri.scheme_str() {
+        Some("http") => false,
+        Some("https") => true,
+        _ => {
+            return Err(DakiaError::i_explain(format!(
+                "url {uri} must have http or https scheme"
+            )))
+        }
+    };
+
+    let host = uri
+        .host()
+        .ok_or(DakiaError::i_explain(format!("url {uri} must have a host")))?;
+    let port = uri.port_u16().unwrap_or(if tls { 443 } else { 80 });
+
+    // host is resolved without blocking, as it's done while serving requests
+    let address =
+        tokio::net::lookup_host((host, port))
+            .await?
+            .next()
+            .ok_or(DakiaError::i_explain(format!(
+                "could not resolve host {host}"
+            )))?;
+
+    let mut peer = HttpPeer::new(address, tls, host.to_string());
+    peer.options.connection_timeout = Some(timeout);
+    peer.options.read_timeout = Some(timeout);
+    peer.options.write_timeout = Some(timeout);
+    Ok(peer)
+}
+
+pub async fn send(request: HttpClientRequest) -> DakiaResult<HttpClientResponse> {
+    let uri: Uri = request
+        .url
+        .parse()
+        .map_err(|e| DakiaError::i_explain(format!("invalid url {} - {e}", request.url)))?;
+    let peer = build_peer(&uri, request.timeout).await?;
+
+    let path_and_query = uri.path_and_query().map_or("/", |path| path.as_str());
+    let mut header =
+        RequestHeader::build(request.method.as_str(), path_and_query.as_bytes(), None)?;
+    // safe to unwrap, host is validated while building peer
+    header.insert_header("host", uri.authority().unwrap().as_str())?;
+    if let Some(body) = &request.body {
+        header.insert_header("content-length", body.len().to_string())?;
+    }
+    for (header_name, header_value) in request.headers {
+        header.insert_header(header_name, header_value)?;
+    }
+
+    let (mut http_session, _) = HTTP_CLIENT_CONNECTOR.get_http_session(&peer).await?;
+    http_session.write_request_header(Box::new(header)).await?;
+    if let Some(body) = request.body {
+        http_session.write_request_body(body, true).await?;
+    }
+    http_session.finish_request_body().await?;
+
+    http_session.read_response_header().await?;
+    let header = http_session
+        .response_header()
+        .cloned()
+        .ok_or(DakiaError::i_explain(format!(
+            "response header not received from {}",
+            request.url
+        )))?;
+
+    let mut body = BytesMut::new();
+    while let Some(chunk) = http_session.read_response_body().await? {
+        if body.len() + chunk.len() > MAX_RESPONSE_BODY_SIZE {
+            return Err(DakiaError::i_explain(format!(
+                "response of {} is larger than {MAX_RESPONSE_BODY_SIZE} bytes",
+                request.url
+            )));
+        }
+        body.extend_from_slice(&chunk);
+    }
+
+    HTTP_CLIENT_CONNECTOR
+        .release_http_session(http_session, &peer, None)
+        .await;
+
+    Ok(HttpClientResponse {
+        header,
+        body: body.freeze(),
+    })
+}
@@ -0,0 +1,33 @@
+use once_cell::sync::Lazy;
+use prometheus::{register_int_counter_vec, register_int_gauge_vec, IntCounterVec, IntGaugeVec};
+
+// metrics are registered in default prometheus registry, which is served by prometheus http service of pingora
+
+// 0 - closed, 1 - open, 2 - half open
+pub static CIRCUIT_BREAKER_STATE: Lazy<IntGaugeVec> = Lazy::new(|| {
+    register_int_gauge_vec!(
+        "dakia_circuit_breaker_state",
+        "Current circuit breaker state of an upstream (0 - closed, 1 - open, 2 - half open)",
+        &["gateway", "upstream"]
+    )
+    .unwrap()
+});
+
+pub static CIRCUIT_BREAKER_TRANSITIONS: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "dakia_circuit_breaker_transitions_total",
+        "Number of circuit breaker state transitions of an upstream",
+        &["gateway", "upstream", "from", "to"]
+    )
+    .unwrap()
+});
+
+// in-flight requests of draining node must reach zero before controller removes it from upstream
+pub static UPSTREAM_NODE_IN_FLIGHT_REQUESTS: Lazy<IntGaugeVec> = Lazy::new(|| {
+    register_int_gauge_vec!(
+        "dakia_upstream_node_in_flight_requests",
+        "Number of requests being served by an upstream node",
+        &["gateway", "upstream", "node"]
+    )
+    .unwrap()
+});
@@ -1,7 +1,14 @@
+pub mod cidr;
 pub mod common;
+pub mod crypto;
 pub mod dakia_state;
+pub mod host_matcher;
+pub mod http_client;
 pub mod into;
+pub mod metrics;
 pub mod mutable_registry;
 pub mod pattern_matcher;
 pub mod pattern_registry;
 pub mod registry;
+#[cfg(test)]
+pub mod test_server;
@@ -21,4 +21,12 @@ impl<I> Registry<I> {
     pub fn add(&mut self, key: String, item: I) {
         self.items.insert(key, item);
     }
+
+    pub fn keys(&self) -> impl Iterator<Item = &String> {
+        self.items.keys()
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &I> {
+        self.items.values()
+    }
 }
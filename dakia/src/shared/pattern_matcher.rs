@@ -1,4 +1,4 @@
-use std::fmt::Debug;
+use std::{collections::HashMap, fmt::Debug};
 
 use pcre2::bytes::Regex;
 
@@ -15,6 +15,34 @@ impl Pcre2PatternMatcher {
         let matcher = Self { regex: pcre2regex };
         Ok(matcher)
     }
+
+    // values of named capture groups, None if text doesn't match the pattern
+    pub fn named_captures(
+        &self,
+        text: &[u8],
+    ) -> Result<Option<HashMap<String, String>>, BErrorStd> {
+        let captures = match self.regex.captures(text)? {
+            Some(captures) => captures,
+            None => return Ok(None),
+        };
+
+        let named_captures = self
+            .regex
+            .capture_names()
+            .iter()
+            .flatten()
+            .filter_map(|name| {
+                captures.name(name).map(|value| {
+                    (
+                        name.clone(),
+                        String::from_utf8_lossy(value.as_bytes()).into_owned(),
+                    )
+                })
+            })
+            .collect();
+
+        Ok(Some(named_captures))
+    }
 }
 
 impl PatternMatcher for Pcre2PatternMatcher {
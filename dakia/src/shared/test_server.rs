@@ -0,0 +1,91 @@
+use std::sync::Arc;
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+pub struct TestRequest {
+    // request line and headers
+    pub head: String,
+    pub body: Vec<u8>,
+}
+
+impl TestRequest {
+    pub fn path(&self) -> &str {
+        self.head.split(' ').nth(1).unwrap_or("/")
+    }
+
+    pub fn header(&self, header_name: &str) -> Option<&str> {
+        self.head.lines().skip(1).find_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            name.eq_ignore_ascii_case(header_name)
+                .then_some(value.trim())
+        })
+    }
+}
+
+type Handler = dyn Fn(&TestRequest) -> (u16, String) + Send + Sync;
+
+async fn read_request(stream: &mut TcpStream, buffer: &mut Vec<u8>) -> Option<TestRequest> {
+    let mut chunk = [0u8; 1024];
+    let head_end = loop {
+        if let Some(head_end) = buffer.windows(4).position(|window| window == b"\r\n\r\n") {
+            break head_end;
+        }
+        let n = stream.read(&mut chunk).await.ok()?;
+        if n == 0 {
+            return None;
+        }
+        buffer.extend_from_slice(&chunk[..n]);
+    };
+
+    let head = String::from_utf8_lossy(&buffer[..head_end]).to_string();
+    let mut request = TestRequest { head, body: vec![] };
+    let content_length: usize = request
+        .header("content-length")
+        .and_then(|content_length| content_length.parse().ok())
+        .unwrap_or(0);
+
+    buffer.drain(..head_end + 4);
+    while buffer.len() < content_length {
+        let n = stream.read(&mut chunk).await.ok()?;
+        if n == 0 {
+            return None;
+        }
+        buffer.extend_from_slice(&chunk[..n]);
+    }
+    request.body = buffer.drain(..content_length).collect();
+    Some(request)
+}
+
+// serves json responses of handler on a local port, connections are kept alive
+pub async fn serve(
+    handler: impl Fn(&TestRequest) -> (u16, String) + Send + Sync + 'static,
+) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = format!("http://{}", listener.local_addr().unwrap());
+    let handler: Arc<Handler> = Arc::new(handler);
+
+    tokio::spawn(async move {
+        loop {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let handler = handler.clone();
+            tokio::spawn(async move {
+                let mut buffer = vec![];
+                while let Some(request) = read_request(&mut stream, &mut buffer).await {
+                    let (status, body) = handler(&request);
+                    let response = format!(
+                        "HTTP/1.1 {status} OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\n\r\n{body}",
+                        body.len()
+                    );
+                    if stream.write_all(response.as_bytes()).await.is_err() {
+                        return;
+                    }
+                }
+            });
+        }
+    });
+
+    address
+}